@@ -1,13 +1,39 @@
-use crate::utils::gazette::Gazette;
+use crate::geocoder::cache::CachedGeocode;
+use crate::utils::announcements::Announcement;
+use crate::utils::auth::{Account, User};
+use crate::utils::gazette::{make_hash, Gazette};
 use anyhow::Result;
+use std::collections::HashMap;
 
 #[async_trait::async_trait]
 pub trait DatabaseProvider {
     type DBResult;
     async fn connect() -> Result<Self::DBResult>;
+    // Notices are kept by the hash of their address, as flagged or discarded but never both
     async fn has_entry(&self, id: &str) -> Result<bool>;
-    async fn create_entry(&self, id: &str, value: &Gazette) -> Result<bool>;
+    async fn save_gazette(&self, id: &str, gazette: &Gazette) -> Result<bool>;
     async fn fetch_entries(&self) -> Result<Vec<Gazette>>;
+    async fn fetch_entry(&self, id: &str) -> Result<Option<Gazette>>;
+    async fn fetch_geocode(&self, key: &str) -> Result<Option<CachedGeocode>>;
+    // Without a TTL the entry is kept until it's replaced
+    async fn store_geocode(
+        &self,
+        key: &str,
+        value: &CachedGeocode,
+        ttl_seconds: Option<u64>,
+    ) -> Result<bool>;
+    // Running totals of calls to each external API, by day as YYYY-MM-DD
+    async fn record_api_calls(&self, day: &str, api: &str, calls: u64) -> Result<u64>;
+    async fn fetch_api_calls(&self, day: &str) -> Result<HashMap<String, u64>>;
+    async fn fetch_account(&self, name: &str) -> Result<Option<Account>>;
+    async fn store_account(&self, account: &Account) -> Result<bool>;
+    async fn delete_account(&self, name: &str) -> Result<bool>;
+    // Sessions always expire, after `ttl_seconds`
+    async fn store_session(&self, id: &str, user: &User, ttl_seconds: u64) -> Result<bool>;
+    async fn fetch_session(&self, id: &str) -> Result<Option<User>>;
+    async fn delete_session(&self, id: &str) -> Result<bool>;
+    async fn store_announcement(&self, id: &str, announcement: &Announcement) -> Result<bool>;
+    async fn fetch_announcements(&self) -> Result<Vec<Announcement>>;
 }
 
 pub struct DatabaseConnection<T>
@@ -25,13 +51,71 @@ where
         T::has_entry(&self.provider, id).await
     }
 
-    pub async fn create_entry(&self, id: &str, value: &Gazette) -> Result<bool> {
-        T::create_entry(&self.provider, id, value).await
+    // Discarding a notice is saving it with `flagged` unset
+    pub async fn save_gazette(&self, gazette: &Gazette) -> Result<bool> {
+        T::save_gazette(&self.provider, &make_hash(&gazette.uri), gazette).await
     }
 
     pub async fn fetch_entries(&self) -> Result<Vec<Gazette>> {
         T::fetch_entries(&self.provider).await
     }
+
+    pub async fn fetch_entry(&self, id: &str) -> Result<Option<Gazette>> {
+        T::fetch_entry(&self.provider, id).await
+    }
+
+    pub async fn fetch_geocode(&self, key: &str) -> Result<Option<CachedGeocode>> {
+        T::fetch_geocode(&self.provider, key).await
+    }
+
+    pub async fn store_geocode(
+        &self,
+        key: &str,
+        value: &CachedGeocode,
+        ttl_seconds: Option<u64>,
+    ) -> Result<bool> {
+        T::store_geocode(&self.provider, key, value, ttl_seconds).await
+    }
+
+    pub async fn record_api_calls(&self, day: &str, api: &str, calls: u64) -> Result<u64> {
+        T::record_api_calls(&self.provider, day, api, calls).await
+    }
+
+    pub async fn fetch_api_calls(&self, day: &str) -> Result<HashMap<String, u64>> {
+        T::fetch_api_calls(&self.provider, day).await
+    }
+
+    pub async fn fetch_account(&self, name: &str) -> Result<Option<Account>> {
+        T::fetch_account(&self.provider, name).await
+    }
+
+    pub async fn store_account(&self, account: &Account) -> Result<bool> {
+        T::store_account(&self.provider, account).await
+    }
+
+    pub async fn delete_account(&self, name: &str) -> Result<bool> {
+        T::delete_account(&self.provider, name).await
+    }
+
+    pub async fn store_session(&self, id: &str, user: &User, ttl_seconds: u64) -> Result<bool> {
+        T::store_session(&self.provider, id, user, ttl_seconds).await
+    }
+
+    pub async fn fetch_session(&self, id: &str) -> Result<Option<User>> {
+        T::fetch_session(&self.provider, id).await
+    }
+
+    pub async fn delete_session(&self, id: &str) -> Result<bool> {
+        T::delete_session(&self.provider, id).await
+    }
+
+    pub async fn store_announcement(&self, id: &str, announcement: &Announcement) -> Result<bool> {
+        T::store_announcement(&self.provider, id, announcement).await
+    }
+
+    pub async fn fetch_announcements(&self) -> Result<Vec<Announcement>> {
+        T::fetch_announcements(&self.provider).await
+    }
 }
 
 #[cfg(test)]
@@ -44,11 +128,17 @@ mod test {
         let provider = MockDatabaseProvider::new();
         let connection = DatabaseConnection { provider };
 
-        let gazette = Gazette::default();
-        let id = "test123";
+        let gazette = Gazette {
+            uri: "http://www.gazette.vic.gov.au/gazette/Gazettes2025/GG2025S737.pdf".to_string(),
+            flagged: true,
+            ..Default::default()
+        };
 
-        assert!(connection.create_entry(id, &gazette).await.unwrap());
-        assert!(connection.has_entry(id).await.unwrap());
+        assert!(connection.save_gazette(&gazette).await.unwrap());
+        assert!(connection
+            .has_entry(&make_hash(&gazette.uri))
+            .await
+            .unwrap());
 
         let entries = connection.fetch_entries().await.unwrap();
         assert_eq!(entries.len(), 1);
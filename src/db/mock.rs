@@ -1,3 +1,6 @@
+use crate::geocoder::cache::CachedGeocode;
+use crate::utils::announcements::Announcement;
+use crate::utils::auth::{Account, User};
 use crate::{db::DatabaseProvider, utils::gazette::Gazette};
 use anyhow::Result;
 use std::collections::HashMap;
@@ -7,12 +10,22 @@ use tokio::sync::RwLock;
 #[derive(Clone)]
 pub struct MockDatabaseProvider {
     storage: Arc<RwLock<HashMap<String, Gazette>>>,
+    geocodes: Arc<RwLock<HashMap<String, CachedGeocode>>>,
+    api_calls: Arc<RwLock<HashMap<String, HashMap<String, u64>>>>,
+    accounts: Arc<RwLock<HashMap<String, Account>>>,
+    sessions: Arc<RwLock<HashMap<String, User>>>,
+    announcements: Arc<RwLock<HashMap<String, Announcement>>>,
 }
 
 impl MockDatabaseProvider {
     pub fn new() -> Self {
         Self {
             storage: Arc::new(RwLock::new(HashMap::new())),
+            geocodes: Arc::new(RwLock::new(HashMap::new())),
+            api_calls: Arc::new(RwLock::new(HashMap::new())),
+            accounts: Arc::new(RwLock::new(HashMap::new())),
+            sessions: Arc::new(RwLock::new(HashMap::new())),
+            announcements: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 }
@@ -27,18 +40,112 @@ impl DatabaseProvider for MockDatabaseProvider {
 
     async fn has_entry(&self, id: &str) -> Result<bool> {
         let storage = self.storage.read().await;
-        Ok(storage.contains_key(id))
+        Ok(storage.contains_key(&format!("flagged:{id}"))
+            || storage.contains_key(&format!("discarded:{id}")))
     }
 
-    async fn create_entry(&self, id: &str, value: &Gazette) -> Result<bool> {
+    async fn save_gazette(&self, id: &str, gazette: &Gazette) -> Result<bool> {
+        let (prefix, other_prefix) = if gazette.flagged {
+            ("flagged:", "discarded:")
+        } else {
+            ("discarded:", "flagged:")
+        };
+
         let mut storage = self.storage.write().await;
-        storage.insert(id.to_string(), value.clone());
+        storage.insert(format!("{prefix}{id}"), gazette.clone());
+        storage.remove(&format!("{other_prefix}{id}"));
         Ok(true)
     }
 
     async fn fetch_entries(&self) -> Result<Vec<Gazette>> {
         let storage = self.storage.read().await;
-        Ok(storage.values().cloned().collect())
+        Ok(storage
+            .iter()
+            .filter(|(key, _)| key.starts_with("flagged:"))
+            .map(|(_, gazette)| gazette.clone())
+            .collect())
+    }
+
+    async fn fetch_entry(&self, id: &str) -> Result<Option<Gazette>> {
+        let storage = self.storage.read().await;
+        Ok(storage.get(&format!("flagged:{id}")).cloned())
+    }
+
+    async fn fetch_geocode(&self, key: &str) -> Result<Option<CachedGeocode>> {
+        let geocodes = self.geocodes.read().await;
+        Ok(geocodes.get(key).cloned())
+    }
+
+    // Expiry is left to the caller, which checks `cached_at` anyway
+    async fn store_geocode(
+        &self,
+        key: &str,
+        value: &CachedGeocode,
+        _ttl_seconds: Option<u64>,
+    ) -> Result<bool> {
+        let mut geocodes = self.geocodes.write().await;
+        geocodes.insert(key.to_string(), value.clone());
+        Ok(true)
+    }
+
+    async fn record_api_calls(&self, day: &str, api: &str, calls: u64) -> Result<u64> {
+        let mut api_calls = self.api_calls.write().await;
+        let total = api_calls
+            .entry(day.to_string())
+            .or_default()
+            .entry(api.to_string())
+            .or_default();
+        *total += calls;
+        Ok(*total)
+    }
+
+    async fn fetch_api_calls(&self, day: &str) -> Result<HashMap<String, u64>> {
+        let api_calls = self.api_calls.read().await;
+        Ok(api_calls.get(day).cloned().unwrap_or_default())
+    }
+
+    async fn fetch_account(&self, name: &str) -> Result<Option<Account>> {
+        let accounts = self.accounts.read().await;
+        Ok(accounts.get(name).cloned())
+    }
+
+    async fn store_account(&self, account: &Account) -> Result<bool> {
+        let mut accounts = self.accounts.write().await;
+        accounts.insert(account.name.clone(), account.clone());
+        Ok(true)
+    }
+
+    async fn delete_account(&self, name: &str) -> Result<bool> {
+        let mut accounts = self.accounts.write().await;
+        Ok(accounts.remove(name).is_some())
+    }
+
+    // Sessions never expire here
+    async fn store_session(&self, id: &str, user: &User, _ttl_seconds: u64) -> Result<bool> {
+        let mut sessions = self.sessions.write().await;
+        sessions.insert(id.to_string(), user.clone());
+        Ok(true)
+    }
+
+    async fn fetch_session(&self, id: &str) -> Result<Option<User>> {
+        let sessions = self.sessions.read().await;
+        Ok(sessions.get(id).cloned())
+    }
+
+    async fn delete_session(&self, id: &str) -> Result<bool> {
+        let mut sessions = self.sessions.write().await;
+        Ok(sessions.remove(id).is_some())
+    }
+
+    async fn store_announcement(&self, id: &str, announcement: &Announcement) -> Result<bool> {
+        let mut announcements = self.announcements.write().await;
+        announcements.insert(id.to_string(), announcement.clone());
+        Ok(true)
+    }
+
+    async fn fetch_announcements(&self) -> Result<Vec<Announcement>> {
+        let announcements = self.announcements.read().await;
+        Ok(announcements.values().cloned().collect())
     }
 }
 
@@ -49,15 +156,29 @@ mod tests {
     #[tokio::test]
     async fn test_mock_database() {
         let db = MockDatabaseProvider::new();
-        let gazette = Gazette::default();
+        let gazette = Gazette {
+            flagged: true,
+            ..Default::default()
+        };
 
-        let result = db.create_entry("test_id", &gazette).await.unwrap();
+        let result = db.save_gazette("test_id", &gazette).await.unwrap();
         assert!(result);
 
         assert!(db.has_entry("test_id").await.unwrap());
         assert!(!db.has_entry("non_existent").await.unwrap());
+        assert!(db.fetch_entry("test_id").await.unwrap().is_some());
 
         let entries = db.fetch_entries().await.unwrap();
         assert_eq!(entries.len(), 1);
+
+        // Discarded notices are still known, but no longer listed
+        let discarded = Gazette {
+            flagged: false,
+            ..gazette
+        };
+        db.save_gazette("test_id", &discarded).await.unwrap();
+        assert!(db.has_entry("test_id").await.unwrap());
+        assert!(db.fetch_entry("test_id").await.unwrap().is_none());
+        assert!(db.fetch_entries().await.unwrap().is_empty());
     }
 }
@@ -1,21 +1,26 @@
 use crate::db::DatabaseProvider;
+use crate::geocoder::cache::CachedGeocode;
+use crate::utils::announcements::Announcement;
+use crate::utils::auth::{Account, User};
 use crate::utils::gazette::Gazette;
 use anyhow::{anyhow, Result};
 use redis::{Connection, TypedCommands};
 use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::HashMap;
 
-#[derive(Clone, Default, Serialize)]
+#[derive(Clone, Copy, Default, Serialize)]
 pub struct RedisProvider;
 
 impl RedisProvider {
     const FLAGGED_PREFIX: &'static str = "flagged:";
     const DISCARDED_PREFIX: &'static str = "discarded:";
+    const API_CALLS_PREFIX: &'static str = "api_calls:";
+    const ACCOUNT_PREFIX: &'static str = "account:";
+    const SESSION_PREFIX: &'static str = "session:";
+    const ANNOUNCEMENT_PREFIX: &'static str = "announcement:";
 
-    pub fn check_key_exists(
-        connection: &mut Connection,
-        prefix: &str,
-        id: &str,
-    ) -> Result<bool> {
+    pub fn check_key_exists(connection: &mut Connection, prefix: &str, id: &str) -> Result<bool> {
         connection
             .exists::<String>(format!("{prefix}{id}"))
             .map_err(|e| anyhow!("Failed to check Redis key: {}", e))
@@ -43,10 +48,8 @@ impl DatabaseProvider for RedisProvider {
             .await
             .map_err(|e| anyhow!("Failed to connect to Redis: {}", e))?;
 
-        let flagged_exists =
-            Self::check_key_exists(&mut connection, Self::FLAGGED_PREFIX, id)?;
-        let discarded_exists =
-            Self::check_key_exists(&mut connection, Self::DISCARDED_PREFIX, id)?;
+        let flagged_exists = Self::check_key_exists(&mut connection, Self::FLAGGED_PREFIX, id)?;
+        let discarded_exists = Self::check_key_exists(&mut connection, Self::DISCARDED_PREFIX, id)?;
 
         let exists = flagged_exists || discarded_exists;
 
@@ -62,12 +65,17 @@ impl DatabaseProvider for RedisProvider {
         Ok(exists)
     }
 
-    async fn create_entry(&self, id: &str, value: &Gazette) -> Result<bool> {
-        if let Ok(mut connection) = Self::connect().await {
-            connection.set(id, value)?;
-            return Ok(true);
-        }
-        Err(anyhow!("Could not create entry"))
+    async fn save_gazette(&self, id: &str, gazette: &Gazette) -> Result<bool> {
+        let (prefix, other_prefix) = if gazette.flagged {
+            (Self::FLAGGED_PREFIX, Self::DISCARDED_PREFIX)
+        } else {
+            (Self::DISCARDED_PREFIX, Self::FLAGGED_PREFIX)
+        };
+
+        let mut connection = Self::connect().await?;
+        connection.set(format!("{prefix}{id}"), gazette)?;
+        connection.del(format!("{other_prefix}{id}"))?;
+        Ok(true)
     }
 
     async fn fetch_entries(&self) -> Result<Vec<Gazette>> {
@@ -77,7 +85,10 @@ impl DatabaseProvider for RedisProvider {
 
         if let Ok(mut connection) = Self::connect().await {
             let mut gazettes: Vec<Gazette> = vec![];
-            if let Ok(keys) = Commands::keys::<&str, Vec<String>>(&mut connection, "flagged:*") {
+            if let Ok(keys) = Commands::keys::<String, Vec<String>>(
+                &mut connection,
+                format!("{}*", Self::FLAGGED_PREFIX),
+            ) {
                 let _ = keys
                     .into_iter()
                     .map(|key| {
@@ -97,4 +108,123 @@ impl DatabaseProvider for RedisProvider {
 
         Err(anyhow!("Could not fetch entries"))
     }
+
+    async fn fetch_geocode(&self, key: &str) -> Result<Option<CachedGeocode>> {
+        use redis::Commands;
+
+        let mut connection = Self::connect().await?;
+        Commands::get::<&str, Option<CachedGeocode>>(&mut connection, key).map_err(Into::into)
+    }
+
+    async fn store_geocode(
+        &self,
+        key: &str,
+        value: &CachedGeocode,
+        ttl_seconds: Option<u64>,
+    ) -> Result<bool> {
+        let mut connection = Self::connect().await?;
+
+        match ttl_seconds {
+            Some(seconds) => connection.set_ex(key, value, seconds)?,
+            None => connection.set(key, value)?,
+        }
+        Ok(true)
+    }
+
+    async fn record_api_calls(&self, day: &str, api: &str, calls: u64) -> Result<u64> {
+        use redis::Commands;
+
+        let mut connection = Self::connect().await?;
+        Commands::hincr(
+            &mut connection,
+            format!("{}{day}", Self::API_CALLS_PREFIX),
+            api,
+            calls,
+        )
+        .map_err(Into::into)
+    }
+
+    async fn fetch_api_calls(&self, day: &str) -> Result<HashMap<String, u64>> {
+        use redis::Commands;
+
+        let mut connection = Self::connect().await?;
+        Commands::hgetall(&mut connection, format!("{}{day}", Self::API_CALLS_PREFIX))
+            .map_err(Into::into)
+    }
+
+    async fn fetch_entry(&self, id: &str) -> Result<Option<Gazette>> {
+        use redis::Commands;
+
+        let mut connection = Self::connect().await?;
+        Commands::get::<String, Option<Gazette>>(
+            &mut connection,
+            format!("{}{id}", Self::FLAGGED_PREFIX),
+        )
+        .map_err(Into::into)
+    }
+
+    async fn fetch_account(&self, name: &str) -> Result<Option<Account>> {
+        use redis::Commands;
+
+        let mut connection = Self::connect().await?;
+        Commands::get::<String, Option<Account>>(
+            &mut connection,
+            format!("{}{name}", Self::ACCOUNT_PREFIX),
+        )
+        .map_err(Into::into)
+    }
+
+    async fn store_account(&self, account: &Account) -> Result<bool> {
+        let mut connection = Self::connect().await?;
+        connection.set(format!("{}{}", Self::ACCOUNT_PREFIX, account.name), account)?;
+        Ok(true)
+    }
+
+    async fn delete_account(&self, name: &str) -> Result<bool> {
+        let mut connection = Self::connect().await?;
+        Ok(connection.del(format!("{}{name}", Self::ACCOUNT_PREFIX))? > 0)
+    }
+
+    async fn store_session(&self, id: &str, user: &User, ttl_seconds: u64) -> Result<bool> {
+        let mut connection = Self::connect().await?;
+        connection.set_ex(format!("{}{id}", Self::SESSION_PREFIX), user, ttl_seconds)?;
+        Ok(true)
+    }
+
+    async fn fetch_session(&self, id: &str) -> Result<Option<User>> {
+        use redis::Commands;
+
+        let mut connection = Self::connect().await?;
+        Commands::get::<String, Option<User>>(
+            &mut connection,
+            format!("{}{id}", Self::SESSION_PREFIX),
+        )
+        .map_err(Into::into)
+    }
+
+    async fn delete_session(&self, id: &str) -> Result<bool> {
+        let mut connection = Self::connect().await?;
+        Ok(connection.del(format!("{}{id}", Self::SESSION_PREFIX))? > 0)
+    }
+
+    async fn store_announcement(&self, id: &str, announcement: &Announcement) -> Result<bool> {
+        let mut connection = Self::connect().await?;
+        connection.set(format!("{}{id}", Self::ANNOUNCEMENT_PREFIX), announcement)?;
+        Ok(true)
+    }
+
+    async fn fetch_announcements(&self) -> Result<Vec<Announcement>> {
+        use redis::Commands;
+
+        let mut connection = Self::connect().await?;
+        let keys: Vec<String> =
+            Commands::keys(&mut connection, format!("{}*", Self::ANNOUNCEMENT_PREFIX))?;
+        let mut announcements: Vec<Announcement> = keys
+            .iter()
+            .filter_map(|key| Commands::get::<&String, Announcement>(&mut connection, key).ok())
+            .collect();
+
+        announcements.sort_by_key(|announcement| Reverse(announcement.start));
+        Ok(announcements)
+    }
 }
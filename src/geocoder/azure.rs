@@ -1,9 +1,13 @@
-use crate::geocoder::GeocoderProvider;
+use crate::geocoder::{Area, GeocodeResult, GeocoderProvider, MatchType, Viewport};
+use crate::utils::http::{client, ApiLimiter};
 use crate::utils::maptypes::GeoPosition;
 use anyhow::{anyhow, Result};
-use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::env;
+use std::time::Duration;
+
+// Well under the search API's limit of 50 requests a second
+static LIMITER: ApiLimiter = ApiLimiter::new("azure", Duration::from_millis(50), 5);
 
 #[derive(Clone, Copy, Debug)]
 pub struct AzureGeocoderProvider;
@@ -15,10 +19,32 @@ pub struct AzureGeocoderPosition {
 }
 
 #[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AzureGeocoderViewport {
+    top_left_point: AzureGeocoderPosition,
+    btm_right_point: AzureGeocoderPosition,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AzureGeocoderAddress {
+    freeform_address: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct AzureGeocoderMatchConfidence {
+    score: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct AzureGeocoderResult {
     id: String,
     r#type: String,
     position: AzureGeocoderPosition,
+    address: Option<AzureGeocoderAddress>,
+    viewport: Option<AzureGeocoderViewport>,
+    match_confidence: Option<AzureGeocoderMatchConfidence>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -28,38 +54,86 @@ struct AzureGeocoderResponse {
 
 #[async_trait::async_trait]
 impl GeocoderProvider for AzureGeocoderProvider {
-    async fn geocode(&self, input: &str, area: &str) -> Result<GeoPosition> {
+    async fn geocode(&self, input: &str, area: &Area) -> Result<GeocodeResult> {
         if let Ok(api_key) = env::var("AZURE_API_KEY") {
-            let client = Client::new();
-            let input = format!("{input}, {area}, VICTORIA, AUSTRALIA");
-            let request = format!("https://atlas.microsoft.com/search/address/json?&subscription-key={api_key}&api-version=1.0&language=en-AU&countrySet=AU&query={input}");
-            let res = client.get(request).send().await?;
+            let input = format!("{input}, {}, VICTORIA, AUSTRALIA", area.name);
+            let mut request = format!("https://atlas.microsoft.com/search/address/json?&subscription-key={api_key}&api-version=1.0&language=en-AU&countrySet=AU&query={input}");
+            if let Some(bounds) = &area.bounds {
+                request.push_str(&format!(
+                    "&topLeft={},{}&btmRight={},{}",
+                    bounds.north_east.latitude,
+                    bounds.south_west.longitude,
+                    bounds.south_west.latitude,
+                    bounds.north_east.longitude
+                ));
+            }
+            let res = LIMITER.send(client().get(request)).await?;
 
             let body = res.json::<AzureGeocoderResponse>().await?;
             return body.into();
         }
         Err(anyhow!("Invalid Azure API key provided"))
     }
+
+    fn name(&self) -> String {
+        "azure".to_string()
+    }
 }
 
-impl From<AzureGeocoderResponse> for Result<GeoPosition> {
+impl From<AzureGeocoderResponse> for Result<GeocodeResult> {
     fn from(value: AzureGeocoderResponse) -> Self {
         // We are *likely* to want a cross-street value
         for result in &value.results {
             if result.r#type == "Cross Street" {
-                return Ok((&result.position).into());
+                return Ok(result.into());
             }
         }
 
         // If there is none, the first result is all we've got
         if let Some(result) = value.results.first() {
-            Ok((&result.position).into())
+            Ok(result.into())
         } else {
             Err(anyhow!("Geocoder returned no results"))
         }
     }
 }
 
+impl From<&AzureGeocoderResult> for GeocodeResult {
+    fn from(value: &AzureGeocoderResult) -> Self {
+        let match_type = match value.r#type.as_str() {
+            "Cross Street" => MatchType::Intersection,
+            "Street" | "Point Address" | "Address Range" => MatchType::Street,
+            "POI" => MatchType::Poi,
+            "Geography" => MatchType::Locality,
+            _ => MatchType::Unknown,
+        };
+
+        Self {
+            position: (&value.position).into(),
+            // Older API versions don't send a match confidence
+            confidence: value
+                .match_confidence
+                .as_ref()
+                .map_or(0.5, |confidence| confidence.score),
+            match_type,
+            formatted_address: value
+                .address
+                .as_ref()
+                .and_then(|address| address.freeform_address.clone()),
+            viewport: value.viewport.as_ref().map(|viewport| Viewport {
+                north_east: GeoPosition {
+                    latitude: viewport.top_left_point.lat,
+                    longitude: viewport.btm_right_point.lon,
+                },
+                south_west: GeoPosition {
+                    latitude: viewport.btm_right_point.lat,
+                    longitude: viewport.top_left_point.lon,
+                },
+            }),
+        }
+    }
+}
+
 impl From<&AzureGeocoderPosition> for GeoPosition {
     fn from(value: &AzureGeocoderPosition) -> Self {
         Self {
@@ -68,3 +142,36 @@ impl From<&AzureGeocoderPosition> for GeoPosition {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_azure_result() {
+        let body = r#"{"results": [
+            {"id": "1", "type": "Geography", "position": {"lat": -37.8136, "lon": 144.9631},
+             "matchConfidence": {"score": 0.4}},
+            {"id": "2", "type": "Cross Street", "position": {"lat": -37.8176, "lon": 144.9668},
+             "address": {"freeformAddress": "Flinders Street & Swanston Street, Melbourne VIC 3000"},
+             "viewport": {"topLeftPoint": {"lat": -37.816, "lon": 144.965},
+                          "btmRightPoint": {"lat": -37.819, "lon": 144.968}},
+             "matchConfidence": {"score": 0.9}}
+        ]}"#;
+        let response: AzureGeocoderResponse = serde_json::from_str(body).unwrap();
+
+        let suburb: GeocodeResult = (&response.results[0]).into();
+        assert!(suburb.is_locality());
+
+        let corner: Result<GeocodeResult> = response.into();
+        let corner = corner.unwrap();
+        assert_eq!(corner.match_type, MatchType::Intersection);
+        assert_eq!(corner.confidence, 0.9);
+        let viewport = corner.viewport.unwrap();
+        assert_eq!(viewport.north_east.latitude, -37.816);
+        assert_eq!(viewport.north_east.longitude, 144.968);
+        assert_eq!(viewport.south_west.latitude, -37.819);
+        assert_eq!(viewport.south_west.longitude, 144.965);
+    }
+}
@@ -0,0 +1,362 @@
+use crate::db::core::DatabaseProvider;
+use crate::db::DatabaseConnection;
+use crate::geocoder::{Area, GeocodeResult, GeocodedPoint, GeocoderProvider};
+use crate::utils::gazette::make_hash;
+use crate::utils::maptypes::GeoPosition;
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use redis_macros::{FromRedisValue, ToRedisArgs};
+use serde::{Deserialize, Serialize};
+use std::env;
+
+const DEFAULT_TTL_DAYS: u64 = 90;
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+// Places recur across notices, so any provider can be wrapped to remember what it found
+#[derive(Clone, Copy, Debug)]
+pub struct CachedGeocoderProvider<W, T>
+where
+    W: GeocoderProvider,
+    T: DatabaseProvider,
+{
+    pub provider: W,
+    pub database_provider: T,
+    pub ttl_seconds: u64,
+}
+
+// Manual entries are corrections made by hand; they never expire and the provider never replaces them
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, FromRedisValue, ToRedisArgs)]
+pub struct CachedGeocode {
+    pub input: String,
+    pub area: String,
+    #[serde(flatten)]
+    pub result: GeocodeResult,
+    #[serde(default)]
+    pub provider: String,
+    pub cached_at: i64,
+    pub manual: bool,
+}
+
+impl CachedGeocode {
+    fn is_fresh(&self, ttl_seconds: u64) -> bool {
+        let age = Utc::now().timestamp().saturating_sub(self.cached_at);
+        self.manual || u64::try_from(age).is_ok_and(|age| age < ttl_seconds)
+    }
+
+    fn to_point(&self, input: &str) -> GeocodedPoint {
+        GeocodedPoint {
+            input: input.to_string(),
+            result: self.result.clone(),
+            provider: self.provider.clone(),
+        }
+    }
+}
+
+// Case and spacing differences in the parser's output shouldn't miss the cache
+pub fn cache_key(input: &str, area: &str) -> String {
+    let normalise = |value: &str| {
+        value
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase()
+    };
+    format!(
+        "geocode:{}",
+        make_hash(&format!("{}|{}", normalise(input), normalise(area)))
+    )
+}
+
+pub fn ttl_from_env() -> u64 {
+    env::var("GEOCODE_CACHE_TTL_DAYS")
+        .ok()
+        .and_then(|days| days.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_TTL_DAYS)
+        * SECONDS_PER_DAY
+}
+
+impl<W, T> CachedGeocoderProvider<W, T>
+where
+    W: GeocoderProvider + Sync,
+    T: DatabaseProvider + Clone + Send + Sync,
+{
+    fn database(&self) -> DatabaseConnection<T> {
+        DatabaseConnection {
+            provider: self.database_provider.clone(),
+        }
+    }
+
+    // A cache we can't reach just means asking the provider; an entry outside the area is
+    // a wrong answer cached before the area was known, so it's asked again too
+    async fn cached(&self, input: &str, area: &Area) -> Option<GeocodedPoint> {
+        match self
+            .database()
+            .fetch_geocode(&cache_key(input, &area.name))
+            .await
+        {
+            Ok(Some(cached))
+                if cached.is_fresh(self.ttl_seconds)
+                    && (cached.manual || area.contains(&cached.result.position)) =>
+            {
+                Some(cached.to_point(input))
+            }
+            _ => None,
+        }
+    }
+
+    async fn remember(&self, point: &GeocodedPoint, area: &Area) {
+        let entry = CachedGeocode {
+            input: point.input.clone(),
+            area: area.name.clone(),
+            result: point.result.clone(),
+            provider: point.provider.clone(),
+            cached_at: Utc::now().timestamp(),
+            manual: false,
+        };
+        let _ = self
+            .database()
+            .store_geocode(
+                &cache_key(&point.input, &area.name),
+                &entry,
+                Some(self.ttl_seconds),
+            )
+            .await;
+    }
+}
+
+pub async fn override_geocode<T>(
+    database_provider: T,
+    input: &str,
+    area: &str,
+    position: GeoPosition,
+) -> Result<bool>
+where
+    T: DatabaseProvider,
+{
+    let db = DatabaseConnection {
+        provider: database_provider,
+    };
+    let entry = CachedGeocode {
+        input: input.to_string(),
+        area: area.to_string(),
+        result: GeocodeResult {
+            position,
+            confidence: 1.0,
+            ..Default::default()
+        },
+        provider: "manual".to_string(),
+        cached_at: Utc::now().timestamp(),
+        manual: true,
+    };
+
+    db.store_geocode(&cache_key(input, area), &entry, None)
+        .await
+}
+
+#[async_trait::async_trait]
+impl<W, T> GeocoderProvider for CachedGeocoderProvider<W, T>
+where
+    W: GeocoderProvider + Sync,
+    T: DatabaseProvider + Clone + Send + Sync,
+{
+    async fn geocode(&self, input: &str, area: &Area) -> Result<GeocodeResult> {
+        Ok(self.geocode_point(input, area).await?.result)
+    }
+
+    fn name(&self) -> String {
+        self.provider.name()
+    }
+
+    async fn geocode_point(&self, input: &str, area: &Area) -> Result<GeocodedPoint> {
+        if let Some(point) = self.cached(input, area).await {
+            return Ok(point);
+        }
+
+        let point = self.provider.geocode_point(input, area).await?;
+        self.remember(&point, area).await;
+        Ok(point)
+    }
+
+    // Only the places we haven't seen go to the provider, together so it can compare them
+    async fn geocode_all(&self, inputs: &[String], area: &Area) -> Vec<Result<GeocodedPoint>> {
+        let cached =
+            futures::future::join_all(inputs.iter().map(|input| self.cached(input, area))).await;
+        let misses: Vec<String> = inputs
+            .iter()
+            .zip(&cached)
+            .filter(|(_, cached)| cached.is_none())
+            .map(|(input, _)| input.clone())
+            .collect();
+
+        let mut found = self.provider.geocode_all(&misses, area).await.into_iter();
+        let mut results = Vec::with_capacity(inputs.len());
+        for hit in cached {
+            match hit {
+                Some(point) => results.push(Ok(point)),
+                None => {
+                    let result = found
+                        .next()
+                        .unwrap_or_else(|| Err(anyhow!("Geocoder skipped a location")));
+                    if let Ok(point) = &result {
+                        self.remember(point, area).await;
+                    }
+                    results.push(result);
+                }
+            }
+        }
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::mock::MockDatabaseProvider;
+    use crate::geocoder::mock::MockGeocoderProvider;
+    use crate::geocoder::Viewport;
+
+    fn cached_provider() -> CachedGeocoderProvider<MockGeocoderProvider, MockDatabaseProvider> {
+        CachedGeocoderProvider {
+            provider: MockGeocoderProvider,
+            database_provider: MockDatabaseProvider::new(),
+            ttl_seconds: SECONDS_PER_DAY,
+        }
+    }
+
+    fn entry(input: &str, latitude: f64, age_seconds: i64, manual: bool) -> CachedGeocode {
+        CachedGeocode {
+            input: input.to_string(),
+            area: "Melbourne".to_string(),
+            result: GeocodeResult {
+                position: GeoPosition {
+                    latitude,
+                    longitude: 0.0,
+                },
+                ..Default::default()
+            },
+            provider: "mock".to_string(),
+            cached_at: Utc::now().timestamp() - age_seconds,
+            manual,
+        }
+    }
+
+    #[test]
+    fn test_cache_key() {
+        assert_eq!(
+            cache_key("Southern Cross  Railway Station", "Melbourne CBD"),
+            cache_key("southern cross railway station", "MELBOURNE CBD")
+        );
+        assert_ne!(
+            cache_key("Tokyo", "Melbourne"),
+            cache_key("Tokyo", "Geelong")
+        );
+    }
+
+    #[tokio::test]
+    #[allow(clippy::float_cmp)]
+    async fn test_cached_geocoder() {
+        let geocoder = cached_provider();
+        let db = geocoder.database();
+        let melbourne = Area::named("Melbourne");
+
+        // Looked up once, then served from the cache
+        let tokyo = geocoder.geocode("Tokyo", &melbourne).await.unwrap();
+        assert_eq!(tokyo.position.latitude, 35.6762);
+        let cached = db
+            .fetch_geocode(&cache_key("Tokyo", "Melbourne"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(cached.result, tokyo);
+        assert!(!cached.manual);
+
+        let key = cache_key("London", "Melbourne");
+        db.store_geocode(&key, &entry("London", 1.0, 60, false), None)
+            .await
+            .unwrap();
+        assert_eq!(
+            geocoder
+                .geocode("London", &melbourne)
+                .await
+                .unwrap()
+                .position
+                .latitude,
+            1.0
+        );
+
+        // Expired entries are looked up again
+        db.store_geocode(&key, &entry("London", 1.0, 2 * 86_400, false), None)
+            .await
+            .unwrap();
+        assert_eq!(
+            geocoder
+                .geocode("London", &melbourne)
+                .await
+                .unwrap()
+                .position
+                .latitude,
+            51.5074
+        );
+
+        // Overrides win regardless of age
+        db.store_geocode(&key, &entry("London", 2.0, 365 * 86_400, true), None)
+            .await
+            .unwrap();
+        assert_eq!(
+            geocoder
+                .geocode("London", &melbourne)
+                .await
+                .unwrap()
+                .position
+                .latitude,
+            2.0
+        );
+
+        let position = GeoPosition {
+            latitude: -37.8,
+            longitude: 144.9,
+        };
+        assert!(override_geocode(
+            geocoder.database_provider.clone(),
+            "Tokyo",
+            "Melbourne",
+            position.clone()
+        )
+        .await
+        .unwrap());
+        let pinned = geocoder
+            .geocode("tokyo", &Area::named("melbourne"))
+            .await
+            .unwrap();
+        assert_eq!(pinned.position, position);
+        assert_eq!(pinned.confidence, 1.0);
+
+        // Cached answers outside the area are looked up again, but pins are trusted
+        let london = Area {
+            bounds: Some(Viewport::around(
+                &GeoPosition {
+                    latitude: 51.5,
+                    longitude: -0.1,
+                },
+                20.0,
+            )),
+            ..Area::named("Melbourne")
+        };
+        db.store_geocode(&key, &entry("London", 1.0, 60, false), None)
+            .await
+            .unwrap();
+        assert_eq!(
+            geocoder
+                .geocode("London", &london)
+                .await
+                .unwrap()
+                .position
+                .latitude,
+            51.5074
+        );
+        assert_eq!(
+            geocoder.geocode("Tokyo", &london).await.unwrap().position,
+            position
+        );
+    }
+}
@@ -0,0 +1,406 @@
+use crate::geocoder::azure::AzureGeocoderProvider;
+use crate::geocoder::gazetteer::GazetteerGeocoderProvider;
+use crate::geocoder::google::GoogleGeocoderProvider;
+use crate::geocoder::nominatim::NominatimGeocoderProvider;
+use crate::geocoder::{Area, GeocodeResult, GeocodedPoint, GeocoderProvider, Viewport};
+use crate::utils::maptypes::GeoPosition;
+use anyhow::{anyhow, Result};
+use std::env;
+use std::sync::Arc;
+
+// Operation areas are a suburb or two; anything further out is a different place with the same name
+const MAX_DISTANCE_FROM_AREA_KM: f64 = 10.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompositeStrategy {
+    // The first provider that finds a place wins
+    Fallback,
+    // Every provider is asked, and the answer that best agrees with the rest of the notice wins
+    Consensus,
+}
+
+#[derive(Clone)]
+pub struct CompositeGeocoderProvider {
+    pub providers: Vec<Arc<dyn GeocoderProvider>>,
+    pub strategy: CompositeStrategy,
+}
+
+impl CompositeGeocoderProvider {
+    // GEOCODER lists providers in order of preference, e.g. "gazetteer,nominatim,google"
+    pub fn from_env() -> Self {
+        let mut providers: Vec<Arc<dyn GeocoderProvider>> = env::var("GEOCODER")
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|name| provider_named(name.trim()))
+            .collect();
+        if providers.is_empty() {
+            providers.push(Arc::new(GoogleGeocoderProvider));
+        }
+
+        let strategy = match env::var("GEOCODER_STRATEGY").as_deref() {
+            Ok("consensus") => CompositeStrategy::Consensus,
+            _ => CompositeStrategy::Fallback,
+        };
+
+        Self {
+            providers,
+            strategy,
+        }
+    }
+
+    // A suburb centroid is only kept if no later provider can do better
+    async fn fallback(&self, input: &str, area: &Area) -> Result<GeocodedPoint> {
+        let mut errors = Vec::new();
+        let mut locality = None;
+
+        for provider in &self.providers {
+            match provider.geocode_point(input, area).await {
+                Ok(point) if point.result.is_locality() => {
+                    locality.get_or_insert(point);
+                }
+                Ok(point) => return Ok(point),
+                Err(e) => errors.push(format!("{}: {e}", provider.name())),
+            }
+        }
+
+        locality.ok_or(anyhow!("No geocoder found {input} ({})", errors.join(", ")))
+    }
+
+    async fn candidates(&self, input: &str, area: &Area) -> Vec<GeocodedPoint> {
+        let futures = self
+            .providers
+            .iter()
+            .map(|provider| provider.geocode_point(input, area));
+
+        futures::future::join_all(futures)
+            .await
+            .into_iter()
+            .filter_map(Result::ok)
+            .collect()
+    }
+
+    async fn consensus(&self, inputs: &[String], area: &Area) -> Vec<Result<GeocodedPoint>> {
+        let area = match area.bounds {
+            Some(_) => area.clone(),
+            None => self.locate_area(&area.name).await,
+        };
+        let candidates =
+            futures::future::join_all(inputs.iter().map(|input| self.candidates(input, &area)))
+                .await;
+
+        // Without the suburb itself, the middle of everything the providers found will do
+        let Some(reference) = area.bounds.as_ref().map(Viewport::centre).or_else(|| {
+            median(
+                candidates
+                    .iter()
+                    .flatten()
+                    .map(|point| &point.result.position),
+            )
+        }) else {
+            return inputs
+                .iter()
+                .map(|input| Err(anyhow!("No geocoder found {input}")))
+                .collect();
+        };
+
+        inputs
+            .iter()
+            .zip(candidates)
+            .map(|(input, candidates)| {
+                choose(candidates, &reference)
+                    .ok_or(anyhow!("No geocoder found {input} near {}", area.name))
+            })
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl GeocoderProvider for CompositeGeocoderProvider {
+    async fn geocode(&self, input: &str, area: &Area) -> Result<GeocodeResult> {
+        Ok(self.geocode_point(input, area).await?.result)
+    }
+
+    // Asking every provider where a suburb is would only be asking for disagreement
+    async fn locate_area(&self, name: &str) -> Area {
+        Area {
+            bounds: self
+                .fallback(name, &Area::named(name))
+                .await
+                .ok()
+                .map(|point| point.result.area_bounds()),
+            ..Area::named(name)
+        }
+    }
+
+    fn name(&self) -> String {
+        let names: Vec<String> = self
+            .providers
+            .iter()
+            .map(|provider| provider.name())
+            .collect();
+
+        match self.strategy {
+            CompositeStrategy::Fallback => format!("fallback({})", names.join(", ")),
+            CompositeStrategy::Consensus => format!("consensus({})", names.join(", ")),
+        }
+    }
+
+    async fn geocode_point(&self, input: &str, area: &Area) -> Result<GeocodedPoint> {
+        match self.strategy {
+            CompositeStrategy::Fallback => self.fallback(input, area).await,
+            CompositeStrategy::Consensus => self
+                .consensus(&[input.to_string()], area)
+                .await
+                .pop()
+                .unwrap_or_else(|| Err(anyhow!("No geocoder found {input}"))),
+        }
+    }
+
+    async fn geocode_all(&self, inputs: &[String], area: &Area) -> Vec<Result<GeocodedPoint>> {
+        match self.strategy {
+            CompositeStrategy::Fallback => {
+                futures::future::join_all(inputs.iter().map(|input| self.fallback(input, area)))
+                    .await
+            }
+            CompositeStrategy::Consensus => self.consensus(inputs, area).await,
+        }
+    }
+}
+
+fn provider_named(name: &str) -> Option<Arc<dyn GeocoderProvider>> {
+    match name {
+        "google" => Some(Arc::new(GoogleGeocoderProvider)),
+        "azure" => Some(Arc::new(AzureGeocoderProvider)),
+        "nominatim" => Some(Arc::new(NominatimGeocoderProvider)),
+        "gazetteer" => Some(Arc::new(GazetteerGeocoderProvider)),
+        _ => None,
+    }
+}
+
+// Prefers answers near the area that the other providers agree with, and anything over a suburb centroid
+#[allow(clippy::cast_precision_loss)]
+fn choose(candidates: Vec<GeocodedPoint>, reference: &GeoPosition) -> Option<GeocodedPoint> {
+    let score = |candidate: &GeocodedPoint| {
+        let others: Vec<f64> = candidates
+            .iter()
+            .filter(|other| other.provider != candidate.provider)
+            .map(|other| {
+                candidate
+                    .result
+                    .position
+                    .distance_to(&other.result.position)
+            })
+            .collect();
+        let disagreement = if others.is_empty() {
+            0.0
+        } else {
+            others.iter().sum::<f64>() / others.len() as f64
+        };
+
+        candidate.result.position.distance_to(reference) + disagreement
+    };
+
+    candidates
+        .iter()
+        .filter(|candidate| {
+            candidate.result.position.distance_to(reference) <= MAX_DISTANCE_FROM_AREA_KM
+        })
+        .min_by(|a, b| {
+            (a.result.is_locality(), score(a))
+                .partial_cmp(&(b.result.is_locality(), score(b)))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+        .cloned()
+}
+
+fn median<'a>(positions: impl Iterator<Item = &'a GeoPosition>) -> Option<GeoPosition> {
+    let (mut latitudes, mut longitudes): (Vec<f64>, Vec<f64>) = positions
+        .map(|position| (position.latitude, position.longitude))
+        .unzip();
+    if latitudes.is_empty() {
+        return None;
+    }
+
+    latitudes.sort_by(f64::total_cmp);
+    longitudes.sort_by(f64::total_cmp);
+    Some(GeoPosition {
+        latitude: latitudes[latitudes.len() / 2],
+        longitude: longitudes[longitudes.len() / 2],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geocoder::MatchType;
+    use std::collections::HashMap;
+
+    struct FixedGeocoder {
+        name: &'static str,
+        places: HashMap<&'static str, (f64, f64)>,
+        // Places this geocoder only knows as far as the suburb
+        localities: &'static [&'static str],
+    }
+
+    #[async_trait::async_trait]
+    impl GeocoderProvider for FixedGeocoder {
+        async fn geocode(&self, input: &str, _area: &Area) -> Result<GeocodeResult> {
+            self.places
+                .get(input)
+                .map(|(latitude, longitude)| GeocodeResult {
+                    position: GeoPosition {
+                        latitude: *latitude,
+                        longitude: *longitude,
+                    },
+                    match_type: if self.localities.contains(&input) {
+                        MatchType::Locality
+                    } else {
+                        MatchType::Street
+                    },
+                    ..Default::default()
+                })
+                .ok_or(anyhow!("Not found"))
+        }
+
+        fn name(&self) -> String {
+            self.name.to_string()
+        }
+    }
+
+    fn composite(strategy: CompositeStrategy) -> CompositeGeocoderProvider {
+        // "first" knows a Flinders Street in the wrong town and only the suburb for Melbourne
+        // Central; "second" doesn't know the station
+        let first = FixedGeocoder {
+            name: "first",
+            places: HashMap::from([
+                ("Melbourne", (-37.814, 144.963)),
+                (
+                    "Corner of Flinders and Swanston Streets",
+                    (-38.000, 145.505),
+                ),
+                ("Southern Cross Railway Station", (-37.818, 144.952)),
+                ("Melbourne Central", (-37.814, 144.963)),
+            ]),
+            localities: &["Melbourne", "Melbourne Central"],
+        };
+        let second = FixedGeocoder {
+            name: "second",
+            places: HashMap::from([
+                (
+                    "Corner of Flinders and Swanston Streets",
+                    (-37.817, 144.966),
+                ),
+                ("Melbourne Central", (-37.810, 144.963)),
+                ("Docklands", (-37.815, 144.946)),
+            ]),
+            localities: &["Docklands"],
+        };
+
+        CompositeGeocoderProvider {
+            providers: vec![Arc::new(first), Arc::new(second)],
+            strategy,
+        }
+    }
+
+    #[tokio::test]
+    #[allow(clippy::float_cmp)]
+    async fn test_fallback() {
+        let geocoder = composite(CompositeStrategy::Fallback);
+        assert_eq!(geocoder.name(), "fallback(first, second)");
+        let melbourne = Area::named("Melbourne");
+
+        let corner = geocoder
+            .geocode_point("Corner of Flinders and Swanston Streets", &melbourne)
+            .await
+            .unwrap();
+        assert_eq!(corner.provider, "first");
+        assert_eq!(corner.result.position.latitude, -38.0);
+
+        // Once the area has been found, the corner in the wrong town is passed over
+        let located = geocoder.locate_area("Melbourne").await;
+        assert!(located.bounds.is_some());
+        let corner = geocoder
+            .geocode_point("Corner of Flinders and Swanston Streets", &located)
+            .await
+            .unwrap();
+        assert_eq!(corner.provider, "second");
+
+        let centre = geocoder
+            .geocode_point("Melbourne Central", &melbourne)
+            .await
+            .unwrap();
+        assert_eq!(centre.provider, "second");
+        assert!(!centre.result.is_locality());
+
+        // A suburb is still better than nothing
+        let docklands = geocoder
+            .geocode_point("Docklands", &melbourne)
+            .await
+            .unwrap();
+        assert!(docklands.result.is_locality());
+
+        assert!(geocoder.geocode("Nowhere", &melbourne).await.is_err());
+    }
+
+    #[tokio::test]
+    #[allow(clippy::float_cmp)]
+    async fn test_consensus() {
+        let geocoder = composite(CompositeStrategy::Consensus);
+        let inputs = [
+            "Corner of Flinders and Swanston Streets".to_string(),
+            "Southern Cross Railway Station".to_string(),
+            "Nowhere".to_string(),
+        ];
+
+        let points = geocoder
+            .geocode_all(&inputs, &Area::named("Melbourne"))
+            .await;
+        let corner = points[0].as_ref().unwrap();
+        assert_eq!(corner.provider, "second");
+        assert_eq!(corner.result.position.latitude, -37.817);
+        assert_eq!(points[1].as_ref().unwrap().provider, "first");
+        assert!(points[2].is_err());
+
+        // Nothing near the area is better than something in the wrong town
+        let lone = CompositeGeocoderProvider {
+            providers: vec![geocoder.providers[0].clone()],
+            strategy: CompositeStrategy::Consensus,
+        };
+        assert!(lone
+            .geocode(
+                "Corner of Flinders and Swanston Streets",
+                &Area::named("Melbourne")
+            )
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_provider_named() {
+        assert_eq!(provider_named("gazetteer").unwrap().name(), "gazetteer");
+        assert_eq!(provider_named("azure").unwrap().name(), "azure");
+        assert!(provider_named("bing").is_none());
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_median() {
+        let positions = [
+            GeoPosition {
+                latitude: -37.0,
+                longitude: 144.0,
+            },
+            GeoPosition {
+                latitude: -38.0,
+                longitude: 145.0,
+            },
+            GeoPosition {
+                latitude: 10.0,
+                longitude: 0.0,
+            },
+        ];
+        let middle = median(positions.iter()).unwrap();
+        assert_eq!((middle.latitude, middle.longitude), (-37.0, 144.0));
+        assert!(median(std::iter::empty()).is_none());
+    }
+}
@@ -1,27 +1,188 @@
 use crate::utils::maptypes::GeoPosition;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+// Locations on a suburb's edge are often the streets bounding it
+const AREA_MARGIN_KM: f64 = 1.0;
+// For providers that place an area without saying how big it is
+const DEFAULT_AREA_RADIUS_KM: f64 = 5.0;
+
+// How specific a match is; a suburb centroid for a street corner drags the polygon off somewhere else
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchType {
+    Intersection,
+    Street,
+    Poi,
+    Locality,
+    #[default]
+    Unknown,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Viewport {
+    pub north_east: GeoPosition,
+    pub south_west: GeoPosition,
+}
+
+impl Viewport {
+    pub fn around(position: &GeoPosition, radius_km: f64) -> Self {
+        Self {
+            north_east: position.clone(),
+            south_west: position.clone(),
+        }
+        .padded(radius_km)
+    }
+
+    pub fn padded(&self, margin_km: f64) -> Self {
+        let latitude = margin_km / 110.574;
+        let longitude = margin_km / (111.32 * self.centre().latitude.to_radians().cos());
+
+        Self {
+            north_east: GeoPosition {
+                latitude: self.north_east.latitude + latitude,
+                longitude: self.north_east.longitude + longitude,
+            },
+            south_west: GeoPosition {
+                latitude: self.south_west.latitude - latitude,
+                longitude: self.south_west.longitude - longitude,
+            },
+        }
+    }
+
+    pub fn centre(&self) -> GeoPosition {
+        GeoPosition {
+            latitude: (self.north_east.latitude + self.south_west.latitude) / 2.0,
+            longitude: (self.north_east.longitude + self.south_west.longitude) / 2.0,
+        }
+    }
+
+    pub fn contains(&self, position: &GeoPosition) -> bool {
+        (self.south_west.latitude..=self.north_east.latitude).contains(&position.latitude)
+            && (self.south_west.longitude..=self.north_east.longitude).contains(&position.longitude)
+    }
+}
+
+// The operation area a notice names, and once it has been found, the box its locations must fall in
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Area {
+    pub name: String,
+    pub bounds: Option<Viewport>,
+}
+
+impl Area {
+    pub fn named(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            bounds: None,
+        }
+    }
+
+    // An area we couldn't find doesn't rule anything out
+    pub fn contains(&self, position: &GeoPosition) -> bool {
+        self.bounds
+            .as_ref()
+            .is_none_or(|bounds| bounds.contains(position))
+    }
+}
+
+// Confidence runs from 0 to 1, as near as each provider's own measure can be mapped onto that
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GeocodeResult {
+    pub position: GeoPosition,
+    pub confidence: f64,
+    pub match_type: MatchType,
+    pub formatted_address: Option<String>,
+    pub viewport: Option<Viewport>,
+}
+
+impl GeocodeResult {
+    pub fn is_locality(&self) -> bool {
+        self.match_type == MatchType::Locality
+    }
+
+    // The box to search for an area's locations in, taking this as where the area is
+    pub fn area_bounds(&self) -> Viewport {
+        match &self.viewport {
+            Some(viewport) => viewport.padded(AREA_MARGIN_KM),
+            None => Viewport::around(&self.position, DEFAULT_AREA_RADIUS_KM),
+        }
+    }
+}
+
+// A located place along with the provider that found it
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GeocodedPoint {
+    pub input: String,
+    #[serde(flatten)]
+    pub result: GeocodeResult,
+    pub provider: String,
+}
 
 #[async_trait::async_trait]
-pub trait GeocoderProvider {
-    async fn geocode(&self, input: &str, area: &str) -> Result<GeoPosition>;
+pub trait GeocoderProvider: Send + Sync {
+    // Providers that can restrict a search to a box are given the area's bounds to do so
+    async fn geocode(&self, input: &str, area: &Area) -> Result<GeocodeResult>;
+    fn name(&self) -> String;
+
+    async fn locate_area(&self, name: &str) -> Area {
+        Area {
+            bounds: self
+                .geocode(name, &Area::named(name))
+                .await
+                .ok()
+                .map(|result| result.area_bounds()),
+            ..Area::named(name)
+        }
+    }
+
+    // Providers that choose between others override this to say which one answered
+    async fn geocode_point(&self, input: &str, area: &Area) -> Result<GeocodedPoint> {
+        let result = self.geocode(input, area).await?;
+        if !area.contains(&result.position) {
+            return Err(anyhow!("{input} was found outside {}", area.name));
+        }
+
+        Ok(GeocodedPoint {
+            input: input.to_string(),
+            result,
+            provider: self.name(),
+        })
+    }
+
+    // Locations in a notice are near each other, so providers can judge each one against the rest
+    async fn geocode_all(&self, inputs: &[String], area: &Area) -> Vec<Result<GeocodedPoint>> {
+        let futures = inputs.iter().map(|input| self.geocode_point(input, area));
+        futures::future::join_all(futures).await
+    }
 }
 
 #[derive(Clone, Debug)]
-pub struct GeocoderRequest<T>
+pub struct GeocoderBatchRequest<T>
 where
     T: GeocoderProvider,
 {
-    pub input: String,
+    pub inputs: Vec<String>,
     pub area: String,
     pub service: T,
 }
 
-impl<T> GeocoderRequest<T>
+impl<T> GeocoderBatchRequest<T>
 where
     T: GeocoderProvider,
 {
-    pub async fn geocode(&self) -> Result<GeoPosition> {
-        self.service.geocode(&self.input, &self.area).await
+    // Places that couldn't be found, or were found outside the area, are left out rather than
+    // dropped at (0, 0) or in a same-named street across the state
+    pub async fn geocode(&self) -> Vec<GeocodedPoint> {
+        let area = self.service.locate_area(&self.area).await;
+
+        self.service
+            .geocode_all(&self.inputs, &area)
+            .await
+            .into_iter()
+            .filter_map(Result::ok)
+            .collect()
     }
 }
 
@@ -36,14 +197,89 @@ mod test {
         let geocoder = MockGeocoderProvider {};
 
         // Test with a known location
-        let request = GeocoderRequest {
+        let result = geocoder
+            .geocode("Tokyo", &Area::named("Japan"))
+            .await
+            .unwrap();
+        assert_eq!(result.position.latitude, 35.6762);
+        assert_eq!(result.position.longitude, 139.6503);
+    }
+
+    #[tokio::test]
+    async fn test_geocoder_batch_request() {
+        let request = GeocoderBatchRequest {
+            inputs: vec!["Tokyo".to_string(), "London".to_string()],
+            area: "Tokyo".to_string(),
+            service: MockGeocoderProvider,
+        };
+
+        // London is nowhere near the area
+        let points = request.geocode().await;
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0].input, "Tokyo");
+        assert_eq!(points[0].provider, "mock");
+    }
+
+    #[test]
+    fn test_area_bounds() {
+        let melbourne = GeoPosition {
+            latitude: -37.8136,
+            longitude: 144.9631,
+        };
+        let footscray = GeoPosition {
+            latitude: -37.8,
+            longitude: 144.9,
+        };
+
+        let area = Area {
+            bounds: Some(Viewport::around(&melbourne, 2.0)),
+            ..Area::named("Melbourne")
+        };
+        assert!(area.contains(&melbourne));
+        assert!(!area.contains(&footscray));
+        assert!(Area::named("Melbourne").contains(&footscray));
+
+        let result = GeocodeResult {
+            position: melbourne.clone(),
+            viewport: Some(Viewport {
+                north_east: GeoPosition {
+                    latitude: -37.80,
+                    longitude: 144.98,
+                },
+                south_west: GeoPosition {
+                    latitude: -37.82,
+                    longitude: 144.95,
+                },
+            }),
+            ..Default::default()
+        };
+        let bounds = result.area_bounds();
+        assert!(bounds.north_east.latitude > -37.80);
+        assert!(bounds.south_west.longitude < 144.95);
+        assert!(!bounds.contains(&footscray));
+    }
+
+    #[test]
+    fn test_geocoded_point_serialisation() {
+        let point = GeocodedPoint {
             input: "Tokyo".to_string(),
-            area: "Japan".to_string(),
-            service: geocoder,
+            result: GeocodeResult {
+                match_type: MatchType::Locality,
+                ..Default::default()
+            },
+            provider: "mock".to_string(),
         };
 
-        let position = request.geocode().await.unwrap();
-        assert_eq!(position.latitude, 35.6762);
-        assert_eq!(position.longitude, 139.6503);
+        let json = serde_json::to_value(&point).unwrap();
+        assert_eq!(json["match_type"], "locality");
+        assert!(json["position"].is_object());
+
+        // Points saved before results carried a match type still load
+        let old: GeocodedPoint = serde_json::from_str(
+            r#"{"input": "Tokyo", "position": {"latitude": 1.0, "longitude": 2.0}, "provider": "mock"}"#,
+        )
+        .unwrap();
+        assert_eq!(old.result.match_type, MatchType::Unknown);
+        assert!(!old.result.is_locality());
     }
 }
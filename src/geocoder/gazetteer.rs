@@ -0,0 +1,719 @@
+use crate::geocoder::{Area, GeocodeResult, GeocoderProvider, MatchType};
+use crate::utils::maptypes::GeoPosition;
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::env;
+use tokio::sync::OnceCell;
+
+// Streets that meet at a T-junction don't always share a node in the source data
+const JUNCTION_TOLERANCE_KM: f64 = 0.05;
+
+// Street types as they appear in notices, with the abbreviations used in street datasets
+const STREET_TYPES: [(&str, &[&str]); 22] = [
+    ("street", &["st", "str"]),
+    ("road", &["rd"]),
+    ("avenue", &["ave", "av"]),
+    ("parade", &["pde"]),
+    ("highway", &["hwy"]),
+    ("freeway", &["fwy"]),
+    ("drive", &["dr"]),
+    ("crescent", &["cres", "cr"]),
+    ("court", &["ct"]),
+    ("place", &["pl"]),
+    ("boulevard", &["bvd", "blvd"]),
+    ("terrace", &["tce"]),
+    ("lane", &["ln"]),
+    ("close", &["cl"]),
+    ("grove", &["gr"]),
+    ("esplanade", &["esp"]),
+    ("square", &["sq"]),
+    ("circuit", &["cct"]),
+    ("walk", &[]),
+    ("way", &[]),
+    ("track", &[]),
+    ("promenade", &["prom"]),
+];
+
+static GAZETTEER: OnceCell<Option<Gazetteer>> = OnceCell::const_new();
+
+// Geocodes against an imported open dataset instead of a paid API, see the README for importing
+#[derive(Clone, Copy, Debug)]
+pub struct GazetteerGeocoderProvider;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PlaceKind {
+    Station,
+    Landmark,
+    Locality,
+}
+
+#[derive(Clone, Debug)]
+struct Place {
+    kind: PlaceKind,
+    position: GeoPosition,
+}
+
+#[derive(Clone, Debug)]
+struct StreetLine {
+    locality: Option<String>,
+    points: Vec<GeoPosition>,
+}
+
+#[derive(Debug, Default)]
+pub struct Gazetteer {
+    streets: HashMap<String, Vec<StreetLine>>,
+    places: HashMap<String, Vec<Place>>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Query {
+    Intersection(String, String),
+    Station(String),
+    Named(String),
+}
+
+#[derive(Deserialize)]
+struct FeatureCollection {
+    features: Vec<Feature>,
+}
+
+#[derive(Deserialize)]
+struct Feature {
+    geometry: Option<Geometry>,
+    #[serde(default)]
+    properties: Option<HashMap<String, Value>>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+enum Geometry {
+    Point {
+        coordinates: Vec<f64>,
+    },
+    LineString {
+        coordinates: Vec<Vec<f64>>,
+    },
+    MultiLineString {
+        coordinates: Vec<Vec<Vec<f64>>>,
+    },
+    Polygon {
+        coordinates: Vec<Vec<Vec<f64>>>,
+    },
+    MultiPolygon {
+        coordinates: Vec<Vec<Vec<Vec<f64>>>>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+impl Gazetteer {
+    // Loaded once and shared by the geocoder and the boundary tracer
+    pub async fn shared() -> Result<&'static Self> {
+        let path = env::var("GAZETTEER_PATH")
+            .ok()
+            .filter(|path| !path.is_empty())
+            .unwrap_or_else(|| "./gazetteer.geojson".to_string());
+
+        GAZETTEER
+            .get_or_init(|| async {
+                let path = path.clone();
+                tokio::task::spawn_blocking(move || {
+                    let data = std::fs::read_to_string(&path).ok()?;
+                    Gazetteer::from_geojson(&data).ok()
+                })
+                .await
+                .ok()
+                .flatten()
+            })
+            .await
+            .as_ref()
+            .ok_or(anyhow!("No gazetteer could be loaded from {path}"))
+    }
+}
+
+#[async_trait::async_trait]
+impl GeocoderProvider for GazetteerGeocoderProvider {
+    async fn geocode(&self, input: &str, area: &Area) -> Result<GeocodeResult> {
+        Gazetteer::shared().await?.lookup(input, &area.name)
+    }
+
+    fn name(&self) -> String {
+        "gazetteer".to_string()
+    }
+}
+
+impl Gazetteer {
+    // Takes GeoJSON as exported by osmium or overpass from OpenStreetMap, or Vicmap Transport
+    pub fn from_geojson(data: &str) -> Result<Self> {
+        let collection: FeatureCollection = serde_json::from_str(data)?;
+        let mut gazetteer = Self::default();
+
+        for feature in collection.features {
+            let properties = feature.properties.unwrap_or_default();
+            let property = |keys: &[&str]| {
+                keys.iter()
+                    .find_map(|key| properties.get(*key).and_then(Value::as_str))
+                    .filter(|value| !value.is_empty())
+            };
+
+            let Some(name) = property(&["name", "ezi_road_name_label", "road_name"]) else {
+                continue;
+            };
+            let locality =
+                property(&["locality", "suburb", "addr:suburb", "locality_name"]).map(normalise);
+            let kind = if property(&["railway"])
+                .is_some_and(|railway| railway == "station" || railway == "halt")
+                || property(&["public_transport"]) == Some("station")
+                || property(&["kind"]) == Some("station")
+            {
+                PlaceKind::Station
+            } else if property(&["place"]).is_some() || property(&["kind"]) == Some("locality") {
+                PlaceKind::Locality
+            } else {
+                PlaceKind::Landmark
+            };
+            let is_street = property(&["railway", "waterway", "power"]).is_none();
+
+            match feature.geometry {
+                Some(Geometry::Point { coordinates }) => {
+                    if let Some(position) = to_position(&coordinates) {
+                        gazetteer.add_place(name, kind, position);
+                    }
+                }
+                Some(Geometry::LineString { coordinates }) if is_street => {
+                    gazetteer.add_street(name, locality, &coordinates);
+                }
+                Some(Geometry::MultiLineString { coordinates }) if is_street => {
+                    for line in coordinates {
+                        gazetteer.add_street(name, locality.clone(), &line);
+                    }
+                }
+                Some(Geometry::Polygon { coordinates }) => {
+                    if let Some(position) = coordinates.first().and_then(|ring| centroid(ring)) {
+                        gazetteer.add_place(name, kind, position);
+                    }
+                }
+                Some(Geometry::MultiPolygon { coordinates }) => {
+                    let ring = coordinates.first().and_then(|polygon| polygon.first());
+                    if let Some(position) = ring.and_then(|ring| centroid(ring)) {
+                        gazetteer.add_place(name, kind, position);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(gazetteer)
+    }
+
+    fn add_place(&mut self, name: &str, kind: PlaceKind, position: GeoPosition) {
+        let key = match kind {
+            PlaceKind::Station => station_name(&normalise(name)),
+            _ => normalise(name),
+        };
+        self.places
+            .entry(key)
+            .or_default()
+            .push(Place { kind, position });
+    }
+
+    fn add_street(&mut self, name: &str, locality: Option<String>, coordinates: &[Vec<f64>]) {
+        let points: Vec<GeoPosition> = coordinates.iter().filter_map(|c| to_position(c)).collect();
+        if points.len() < 2 {
+            return;
+        }
+
+        self.streets
+            .entry(normalise_street(name))
+            .or_default()
+            .push(StreetLine { locality, points });
+    }
+
+    // The data has no addresses, and no way to say how sure it is beyond what kind of place matched
+    pub fn lookup(&self, input: &str, area: &str) -> Result<GeocodeResult> {
+        let anchor = self.locate_area(area);
+        let area = normalise(area);
+
+        let found = match parse_query(input) {
+            Query::Intersection(first, second) => self
+                .intersection(&first, &second, &area, anchor.as_ref())
+                .map(|position| (position, MatchType::Intersection, 0.9)),
+            Query::Station(name) => self
+                .place(&name, Some(PlaceKind::Station), anchor.as_ref())
+                .map(|place| (place.position, MatchType::Poi, 0.9)),
+            Query::Named(name) => self
+                .place(&name, None, anchor.as_ref())
+                .map(|place| match place.kind {
+                    PlaceKind::Locality => (place.position, MatchType::Locality, 0.3),
+                    _ => (place.position, MatchType::Poi, 0.8),
+                }),
+        };
+
+        let (position, match_type, confidence) =
+            found.ok_or(anyhow!("{input} is not in the gazetteer"))?;
+        Ok(GeocodeResult {
+            position,
+            confidence,
+            match_type,
+            formatted_address: None,
+            viewport: None,
+        })
+    }
+
+    // Every stretch of the named street in the data, wherever it is
+    pub fn street_lines(&self, name: &str) -> impl Iterator<Item = &[GeoPosition]> {
+        self.streets
+            .get(&normalise_street(name))
+            .into_iter()
+            .flatten()
+            .map(|line| line.points.as_slice())
+    }
+
+    // The two streets a "Corner of X and Y Streets" location names, as the data names them
+    pub fn corner_streets(input: &str) -> Option<(String, String)> {
+        match parse_query(input) {
+            Query::Intersection(first, second) => Some((first, second)),
+            _ => None,
+        }
+    }
+
+    // Streets listed together in a notice, as the data names them; "Charlie and Bravo Streets"
+    // means both are streets
+    pub fn street_names(listed: &[&str]) -> Vec<String> {
+        let Some(last) = listed.last().map(|name| normalise(name)) else {
+            return vec![];
+        };
+
+        let (_, shared) = share_street_type(&last, &last);
+        listed[..listed.len() - 1]
+            .iter()
+            .map(|name| share_street_type(&normalise(name), &last).0)
+            .chain([shared])
+            .map(|name| normalise_street(&name))
+            .collect()
+    }
+
+    // Operation areas are suburbs, or "X CBD"
+    fn locate_area(&self, area: &str) -> Option<GeoPosition> {
+        let area = normalise(area);
+        let area = area.trim_end_matches(" cbd");
+
+        let places = self.places.get(area)?;
+        places
+            .iter()
+            .find(|place| place.kind == PlaceKind::Locality)
+            .or(places.first())
+            .map(|place| place.position.clone())
+    }
+
+    fn place(
+        &self,
+        name: &str,
+        kind: Option<PlaceKind>,
+        anchor: Option<&GeoPosition>,
+    ) -> Option<Place> {
+        let mut candidates = self
+            .places
+            .get(name)?
+            .iter()
+            .filter(|place| kind.is_none_or(|kind| place.kind == kind));
+
+        match anchor {
+            Some(anchor) => candidates.min_by(|a, b| {
+                distance_km(&a.position, anchor).total_cmp(&distance_km(&b.position, anchor))
+            }),
+            None => candidates.next(),
+        }
+        .cloned()
+    }
+
+    fn intersection(
+        &self,
+        first: &str,
+        second: &str,
+        area: &str,
+        anchor: Option<&GeoPosition>,
+    ) -> Option<GeoPosition> {
+        let first_lines = self.streets.get(first)?;
+        let second_lines = self.streets.get(second)?;
+        let mut crossings = Vec::new();
+        let mut in_area = Vec::new();
+
+        for a in first_lines {
+            for b in second_lines {
+                let Some(crossing) = crossing(&a.points, &b.points) else {
+                    continue;
+                };
+
+                if a.locality.as_deref() == Some(area) || b.locality.as_deref() == Some(area) {
+                    in_area.push(crossing.clone());
+                }
+                crossings.push(crossing);
+            }
+        }
+
+        if in_area.is_empty() {
+            nearest(crossings, anchor)
+        } else {
+            nearest(in_area, anchor)
+        }
+    }
+}
+
+fn parse_query(input: &str) -> Query {
+    let input = normalise(
+        &input
+            .split(',')
+            .next()
+            .unwrap_or_default()
+            .replace('&', " and "),
+    );
+    let stripped = [
+        "corner of ",
+        "intersection of ",
+        "cnr of ",
+        "cnr ",
+        "corner ",
+    ]
+    .iter()
+    .find_map(|prefix| input.strip_prefix(prefix));
+
+    let parts: Vec<&str> = stripped
+        .unwrap_or(&input)
+        .split(" and ")
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect();
+
+    if let [first, second, ..] = parts[..] {
+        if stripped.is_some()
+            || [first, second]
+                .iter()
+                .all(|part| street_type_of(part).is_some())
+        {
+            let (first, second) = share_street_type(first, second);
+            return Query::Intersection(normalise_street(&first), normalise_street(&second));
+        }
+    }
+
+    if input.ends_with(" station") {
+        return Query::Station(station_name(&input));
+    }
+
+    Query::Named(input.trim_end_matches(" cbd").to_string())
+}
+
+// "Corner of Flinders and Swanston Streets" means both are streets
+fn share_street_type(first: &str, second: &str) -> (String, String) {
+    let Some((rest, last)) = second.rsplit_once(' ') else {
+        return (first.to_string(), second.to_string());
+    };
+
+    let plural = STREET_TYPES
+        .iter()
+        .find(|(street_type, _)| last.strip_suffix('s') == Some(street_type));
+
+    match plural {
+        Some((street_type, _)) => {
+            let first = match street_type_of(first) {
+                Some(_) => first.to_string(),
+                None => format!("{first} {street_type}"),
+            };
+            (first, format!("{rest} {street_type}"))
+        }
+        None if street_type_of(first).is_none() => match street_type_of(second) {
+            Some(street_type) => (format!("{first} {street_type}"), second.to_string()),
+            None => (first.to_string(), second.to_string()),
+        },
+        None => (first.to_string(), second.to_string()),
+    }
+}
+
+fn street_type(word: &str) -> Option<&'static str> {
+    STREET_TYPES
+        .iter()
+        .find(|(street_type, abbreviations)| *street_type == word || abbreviations.contains(&word))
+        .map(|(street_type, _)| *street_type)
+}
+
+fn street_type_of(name: &str) -> Option<&'static str> {
+    name.rsplit_once(' ')
+        .and_then(|(_, last)| street_type(last))
+}
+
+fn normalise(name: &str) -> String {
+    name.to_lowercase()
+        .replace(|c: char| !c.is_alphanumeric(), " ")
+        .split_whitespace()
+        .filter(|word| *word != "the")
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+// Only the last word is a street type, "St Kilda Road" is not "Street Kilda Road"
+fn normalise_street(name: &str) -> String {
+    let name = normalise(name);
+
+    match name.rsplit_once(' ') {
+        Some((rest, last)) => match street_type(last) {
+            Some(street_type) => format!("{rest} {street_type}"),
+            None => name,
+        },
+        None => name,
+    }
+}
+
+fn station_name(name: &str) -> String {
+    ["railway station", "train station", "station"]
+        .iter()
+        .find_map(|suffix| name.strip_suffix(suffix))
+        .unwrap_or(name)
+        .trim()
+        .to_string()
+}
+
+fn to_position(coordinates: &[f64]) -> Option<GeoPosition> {
+    match coordinates {
+        [longitude, latitude, ..] => Some(GeoPosition {
+            latitude: *latitude,
+            longitude: *longitude,
+        }),
+        _ => None,
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn centroid(ring: &[Vec<f64>]) -> Option<GeoPosition> {
+    let points: Vec<GeoPosition> = ring.iter().filter_map(|c| to_position(c)).collect();
+    if points.is_empty() {
+        return None;
+    }
+
+    let count = points.len() as f64;
+    Some(GeoPosition {
+        latitude: points.iter().map(|p| p.latitude).sum::<f64>() / count,
+        longitude: points.iter().map(|p| p.longitude).sum::<f64>() / count,
+    })
+}
+
+fn nearest(candidates: Vec<GeoPosition>, anchor: Option<&GeoPosition>) -> Option<GeoPosition> {
+    match anchor {
+        Some(anchor) => candidates
+            .into_iter()
+            .min_by(|a, b| distance_km(a, anchor).total_cmp(&distance_km(b, anchor))),
+        None => candidates.into_iter().next(),
+    }
+}
+
+// Street-scale distances, so a flat projection around the points is plenty
+fn to_km(position: &GeoPosition, origin: &GeoPosition) -> (f64, f64) {
+    (
+        (position.longitude - origin.longitude) * 111.32 * origin.latitude.to_radians().cos(),
+        (position.latitude - origin.latitude) * 110.574,
+    )
+}
+
+fn distance_km(a: &GeoPosition, b: &GeoPosition) -> f64 {
+    let (x, y) = to_km(a, b);
+    x.hypot(y)
+}
+
+fn crossing(first: &[GeoPosition], second: &[GeoPosition]) -> Option<GeoPosition> {
+    for a in first.windows(2) {
+        for b in second.windows(2) {
+            if let Some(point) = segment_intersection(&a[0], &a[1], &b[0], &b[1]) {
+                return Some(point);
+            }
+        }
+    }
+
+    // No crossing, so look for one street ending at (or just short of) the other
+    let mut closest: Option<(f64, GeoPosition)> = None;
+    for (points, other) in [(first, second), (second, first)] {
+        for point in [points.first(), points.last()].into_iter().flatten() {
+            for segment in other.windows(2) {
+                let distance = distance_to_segment(point, &segment[0], &segment[1]);
+                if distance <= JUNCTION_TOLERANCE_KM
+                    && closest.as_ref().is_none_or(|(d, _)| distance < *d)
+                {
+                    closest = Some((distance, point.clone()));
+                }
+            }
+        }
+    }
+
+    closest.map(|(_, point)| point)
+}
+
+fn segment_intersection(
+    p1: &GeoPosition,
+    p2: &GeoPosition,
+    q1: &GeoPosition,
+    q2: &GeoPosition,
+) -> Option<GeoPosition> {
+    let r = (p2.longitude - p1.longitude, p2.latitude - p1.latitude);
+    let s = (q2.longitude - q1.longitude, q2.latitude - q1.latitude);
+    let denominator = r.0 * s.1 - r.1 * s.0;
+    if denominator.abs() < f64::EPSILON {
+        return None;
+    }
+
+    let qp = (q1.longitude - p1.longitude, q1.latitude - p1.latitude);
+    let t = (qp.0 * s.1 - qp.1 * s.0) / denominator;
+    let u = (qp.0 * r.1 - qp.1 * r.0) / denominator;
+
+    ((0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u)).then_some(GeoPosition {
+        latitude: p1.latitude + t * r.1,
+        longitude: p1.longitude + t * r.0,
+    })
+}
+
+fn distance_to_segment(point: &GeoPosition, start: &GeoPosition, end: &GeoPosition) -> f64 {
+    let (px, py) = to_km(point, start);
+    let (ex, ey) = to_km(end, start);
+    let length = ex * ex + ey * ey;
+    if length == 0.0 {
+        return px.hypot(py);
+    }
+
+    let t = ((px * ex + py * ey) / length).clamp(0.0, 1.0);
+    (px - t * ex).hypot(py - t * ey)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two Flinders Streets, so the area has to pick the right one
+    const FIXTURE: &str = r#"{
+        "type": "FeatureCollection",
+        "features": [
+            {"type": "Feature", "properties": {"name": "Flinders Street", "highway": "primary"},
+             "geometry": {"type": "LineString", "coordinates": [[144.960, -37.8185], [144.970, -37.8170]]}},
+            {"type": "Feature", "properties": {"name": "Swanston Street", "highway": "primary"},
+             "geometry": {"type": "LineString", "coordinates": [[144.966, -37.8100], [144.966, -37.8250]]}},
+            {"type": "Feature", "properties": {"name": "Flinders St", "highway": "residential"},
+             "geometry": {"type": "LineString", "coordinates": [[145.500, -38.000], [145.510, -38.000]]}},
+            {"type": "Feature", "properties": {"name": "Swanston St", "highway": "residential"},
+             "geometry": {"type": "LineString", "coordinates": [[145.505, -37.990], [145.505, -38.010]]}},
+            {"type": "Feature", "properties": {"name": "Elizabeth Street", "highway": "primary"},
+             "geometry": {"type": "MultiLineString", "coordinates": [[[144.9635, -37.810], [144.9635, -37.8179]]]}},
+            {"type": "Feature", "properties": {"name": "St Kilda Road", "highway": "primary"},
+             "geometry": {"type": "LineString", "coordinates": [[144.9685, -37.8172], [144.9685, -37.8300]]}},
+            {"type": "Feature", "properties": {"name": "Southern Cross", "railway": "station"},
+             "geometry": {"type": "Point", "coordinates": [144.9525, -37.8184]}},
+            {"type": "Feature", "properties": {"name": "Melbourne Central Shopping Centre", "shop": "mall"},
+             "geometry": {"type": "Polygon", "coordinates": [[[144.962, -37.810], [144.964, -37.810], [144.964, -37.812], [144.962, -37.812], [144.962, -37.810]]]}},
+            {"type": "Feature", "properties": {"name": "Melbourne", "place": "city"},
+             "geometry": {"type": "Point", "coordinates": [144.963, -37.814]}},
+            {"type": "Feature", "properties": {"name": "Upper Plenty", "place": "locality"},
+             "geometry": {"type": "Point", "coordinates": [145.505, -38.001]}},
+            {"type": "Feature", "properties": {"name": "Flinders Street Line", "railway": "rail"},
+             "geometry": {"type": "LineString", "coordinates": [[144.960, -37.820], [144.970, -37.820]]}}
+        ]
+    }"#;
+
+    #[test]
+    fn test_parse_query() {
+        assert_eq!(
+            parse_query("Corner of Flinders and Swanston Streets"),
+            Query::Intersection("flinders street".into(), "swanston street".into())
+        );
+        assert_eq!(
+            parse_query("Corner of St Kilda Rd and Flinders St, Melbourne"),
+            Query::Intersection("st kilda road".into(), "flinders street".into())
+        );
+        assert_eq!(
+            parse_query("Flinders Street & Elizabeth Street"),
+            Query::Intersection("flinders street".into(), "elizabeth street".into())
+        );
+        assert_eq!(
+            parse_query("Southern Cross Railway Station"),
+            Query::Station("southern cross".into())
+        );
+        assert_eq!(
+            parse_query("Melbourne CBD"),
+            Query::Named("melbourne".into())
+        );
+    }
+
+    #[test]
+    fn test_street_names() {
+        assert_eq!(
+            Gazetteer::street_names(&["Delta St", "Charlie", "Bravo Streets"]),
+            ["delta street", "charlie street", "bravo street"]
+        );
+        assert_eq!(Gazetteer::street_names(&["St Kilda Rd"]), ["st kilda road"]);
+        assert!(Gazetteer::street_names(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_intersection_lookup() {
+        let gazetteer = Gazetteer::from_geojson(FIXTURE).unwrap();
+
+        let city = gazetteer
+            .lookup("Corner of Flinders and Swanston Streets", "Melbourne CBD")
+            .unwrap();
+        assert_eq!(city.match_type, MatchType::Intersection);
+        let city = city.position;
+        assert!((city.longitude - 144.966).abs() < 1e-6);
+        assert!((city.latitude + 37.8176).abs() < 1e-3);
+
+        let country = gazetteer
+            .lookup("Corner of Flinders and Swanston Streets", "Upper Plenty")
+            .unwrap()
+            .position;
+        assert!((country.longitude - 145.505).abs() < 1e-6);
+        assert!((country.latitude + 38.0).abs() < 1e-6);
+
+        // Elizabeth Street stops just short of Flinders Street in the data
+        let junction = gazetteer
+            .lookup(
+                "Corner of Elizabeth Street and Flinders Street",
+                "Melbourne",
+            )
+            .unwrap()
+            .position;
+        assert!((junction.longitude - 144.9635).abs() < 1e-6);
+
+        assert!(gazetteer
+            .lookup("Corner of St Kilda Road and Flinders Street", "Melbourne")
+            .is_ok());
+        assert!(gazetteer
+            .lookup(
+                "Corner of Swanston Street and Elizabeth Street",
+                "Melbourne"
+            )
+            .is_err());
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_place_lookup() {
+        let gazetteer = Gazetteer::from_geojson(FIXTURE).unwrap();
+
+        let station = gazetteer
+            .lookup("Southern Cross Railway Station", "Melbourne CBD")
+            .unwrap();
+        assert_eq!(station.position.longitude, 144.9525);
+        assert_eq!(station.match_type, MatchType::Poi);
+
+        let centre = gazetteer
+            .lookup("Melbourne Central Shopping Centre", "Melbourne")
+            .unwrap();
+        assert!((centre.position.latitude + 37.8108).abs() < 1e-3);
+
+        let cbd = gazetteer.lookup("Melbourne CBD", "Melbourne CBD").unwrap();
+        assert_eq!(cbd.position.longitude, 144.963);
+        assert!(cbd.is_locality());
+
+        assert!(gazetteer
+            .lookup("Flinders Street Line", "Melbourne")
+            .is_err());
+        assert!(gazetteer
+            .lookup("Flinders Street Station", "Melbourne")
+            .is_err());
+    }
+}
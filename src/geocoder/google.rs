@@ -1,9 +1,13 @@
-use crate::geocoder::GeocoderProvider;
+use crate::geocoder::{Area, GeocodeResult, GeocoderProvider, MatchType, Viewport};
+use crate::utils::http::{client, ApiLimiter};
 use crate::utils::maptypes::GeoPosition;
 use anyhow::{anyhow, Result};
-use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::env;
+use std::time::Duration;
+
+// Google allows 50 requests a second
+static LIMITER: ApiLimiter = ApiLimiter::new("google", Duration::from_millis(20), 10);
 
 #[derive(Clone, Copy, Debug)]
 pub struct GoogleGeocoderProvider;
@@ -14,15 +18,27 @@ pub struct GoogleGeocoderPosition {
     pub lng: f64,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct GoogleGeocoderViewport {
+    northeast: GoogleGeocoderPosition,
+    southwest: GoogleGeocoderPosition,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 struct GoogleGeocoderGeometry {
     location: GoogleGeocoderPosition,
+    #[serde(default)]
+    location_type: String,
+    viewport: Option<GoogleGeocoderViewport>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 struct GoogleGeocoderResult {
     geometry: GoogleGeocoderGeometry,
     types: Vec<String>,
+    formatted_address: Option<String>,
+    #[serde(default)]
+    partial_match: bool,
 }
 
 #[derive(Deserialize, Clone, Debug)]
@@ -32,32 +48,99 @@ struct GoogleGeocoderResponse {
 
 #[async_trait::async_trait]
 impl GeocoderProvider for GoogleGeocoderProvider {
-    async fn geocode(&self, input: &str, area: &str) -> Result<GeoPosition> {
+    async fn geocode(&self, input: &str, area: &Area) -> Result<GeocodeResult> {
         if let Ok(api_key) = env::var("GOOGLE_MAPS_API_KEY") {
-            let client = Client::new();
-            let input = format!("{input}, {area}, VICTORIA, AUSTRALIA");
-            let request = format!("https://maps.googleapis.com/maps/api/geocode/json?key={api_key}&api-version=1.0&language=en-AU&region=AU&address={input}");
-            let res = client.get(request).send().await?;
+            let input = format!("{input}, {}, VICTORIA, AUSTRALIA", area.name);
+            let mut request = format!("https://maps.googleapis.com/maps/api/geocode/json?key={api_key}&api-version=1.0&language=en-AU&region=AU&address={input}");
+            // Google only prefers results in the bounds; the trait rejects the rest
+            if let Some(bounds) = &area.bounds {
+                request.push_str(&format!(
+                    "&bounds={},{}|{},{}",
+                    bounds.south_west.latitude,
+                    bounds.south_west.longitude,
+                    bounds.north_east.latitude,
+                    bounds.north_east.longitude
+                ));
+            }
+            let res = LIMITER.send(client().get(request)).await?;
 
             let body = res.json::<GoogleGeocoderResponse>().await?;
             return body.into();
         }
         Err(anyhow!("Geocoding failed"))
     }
+
+    fn name(&self) -> String {
+        "google".to_string()
+    }
 }
 
-impl From<GoogleGeocoderResponse> for Result<GeoPosition> {
+impl From<GoogleGeocoderResponse> for Result<GeocodeResult> {
     fn from(value: GoogleGeocoderResponse) -> Self {
         // The google geocoder seems to be good enough to get a hit on the first result,
         // but I'm not sure how i'd filter them to find relevant ones if it doesn't
         if let Some(result) = value.results.first() {
-            Ok((&result.clone().geometry.location).into())
+            Ok(result.into())
         } else {
             Err(anyhow!("Geocoder returned no results"))
         }
     }
 }
 
+impl From<&GoogleGeocoderResult> for GeocodeResult {
+    fn from(value: &GoogleGeocoderResult) -> Self {
+        // A partial match is Google guessing at part of the address, so halve its confidence
+        let confidence = match value.geometry.location_type.as_str() {
+            "ROOFTOP" => 1.0,
+            "RANGE_INTERPOLATED" => 0.8,
+            "GEOMETRIC_CENTER" => 0.6,
+            _ => 0.4,
+        } * if value.partial_match { 0.5 } else { 1.0 };
+
+        Self {
+            position: (&value.geometry.location).into(),
+            confidence,
+            match_type: match_type(&value.types),
+            formatted_address: value.formatted_address.clone(),
+            viewport: value.geometry.viewport.as_ref().map(|viewport| Viewport {
+                north_east: (&viewport.northeast).into(),
+                south_west: (&viewport.southwest).into(),
+            }),
+        }
+    }
+}
+
+// Results list every type that applies, so the most specific one found wins
+fn match_type(types: &[String]) -> MatchType {
+    let has = |wanted: &[&str]| types.iter().any(|kind| wanted.contains(&kind.as_str()));
+
+    if has(&["intersection"]) {
+        MatchType::Intersection
+    } else if has(&[
+        "point_of_interest",
+        "establishment",
+        "transit_station",
+        "train_station",
+        "premise",
+        "park",
+    ]) {
+        MatchType::Poi
+    } else if has(&["street_address", "route", "street_number"]) {
+        MatchType::Street
+    } else if has(&[
+        "locality",
+        "sublocality",
+        "neighborhood",
+        "colloquial_area",
+        "postal_code",
+        "political",
+    ]) {
+        MatchType::Locality
+    } else {
+        MatchType::Unknown
+    }
+}
+
 impl From<&GoogleGeocoderPosition> for GeoPosition {
     fn from(value: &GoogleGeocoderPosition) -> Self {
         Self {
@@ -66,3 +149,43 @@ impl From<&GoogleGeocoderPosition> for GeoPosition {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_google_result() {
+        let body = r#"{"results": [
+            {"formatted_address": "Flinders St & Swanston St, Melbourne VIC 3000, Australia",
+             "geometry": {"location": {"lat": -37.8176, "lng": 144.9668},
+                          "location_type": "GEOMETRIC_CENTER",
+                          "viewport": {"northeast": {"lat": -37.816, "lng": 144.968},
+                                       "southwest": {"lat": -37.819, "lng": 144.965}}},
+             "types": ["intersection"]},
+            {"formatted_address": "Melbourne VIC 3000, Australia",
+             "geometry": {"location": {"lat": -37.8136, "lng": 144.9631},
+                          "location_type": "APPROXIMATE"},
+             "partial_match": true,
+             "types": ["locality", "political"]}
+        ]}"#;
+        let response: GoogleGeocoderResponse = serde_json::from_str(body).unwrap();
+
+        let corner: GeocodeResult = (&response.results[0]).into();
+        assert_eq!(corner.match_type, MatchType::Intersection);
+        assert_eq!(corner.confidence, 0.6);
+        assert_eq!(corner.viewport.unwrap().north_east.latitude, -37.816);
+
+        let suburb: GeocodeResult = (&response.results[1]).into();
+        assert!(suburb.is_locality());
+        assert_eq!(suburb.confidence, 0.2);
+        assert!(suburb.viewport.is_none());
+
+        let first: Result<GeocodeResult> = response.into();
+        assert_eq!(
+            first.unwrap().formatted_address.as_deref(),
+            Some("Flinders St & Swanston St, Melbourne VIC 3000, Australia")
+        );
+    }
+}
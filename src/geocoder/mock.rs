@@ -1,4 +1,5 @@
-use crate::{geocoder::GeocoderProvider, utils::maptypes::GeoPosition};
+use crate::geocoder::{Area, GeocodeResult, GeocoderProvider, MatchType};
+use crate::utils::maptypes::GeoPosition;
 use anyhow::Result;
 use std::collections::HashMap;
 
@@ -35,20 +36,29 @@ impl MockGeocoderProvider {
 
 #[async_trait::async_trait]
 impl GeocoderProvider for MockGeocoderProvider {
-    async fn geocode(&self, input: &str, area: &str) -> Result<GeoPosition> {
+    async fn geocode(&self, input: &str, _area: &Area) -> Result<GeocodeResult> {
         let responses = Self::get_mock_responses();
 
-        Ok(responses.get(input).cloned().unwrap_or(GeoPosition {
-            latitude: 0.0,
-            longitude: 0.0,
-        }))
+        Ok(match responses.get(input) {
+            Some(position) => GeocodeResult {
+                position: position.clone(),
+                confidence: 1.0,
+                match_type: MatchType::Poi,
+                formatted_address: Some(input.to_string()),
+                viewport: None,
+            },
+            None => GeocodeResult::default(),
+        })
+    }
+
+    fn name(&self) -> String {
+        "mock".to_string()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::geocoder::GeocoderRequest;
 
     #[tokio::test]
     #[allow(clippy::float_cmp)]
@@ -56,28 +66,33 @@ mod tests {
         let geocoder = MockGeocoderProvider {};
 
         // Test known location
-        let ny_position = geocoder.geocode("New York", "New York").await.unwrap();
-        assert_eq!(ny_position.latitude, 40.7128);
-        assert_eq!(ny_position.longitude, -74.0060);
+        let ny = geocoder
+            .geocode("New York", &Area::named("New York"))
+            .await
+            .unwrap();
+        assert_eq!(ny.position.latitude, 40.7128);
+        assert_eq!(ny.position.longitude, -74.0060);
+        assert_eq!(ny.match_type, MatchType::Poi);
 
         // Test unknown location (should return default position)
-        let unknown_position = geocoder.geocode("Unknown Location", "Unknown Location").await.unwrap();
-        assert_eq!(unknown_position.latitude, 0.0);
-        assert_eq!(unknown_position.longitude, 0.0);
+        let unknown = geocoder
+            .geocode("Unknown Location", &Area::named("Unknown Location"))
+            .await
+            .unwrap();
+        assert_eq!(unknown.position.latitude, 0.0);
+        assert_eq!(unknown.position.longitude, 0.0);
+        assert_eq!(unknown.confidence, 0.0);
     }
 
     #[tokio::test]
     #[allow(clippy::float_cmp)]
     async fn test_geocoder_request() {
         let service = MockGeocoderProvider {};
-        let request = GeocoderRequest {
-            input: "London".to_string(),
-            area: "United Kingdom".to_string(),
-            service,
-        };
-
-        let position = request.geocode().await.unwrap();
-        assert_eq!(position.latitude, 51.5074);
-        assert_eq!(position.longitude, -0.1278);
+        let result = service
+            .geocode("London", &Area::named("United Kingdom"))
+            .await
+            .unwrap();
+        assert_eq!(result.position.latitude, 51.5074);
+        assert_eq!(result.position.longitude, -0.1278);
     }
 }
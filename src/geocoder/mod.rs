@@ -1,9 +1,13 @@
 pub mod azure;
+pub mod cache;
+pub mod composite;
 pub mod core;
+pub mod gazetteer;
 pub mod google;
 #[cfg(test)]
 pub(crate) mod mock;
+pub mod nominatim;
 
 use core::GeocoderProvider;
 
-pub use core::GeocoderRequest;
+pub use core::{Area, GeocodeResult, GeocodedPoint, GeocoderBatchRequest, MatchType, Viewport};
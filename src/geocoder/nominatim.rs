@@ -0,0 +1,361 @@
+use crate::geocoder::{Area, GeocodeResult, GeocoderProvider, MatchType, Viewport};
+use crate::utils::http::{client, ApiLimiter};
+use crate::utils::maptypes::GeoPosition;
+use anyhow::{anyhow, Result};
+use reqwest::header::USER_AGENT;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::time::Duration;
+
+const DEFAULT_URL: &str = "https://nominatim.openstreetmap.org";
+
+// The public Nominatim usage policy allows one request a second
+static LIMITER: ApiLimiter = ApiLimiter::new("nominatim", Duration::from_secs(1), 1);
+
+// Talks to Nominatim, or to Photon with NOMINATIM_API=photon, hosted publicly or by yourself
+#[derive(Clone, Copy, Debug)]
+pub struct NominatimGeocoderProvider;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum NominatimApi {
+    Nominatim,
+    Photon,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct NominatimResult {
+    lat: String,
+    lon: String,
+    category: String,
+    r#type: String,
+    display_name: Option<String>,
+    // South, north, west, east
+    boundingbox: Option<[String; 4]>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+struct PhotonProperties {
+    osm_key: String,
+    osm_value: String,
+    name: Option<String>,
+    street: Option<String>,
+    city: Option<String>,
+    // West, north, east, south
+    extent: Option<[f64; 4]>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+struct PhotonGeometry {
+    coordinates: [f64; 2],
+}
+
+#[derive(Deserialize, Clone, Debug)]
+struct PhotonFeature {
+    geometry: PhotonGeometry,
+    properties: PhotonProperties,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+struct PhotonResponse {
+    features: Vec<PhotonFeature>,
+}
+
+// An OSM feature as both APIs describe it, e.g. class "railway" and type "station"
+#[derive(Clone, Debug, PartialEq)]
+struct Candidate {
+    class: String,
+    kind: String,
+    position: GeoPosition,
+    address: Option<String>,
+    viewport: Option<Viewport>,
+}
+
+impl NominatimApi {
+    fn from_env() -> Self {
+        match env::var("NOMINATIM_API").as_deref() {
+            Ok("photon") => Self::Photon,
+            _ => Self::Nominatim,
+        }
+    }
+}
+
+impl NominatimGeocoderProvider {
+    fn base_url() -> String {
+        env::var("NOMINATIM_URL")
+            .ok()
+            .filter(|url| !url.is_empty())
+            .unwrap_or_else(|| DEFAULT_URL.to_string())
+            .trim_end_matches('/')
+            .to_string()
+    }
+
+    // Nominatim blocks generic user agents, so identify ourselves and give a way to get in touch
+    fn user_agent() -> String {
+        env::var("NOMINATIM_USER_AGENT")
+            .ok()
+            .filter(|agent| !agent.is_empty())
+            .unwrap_or_else(|| {
+                format!(
+                    "{}/{} (+https://github.com/benbeshara/Carcinised-Gazette-Scraper)",
+                    env!("CARGO_PKG_NAME"),
+                    env!("CARGO_PKG_VERSION")
+                )
+            })
+    }
+
+    async fn search(query: &str, bounds: Option<&Viewport>) -> Result<Vec<Candidate>> {
+        let base_url = Self::base_url();
+
+        match NominatimApi::from_env() {
+            NominatimApi::Nominatim => {
+                let mut request = client()
+                    .get(format!("{base_url}/search"))
+                    .header(USER_AGENT, Self::user_agent())
+                    .query(&[
+                        ("q", query),
+                        ("format", "jsonv2"),
+                        ("countrycodes", "au"),
+                        ("accept-language", "en-AU"),
+                        ("limit", "10"),
+                    ]);
+                if let Some(bounds) = bounds {
+                    let viewbox = format!(
+                        "{},{},{},{}",
+                        bounds.south_west.longitude,
+                        bounds.north_east.latitude,
+                        bounds.north_east.longitude,
+                        bounds.south_west.latitude
+                    );
+                    request = request.query(&[("viewbox", viewbox.as_str()), ("bounded", "1")]);
+                }
+
+                let res = LIMITER.send(request).await?;
+
+                let body = res.json::<Vec<NominatimResult>>().await?;
+                Ok(body.iter().filter_map(Candidate::from_nominatim).collect())
+            }
+            NominatimApi::Photon => {
+                let mut request = client()
+                    .get(format!("{base_url}/api"))
+                    .header(USER_AGENT, Self::user_agent())
+                    .query(&[("q", query), ("lang", "en"), ("limit", "10")]);
+                if let Some(bounds) = bounds {
+                    let bbox = format!(
+                        "{},{},{},{}",
+                        bounds.south_west.longitude,
+                        bounds.south_west.latitude,
+                        bounds.north_east.longitude,
+                        bounds.north_east.latitude
+                    );
+                    request = request.query(&[("bbox", bbox.as_str())]);
+                }
+
+                let res = LIMITER.send(request).await?;
+
+                let body = res.json::<PhotonResponse>().await?;
+                Ok(body.features.into_iter().map(Into::into).collect())
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl GeocoderProvider for NominatimGeocoderProvider {
+    async fn geocode(&self, input: &str, area: &Area) -> Result<GeocodeResult> {
+        let query = format!("{input}, {}, Victoria, Australia", area.name);
+        let candidates = Self::search(&query, area.bounds.as_ref()).await?;
+
+        best_candidate(candidates, input)
+    }
+
+    fn name(&self) -> String {
+        match NominatimApi::from_env() {
+            NominatimApi::Nominatim => "nominatim".to_string(),
+            NominatimApi::Photon => "photon".to_string(),
+        }
+    }
+}
+
+impl Candidate {
+    fn from_nominatim(result: &NominatimResult) -> Option<Self> {
+        let viewport = result
+            .boundingbox
+            .as_ref()
+            .and_then(|[south, north, west, east]| {
+                Some(Viewport {
+                    north_east: GeoPosition {
+                        latitude: north.parse().ok()?,
+                        longitude: east.parse().ok()?,
+                    },
+                    south_west: GeoPosition {
+                        latitude: south.parse().ok()?,
+                        longitude: west.parse().ok()?,
+                    },
+                })
+            });
+
+        Some(Self {
+            class: result.category.clone(),
+            kind: result.r#type.clone(),
+            position: GeoPosition {
+                latitude: result.lat.parse().ok()?,
+                longitude: result.lon.parse().ok()?,
+            },
+            address: result.display_name.clone(),
+            viewport,
+        })
+    }
+
+    fn match_type(&self) -> MatchType {
+        match (self.class.as_str(), self.kind.as_str()) {
+            (
+                "highway",
+                "traffic_signals" | "crossing" | "stop" | "give_way" | "mini_roundabout",
+            ) => MatchType::Intersection,
+            ("highway", _) => MatchType::Street,
+            ("place" | "boundary", _) => MatchType::Locality,
+            _ => MatchType::Poi,
+        }
+    }
+
+    // Neither API scores its matches, so how well the kind of place suits the query stands in
+    fn into_result(self, input: &str) -> GeocodeResult {
+        GeocodeResult {
+            confidence: (f64::from(self.preference(input)) + 1.0) / 5.0,
+            match_type: self.match_type(),
+            position: self.position,
+            formatted_address: self.address,
+            viewport: self.viewport,
+        }
+    }
+
+    // Higher is better; the location parser phrases places as intersections or stations where
+    // it can, so those are what we want back, and a suburb centroid is the last resort
+    fn preference(&self, input: &str) -> u8 {
+        let input = input.to_lowercase();
+        let wants_intersection = input.starts_with("corner of") || input.contains(" and ");
+        let wants_station = input.contains("station");
+
+        match (self.class.as_str(), self.kind.as_str()) {
+            ("railway", "station" | "halt" | "stop") | ("public_transport", "station")
+                if wants_station =>
+            {
+                4
+            }
+            (
+                "highway",
+                "traffic_signals" | "crossing" | "stop" | "give_way" | "mini_roundabout",
+            ) if wants_intersection => 4,
+            ("highway", _) if wants_intersection => 3,
+            ("place" | "boundary", _) => 0,
+            _ => 1,
+        }
+    }
+}
+
+impl From<PhotonFeature> for Candidate {
+    fn from(value: PhotonFeature) -> Self {
+        let [longitude, latitude] = value.geometry.coordinates;
+        let properties = value.properties;
+        let address: Vec<String> = [properties.name, properties.street, properties.city]
+            .into_iter()
+            .flatten()
+            .collect();
+
+        Self {
+            class: properties.osm_key,
+            kind: properties.osm_value,
+            position: GeoPosition {
+                latitude,
+                longitude,
+            },
+            address: (!address.is_empty()).then(|| address.join(", ")),
+            viewport: properties
+                .extent
+                .map(|[west, north, east, south]| Viewport {
+                    north_east: GeoPosition {
+                        latitude: north,
+                        longitude: east,
+                    },
+                    south_west: GeoPosition {
+                        latitude: south,
+                        longitude: west,
+                    },
+                }),
+        }
+    }
+}
+
+// Results come back in the API's own relevance order, which breaks ties
+fn best_candidate(candidates: Vec<Candidate>, input: &str) -> Result<GeocodeResult> {
+    candidates
+        .into_iter()
+        .enumerate()
+        .max_by_key(|(index, candidate)| (candidate.preference(input), std::cmp::Reverse(*index)))
+        .map(|(_, candidate)| candidate.into_result(input))
+        .ok_or(anyhow!("Geocoder returned no results"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_nominatim_results() {
+        let body = r#"[
+            {"lat": "-37.8142176", "lon": "144.9631608", "category": "place", "type": "city"},
+            {"lat": "-37.8180", "lon": "144.9530", "category": "building", "type": "yes"},
+            {"lat": "-37.8183886", "lon": "144.9525", "category": "railway", "type": "station",
+             "display_name": "Southern Cross, Spencer Street, Docklands, Melbourne",
+             "boundingbox": ["-37.8210", "-37.8150", "144.9500", "144.9550"]},
+            {"lat": "not a number", "lon": "144.9", "category": "railway", "type": "station"}
+        ]"#;
+        let results: Vec<NominatimResult> = serde_json::from_str(body).unwrap();
+        let candidates: Vec<Candidate> = results
+            .iter()
+            .filter_map(Candidate::from_nominatim)
+            .collect();
+        assert_eq!(candidates.len(), 3);
+
+        let station = best_candidate(candidates.clone(), "Southern Cross Railway Station").unwrap();
+        assert_eq!(station.position.longitude, 144.9525);
+        assert_eq!(station.match_type, MatchType::Poi);
+        assert_eq!(station.confidence, 1.0);
+        assert_eq!(station.viewport.unwrap().north_east.latitude, -37.8150);
+
+        let landmark = best_candidate(candidates, "Marvel Stadium").unwrap();
+        assert_eq!(landmark.position.longitude, 144.9530);
+
+        assert!(best_candidate(vec![], "Marvel Stadium").is_err());
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_photon_results() {
+        let body = r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "geometry": {"type": "Point", "coordinates": [144.9631, -37.8136]},
+             "properties": {"osm_key": "place", "osm_value": "suburb", "name": "Melbourne"}},
+            {"type": "Feature", "geometry": {"type": "Point", "coordinates": [144.9660, -37.8170]},
+             "properties": {"osm_key": "highway", "osm_value": "primary", "name": "Swanston Street"}},
+            {"type": "Feature", "geometry": {"type": "Point", "coordinates": [144.9662, -37.8176]},
+             "properties": {"osm_key": "highway", "osm_value": "traffic_signals"}}
+        ]}"#;
+        let response: PhotonResponse = serde_json::from_str(body).unwrap();
+        let candidates: Vec<Candidate> = response.features.into_iter().map(Into::into).collect();
+
+        let corner = best_candidate(
+            candidates.clone(),
+            "Corner of Flinders and Swanston Streets",
+        )
+        .unwrap();
+        assert_eq!(corner.position.longitude, 144.9662);
+        assert_eq!(corner.match_type, MatchType::Intersection);
+
+        // Suburb centroids lose to anything more specific, and ties go to the API's own ranking
+        let first = best_candidate(candidates, "Swanston Street").unwrap();
+        assert_eq!(first.position.longitude, 144.9660);
+        assert_eq!(first.match_type, MatchType::Street);
+        assert_eq!(first.formatted_address.as_deref(), Some("Swanston Street"));
+    }
+}
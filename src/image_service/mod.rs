@@ -1,7 +0,0 @@
-mod core;
-mod s3;
-#[cfg(test)]
-pub(crate) mod mock;
-
-pub use core::{Image, ImageService};
-pub use s3::S3;
@@ -1,9 +1,12 @@
 use crate::location_parser::LocationParserService;
+use crate::utils::http::{client, ApiLimiter};
 use anyhow::Result;
-use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::env;
+use std::time::Duration;
+
+static LIMITER: ApiLimiter = ApiLimiter::new("openai", Duration::ZERO, 4);
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 struct OpenAIMessage {
@@ -38,8 +41,7 @@ pub struct OpenAI;
 impl LocationParserService for OpenAI {
     async fn parse_locations(&self, locations: String) -> Result<Vec<String>> {
         let api_key = env::var("OPENAI_API_KEY")?;
-        let client = Client::new();
-        let res = client
+        let request = client()
             .post("https://api.openai.com/v1/chat/completions")
             .bearer_auth(api_key)
             .json(&json!({
@@ -69,9 +71,8 @@ impl LocationParserService for OpenAI {
             "temperature": 0.0,
             "response_format": { "type": "text" },
             "max_tokens": 1000
-        }))
-            .send()
-            .await?;
+        }));
+        let res = LIMITER.send(request).await?;
 
         res.json::<OpenAIResponse>()
             .await
@@ -1,25 +1,70 @@
 mod db;
 mod geocoder;
-mod image_service;
 mod location_parser;
+mod ocr;
+mod renderer;
+mod storage;
 mod utils;
 mod web;
 
 use crate::db::redis::RedisProvider;
-use crate::geocoder::google::GoogleGeocoderProvider;
-use crate::image_service::S3;
+use crate::db::DatabaseConnection;
+use crate::geocoder::cache::{override_geocode, ttl_from_env, CachedGeocoderProvider};
+use crate::geocoder::composite::CompositeGeocoderProvider;
 use crate::location_parser::openai::OpenAI;
+use crate::ocr::tesseract::Tesseract;
+use crate::renderer::mutool::Mutool;
+use crate::storage::{LocalStorage, StorageService, S3};
+use crate::utils::auth::{Account, Role};
+use crate::utils::gazette::make_hash;
+use crate::utils::geometry::{display_geometry, CleanupSettings};
+use crate::utils::http::today;
+use crate::utils::maptypes::{GeoPosition, ShapeMode};
 use crate::utils::updater::ServiceConfig;
 use crate::utils::updater::Updater;
 use crate::web::start_server;
+use std::env;
+use std::io;
 
 #[tokio::main]
 async fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if run_command(&args).await {
+        return;
+    }
+
+    // Self-hosters without object storage get their files kept on disk and served by the app itself
+    let use_s3 = match env::var("STORAGE_BACKEND").as_deref() {
+        Ok("s3") => true,
+        Ok("local") => false,
+        _ => env::var("OBJECT_STORAGE_ACCESS_KEY_ID").is_ok_and(|key| !key.is_empty()),
+    };
+
+    if use_s3 {
+        match S3::from_env() {
+            Ok(storage_service) => run(storage_service).await,
+            Err(e) => eprintln!("{e}"),
+        }
+    } else {
+        run(LocalStorage).await;
+    }
+}
+
+async fn run<U>(storage_service: U)
+where
+    U: StorageService + Clone + Send + Sync + 'static,
+{
     let config = ServiceConfig {
         database_provider: RedisProvider,
-        image_service: S3,
+        storage_service,
         location_parser: OpenAI,
-        geocoder: GoogleGeocoderProvider,
+        geocoder: CachedGeocoderProvider {
+            provider: CompositeGeocoderProvider::from_env(),
+            database_provider: RedisProvider,
+            ttl_seconds: ttl_from_env(),
+        },
+        ocr_service: Tesseract,
+        page_renderer: Mutool,
     };
 
     let server = tokio::spawn(start_server(config.clone()));
@@ -29,8 +74,153 @@ async fn main() {
             uri: "http://www.gazette.vic.gov.au/gazette_bin/gazette_archives.cfm".to_string(),
             base_uri: "http://www.gazette.vic.gov.au".to_string(),
             config,
-        }.update().await;
+        }
+        .update()
+        .await;
     });
 
     let (_, _) = tokio::join!(update, server);
 }
+
+// Maintenance commands run instead of the server; anything else starts it
+async fn run_command(args: &[String]) -> bool {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args[..] {
+        ["override-geocode", input, area, latitude, longitude] => {
+            pin_geocode(input, area, latitude, longitude).await;
+        }
+        ["api-usage"] => api_usage(&today()).await,
+        ["api-usage", day] => api_usage(day).await,
+        ["set-shape", uri, shape] => set_shape(uri, shape).await,
+        ["add-user", name, role] => add_user(name, role).await,
+        ["remove-user", name] => remove_user(name).await,
+        ["reprocess-geometry"] => reprocess_geometry().await,
+        _ => return false,
+    }
+    true
+}
+
+// `override-geocode "<location>" "<area>" <latitude> <longitude>` pins a location the geocoders get wrong
+async fn pin_geocode(input: &str, area: &str, latitude: &str, longitude: &str) {
+    let (Ok(latitude), Ok(longitude)) = (latitude.parse(), longitude.parse()) else {
+        eprintln!("Latitude and longitude must be numbers");
+        return;
+    };
+    let position = GeoPosition {
+        latitude,
+        longitude,
+    };
+
+    match override_geocode(RedisProvider, input, area, position).await {
+        Ok(_) => println!("Pinned {input}, {area}"),
+        Err(e) => eprintln!("Could not save override: {e}"),
+    }
+}
+
+// `api-usage [YYYY-MM-DD]` shows how many calls were made to each paid API that day
+async fn api_usage(day: &str) {
+    let db = DatabaseConnection {
+        provider: RedisProvider,
+    };
+
+    match db.fetch_api_calls(day).await {
+        Ok(calls) => {
+            let mut calls: Vec<_> = calls.into_iter().collect();
+            calls.sort();
+            println!("API calls on {day}:");
+            for (api, count) in calls {
+                println!("  {api}: {count}");
+            }
+        }
+        Err(e) => eprintln!("Could not fetch API usage: {e}"),
+    }
+}
+
+// `set-shape <gazette uri> <convex|concave[:<concavity>]|ordered>` changes how one notice is drawn
+async fn set_shape(uri: &str, shape: &str) {
+    let Ok(shape) = shape.parse::<ShapeMode>() else {
+        eprintln!("Shape must be convex, concave, concave:<concavity> or ordered");
+        return;
+    };
+    let db = DatabaseConnection {
+        provider: RedisProvider,
+    };
+    let Ok(Some(mut gazette)) = db.fetch_entry(&make_hash(uri)).await else {
+        eprintln!("No notice found for {uri}");
+        return;
+    };
+
+    // Redrawn with the cleanup it was processed with, so only the shape changes
+    gazette.shape = shape;
+    let cleanup = gazette
+        .display
+        .as_ref()
+        .map_or_else(CleanupSettings::from_env, |display| display.cleanup.clone());
+    gazette.display = display_geometry(&gazette, &cleanup);
+    match db.save_gazette(&gazette).await {
+        Ok(_) => println!("Drawing {uri} as {shape:?}"),
+        Err(e) => eprintln!("Could not save shape: {e}"),
+    }
+}
+
+// `add-user <name> <viewer|editor|admin>` creates an admin account, or changes its role and
+// password; the password is read from standard input so it stays out of the shell history
+async fn add_user(name: &str, role: &str) {
+    let Ok(role) = role.parse::<Role>() else {
+        eprintln!("Role must be viewer, editor or admin");
+        return;
+    };
+    println!("Password for {name}:");
+    let mut password = String::new();
+    if io::stdin().read_line(&mut password).is_err() || password.trim().is_empty() {
+        eprintln!("No password given");
+        return;
+    }
+
+    let db = DatabaseConnection {
+        provider: RedisProvider,
+    };
+    let saved = match Account::new(name, role, password.trim_end_matches(['\r', '\n'])) {
+        Ok(account) => db.store_account(&account).await,
+        Err(e) => Err(e),
+    };
+    match saved {
+        Ok(_) => println!("Saved {name} as {role}"),
+        Err(e) => eprintln!("Could not save account: {e}"),
+    }
+}
+
+// `remove-user <name>` deletes an admin account, which signs out anyone signed in as it
+async fn remove_user(name: &str) {
+    let db = DatabaseConnection {
+        provider: RedisProvider,
+    };
+    match db.delete_account(name).await {
+        Ok(true) => println!("Removed {name}"),
+        Ok(false) => eprintln!("No account named {name}"),
+        Err(e) => eprintln!("Could not remove account: {e}"),
+    }
+}
+
+// `reprocess-geometry` redraws every notice with the current GEOMETRY_* cleanup settings, and
+// reads the operation area from any notice saved without one
+async fn reprocess_geometry() {
+    let db = DatabaseConnection {
+        provider: RedisProvider,
+    };
+    let Ok(gazettes) = db.fetch_entries().await else {
+        eprintln!("Could not fetch notices");
+        return;
+    };
+
+    let cleanup = CleanupSettings::from_env();
+    let mut saved = 0;
+    for mut gazette in gazettes {
+        gazette.fill_area().await;
+        gazette.display = display_geometry(&gazette, &cleanup);
+        if db.save_gazette(&gazette).await.is_ok() {
+            saved += 1;
+        }
+    }
+    println!("Redrew {saved} notices with {cleanup:?}");
+}
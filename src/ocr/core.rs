@@ -0,0 +1,71 @@
+use anyhow::Result;
+
+// Below this many printable characters a page is assumed to have no usable text layer
+const MIN_TEXT_LENGTH: usize = 20;
+const MIN_READABLE_RATIO: f64 = 0.75;
+
+#[async_trait::async_trait]
+pub trait OcrService {
+    async fn recognise(&self, image: Vec<u8>) -> Result<String>;
+}
+
+pub struct OcrRequest<T>
+where
+    T: OcrService,
+{
+    pub image: Vec<u8>,
+    pub service: T,
+}
+
+impl<T> OcrRequest<T>
+where
+    T: OcrService,
+{
+    pub async fn recognise(&self) -> Result<String> {
+        self.service.recognise(self.image.clone()).await
+    }
+}
+
+// Scanned gazettes either have no text layer at all, or one built from fonts lopdf can't map back
+// to characters; either way the result is no use to the location parser
+#[allow(clippy::cast_precision_loss)]
+pub fn is_garbled(text: &str) -> bool {
+    let printable = text.chars().filter(|c| !c.is_whitespace()).count();
+    if printable < MIN_TEXT_LENGTH {
+        return true;
+    }
+
+    let readable = text
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || c.is_ascii_punctuation())
+        .count();
+
+    (readable as f64 / printable as f64) < MIN_READABLE_RATIO
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ocr::mock::MockOcrService;
+
+    #[tokio::test]
+    async fn test_ocr_request() {
+        let request = OcrRequest {
+            image: b"Planned Operation in Melbourne CBD".to_vec(),
+            service: MockOcrService::new(true),
+        };
+
+        let text = request.recognise().await.unwrap();
+        assert_eq!(text, "Planned Operation in Melbourne CBD");
+    }
+
+    #[test]
+    fn test_is_garbled() {
+        assert!(is_garbled(""));
+        assert!(is_garbled("  \n \n  "));
+        assert!(is_garbled(&"\u{fffd}".repeat(40)));
+        assert!(!is_garbled(
+            "Control of Weapons Act 1990 - Declaration of Designated Area"
+        ));
+    }
+}
@@ -0,0 +1,40 @@
+use crate::ocr::OcrService;
+use anyhow::{anyhow, Result};
+
+// Treats the image bytes as the text printed on the page, so tests can build "scans" from strings
+#[derive(Clone, Copy, Debug)]
+pub struct MockOcrService {
+    should_succeed: bool,
+}
+
+impl MockOcrService {
+    pub fn new(should_succeed: bool) -> Self {
+        Self { should_succeed }
+    }
+}
+
+#[async_trait::async_trait]
+impl OcrService for MockOcrService {
+    async fn recognise(&self, image: Vec<u8>) -> Result<String> {
+        if self.should_succeed {
+            Ok(String::from_utf8_lossy(&image).into_owned())
+        } else {
+            Err(anyhow!("Mock recognition failed"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_mock_ocr() {
+        let service = MockOcrService::new(true);
+        let text = service.recognise(b"Southern Cross".to_vec()).await.unwrap();
+        assert_eq!(text, "Southern Cross");
+
+        let service = MockOcrService::new(false);
+        assert!(service.recognise(b"Southern Cross".to_vec()).await.is_err());
+    }
+}
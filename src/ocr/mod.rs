@@ -0,0 +1,8 @@
+pub mod core;
+#[cfg(test)]
+pub(crate) mod mock;
+pub mod tesseract;
+
+use core::OcrService;
+
+pub use core::{is_garbled, OcrRequest};
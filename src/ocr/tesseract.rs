@@ -0,0 +1,47 @@
+use crate::ocr::OcrService;
+use anyhow::{anyhow, Result};
+use std::env;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Tesseract;
+
+#[async_trait::async_trait]
+impl OcrService for Tesseract {
+    async fn recognise(&self, image: Vec<u8>) -> Result<String> {
+        let binary = env::var("TESSERACT_PATH")
+            .ok()
+            .filter(|path| !path.is_empty())
+            .unwrap_or_else(|| "tesseract".to_string());
+        let language = env::var("TESSERACT_LANG")
+            .ok()
+            .filter(|lang| !lang.is_empty())
+            .unwrap_or_else(|| "eng".to_string());
+
+        let mut child = Command::new(binary)
+            .args(["stdin", "stdout", "-l", &language])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| anyhow!("Failed to start tesseract: {}", e))?;
+
+        // Dropping stdin once written closes the pipe so tesseract knows the image is complete
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(&image).await?;
+        }
+
+        let output = child.wait_with_output().await?;
+        if !output.status.success() {
+            Err(anyhow!(
+                "Tesseract failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ))?;
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
@@ -0,0 +1,47 @@
+use anyhow::Result;
+
+#[async_trait::async_trait]
+pub trait PageRenderer {
+    // Returns the given (1-indexed) page of the PDF as a PNG
+    async fn render(&self, pdf: Vec<u8>, page: u32) -> Result<Vec<u8>>;
+}
+
+pub struct RenderRequest<T>
+where
+    T: PageRenderer,
+{
+    pub pdf: Vec<u8>,
+    pub page: u32,
+    pub service: T,
+}
+
+impl<T> RenderRequest<T>
+where
+    T: PageRenderer,
+{
+    pub async fn render(&self) -> Result<Vec<u8>> {
+        self.service.render(self.pdf.clone(), self.page).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::renderer::mock::MockPageRenderer;
+
+    #[tokio::test]
+    async fn test_render_request() {
+        let request = RenderRequest {
+            pdf: vec![],
+            page: 2,
+            service: MockPageRenderer::new(true),
+        };
+
+        let png = request.render().await.unwrap();
+        let image = image::load_from_memory(&png).unwrap();
+        assert_eq!(
+            (image.width(), image.height()),
+            (MockPageRenderer::WIDTH, MockPageRenderer::HEIGHT)
+        );
+    }
+}
@@ -0,0 +1,55 @@
+use crate::renderer::PageRenderer;
+use anyhow::{anyhow, Result};
+use image::{DynamicImage, ImageFormat, RgbImage};
+use std::io::Cursor;
+
+#[derive(Clone, Copy, Debug)]
+pub struct MockPageRenderer {
+    should_succeed: bool,
+}
+
+impl MockPageRenderer {
+    pub const WIDTH: u32 = 420;
+    pub const HEIGHT: u32 = 595;
+
+    pub fn new(should_succeed: bool) -> Self {
+        Self { should_succeed }
+    }
+}
+
+#[async_trait::async_trait]
+impl PageRenderer for MockPageRenderer {
+    async fn render(&self, _pdf: Vec<u8>, _page: u32) -> Result<Vec<u8>> {
+        if !self.should_succeed {
+            return Err(anyhow!("Mock render failed"));
+        }
+
+        // A red boundary on a grey page is about as much of a map as the tests need
+        let page = RgbImage::from_fn(Self::WIDTH, Self::HEIGHT, |x, y| {
+            if (x + y) % 2 == 0 {
+                image::Rgb([220, 20, 20])
+            } else {
+                image::Rgb([128, 128, 128])
+            }
+        });
+
+        let mut png = Cursor::new(Vec::new());
+        DynamicImage::ImageRgb8(page).write_to(&mut png, ImageFormat::Png)?;
+        Ok(png.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_mock_renderer() {
+        let renderer = MockPageRenderer::new(true);
+        let png = renderer.render(vec![], 1).await.unwrap();
+        assert!(image::load_from_memory_with_format(&png, ImageFormat::Png).is_ok());
+
+        let renderer = MockPageRenderer::new(false);
+        assert!(renderer.render(vec![], 1).await.is_err());
+    }
+}
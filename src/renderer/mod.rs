@@ -0,0 +1,8 @@
+pub mod core;
+#[cfg(test)]
+pub(crate) mod mock;
+pub mod mutool;
+
+use core::PageRenderer;
+
+pub use core::RenderRequest;
@@ -0,0 +1,63 @@
+use crate::renderer::PageRenderer;
+use anyhow::{anyhow, Result};
+use std::env;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::process::Command;
+
+// mutool won't read from stdin, so each render gets its own pair of scratch files
+static RENDER_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone, Copy, Debug)]
+pub struct Mutool;
+
+#[async_trait::async_trait]
+impl PageRenderer for Mutool {
+    async fn render(&self, pdf: Vec<u8>, page: u32) -> Result<Vec<u8>> {
+        let binary = env::var("MUTOOL_PATH")
+            .ok()
+            .filter(|path| !path.is_empty())
+            .unwrap_or_else(|| "mutool".to_string());
+        let resolution = env::var("RENDER_DPI")
+            .ok()
+            .and_then(|dpi| dpi.parse::<u32>().ok())
+            .unwrap_or(150);
+
+        let name = format!(
+            "gazette-render-{}-{}",
+            std::process::id(),
+            RENDER_COUNT.fetch_add(1, Ordering::SeqCst)
+        );
+        let input = env::temp_dir().join(format!("{name}.pdf"));
+        let output = env::temp_dir().join(format!("{name}.png"));
+
+        tokio::fs::write(&input, &pdf).await?;
+
+        let result = Command::new(binary)
+            .arg("draw")
+            .arg("-q")
+            .args(["-r", &resolution.to_string()])
+            .args(["-F", "png"])
+            .arg("-o")
+            .arg(&output)
+            .arg(&input)
+            .arg(page.to_string())
+            .output()
+            .await;
+
+        let png = match result {
+            Ok(result) if result.status.success() => {
+                tokio::fs::read(&output).await.map_err(Into::into)
+            }
+            Ok(result) => Err(anyhow!(
+                "mutool failed: {}",
+                String::from_utf8_lossy(&result.stderr)
+            )),
+            Err(e) => Err(anyhow!("Failed to start mutool: {}", e)),
+        };
+
+        let _ = tokio::fs::remove_file(&input).await;
+        let _ = tokio::fs::remove_file(&output).await;
+
+        png
+    }
+}
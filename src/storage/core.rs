@@ -0,0 +1,109 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+// Objects keyed by a hash of their bytes never change, so caches can hold on to them
+pub const IMMUTABLE_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StoredBlob {
+    pub data: Vec<u8>,
+    pub content_type: String,
+    pub cache_control: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredObject {
+    pub key: String,
+    pub url: String,
+    pub size: usize,
+    pub mime: String,
+}
+
+pub struct Blob<T>
+where
+    T: StorageService + Clone,
+{
+    pub key: String,
+    pub data: Vec<u8>,
+    pub content_type: String,
+    pub cache_control: Option<String>,
+    pub service: T,
+}
+
+// Keys are relative paths like "abc123.jpg"; `url` is where the object is served from, either
+// relative to OBJECT_STORAGE_URL or absolute
+#[async_trait::async_trait]
+#[allow(dead_code)]
+pub trait StorageService {
+    async fn put(
+        &self,
+        key: &str,
+        data: Vec<u8>,
+        content_type: &str,
+        cache_control: Option<&str>,
+    ) -> Result<StoredObject>;
+    async fn get(&self, key: &str) -> Result<Option<StoredBlob>>;
+    async fn delete(&self, key: &str) -> Result<bool>;
+    async fn exists(&self, key: &str) -> Result<bool>;
+    async fn list(&self, prefix: &str) -> Result<Vec<String>>;
+    fn url(&self, key: &str) -> String;
+}
+
+impl<T> Blob<T>
+where
+    T: StorageService + Clone,
+{
+    // Keys are content hashes, so an existing object already holds these bytes
+    pub async fn upload(&self) -> Result<StoredObject> {
+        if self.service.exists(&self.key).await? {
+            return Ok(StoredObject {
+                key: self.key.clone(),
+                url: self.service.url(&self.key),
+                size: self.data.len(),
+                mime: self.content_type.clone(),
+            });
+        }
+
+        self.service
+            .put(
+                &self.key,
+                self.data.clone(),
+                &self.content_type,
+                self.cache_control.as_deref(),
+            )
+            .await
+    }
+}
+
+#[async_trait::async_trait]
+impl StorageService for () {
+    async fn put(
+        &self,
+        _key: &str,
+        _data: Vec<u8>,
+        _content_type: &str,
+        _cache_control: Option<&str>,
+    ) -> Result<StoredObject> {
+        Err(anyhow!("This should never happen"))
+    }
+
+    async fn get(&self, _key: &str) -> Result<Option<StoredBlob>> {
+        Err(anyhow!("This should never happen"))
+    }
+
+    async fn delete(&self, _key: &str) -> Result<bool> {
+        Err(anyhow!("This should never happen"))
+    }
+
+    async fn exists(&self, _key: &str) -> Result<bool> {
+        Err(anyhow!("This should never happen"))
+    }
+
+    async fn list(&self, _prefix: &str) -> Result<Vec<String>> {
+        Err(anyhow!("This should never happen"))
+    }
+
+    fn url(&self, key: &str) -> String {
+        key.to_string()
+    }
+}
@@ -0,0 +1,263 @@
+use crate::storage::core::{StorageService, StoredBlob, StoredObject};
+use anyhow::{anyhow, Result};
+use std::env;
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
+
+// Content types (and cache headers, on a second line) live in a mirror of the storage tree so
+// the stored files stay byte-for-byte
+const METADATA_DIR: &str = ".meta";
+const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+
+#[derive(Clone, Copy, Debug)]
+pub struct LocalStorage;
+
+#[allow(dead_code)]
+impl LocalStorage {
+    fn root() -> PathBuf {
+        env::var("LOCAL_STORAGE_PATH")
+            .ok()
+            .filter(|path| !path.is_empty())
+            .map_or_else(|| PathBuf::from("./storage"), PathBuf::from)
+    }
+
+    // Keys come from URLs as well as from us, so anything that could escape the root is refused
+    fn relative_path(key: &str) -> Result<PathBuf> {
+        let mut path = PathBuf::new();
+
+        for component in Path::new(key).components() {
+            match component {
+                Component::Normal(part) if part != METADATA_DIR => path.push(part),
+                Component::CurDir | Component::RootDir => {}
+                _ => Err(anyhow!("Invalid storage key {key}"))?,
+            }
+        }
+
+        if path.as_os_str().is_empty() {
+            Err(anyhow!("Invalid storage key {key}"))?;
+        }
+        Ok(path)
+    }
+
+    async fn put_in(
+        root: &Path,
+        key: &str,
+        data: Vec<u8>,
+        content_type: &str,
+        cache_control: Option<&str>,
+    ) -> Result<()> {
+        let relative = Self::relative_path(key)?;
+        let path = root.join(&relative);
+        let metadata = root.join(METADATA_DIR).join(&relative);
+
+        for parent in [path.parent(), metadata.parent()].into_iter().flatten() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        tokio::fs::write(&path, data).await?;
+        let metadata_contents = match cache_control {
+            Some(cache_control) => format!("{content_type}\n{cache_control}"),
+            None => content_type.to_string(),
+        };
+        tokio::fs::write(&metadata, metadata_contents).await?;
+        Ok(())
+    }
+
+    async fn get_in(root: &Path, key: &str) -> Result<Option<StoredBlob>> {
+        let relative = Self::relative_path(key)?;
+
+        let data = match tokio::fs::read(root.join(&relative)).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => Err(e)?,
+        };
+        let metadata = tokio::fs::read_to_string(root.join(METADATA_DIR).join(&relative))
+            .await
+            .unwrap_or_else(|_| DEFAULT_CONTENT_TYPE.to_string());
+        let mut lines = metadata.lines();
+
+        Ok(Some(StoredBlob {
+            data,
+            content_type: lines.next().unwrap_or(DEFAULT_CONTENT_TYPE).to_string(),
+            cache_control: lines.next().map(str::to_string),
+        }))
+    }
+
+    async fn delete_in(root: &Path, key: &str) -> Result<bool> {
+        let relative = Self::relative_path(key)?;
+
+        match tokio::fs::remove_file(root.join(&relative)).await {
+            Ok(()) => {
+                let _ = tokio::fs::remove_file(root.join(METADATA_DIR).join(&relative)).await;
+                Ok(true)
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn exists_in(root: &Path, key: &str) -> Result<bool> {
+        let path = root.join(Self::relative_path(key)?);
+        Ok(tokio::fs::try_exists(&path).await? && path.is_file())
+    }
+
+    async fn list_in(root: &Path, prefix: &str) -> Result<Vec<String>> {
+        let prefix = prefix.trim_start_matches("./").trim_start_matches('/');
+        let mut keys = Vec::new();
+        let mut directories = vec![root.to_path_buf()];
+
+        while let Some(directory) = directories.pop() {
+            let mut entries = match tokio::fs::read_dir(&directory).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => Err(e)?,
+            };
+
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                if entry.file_type().await?.is_dir() {
+                    if path != root.join(METADATA_DIR) {
+                        directories.push(path);
+                    }
+                    continue;
+                }
+
+                let key = path
+                    .strip_prefix(root)?
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                if key.starts_with(prefix) {
+                    keys.push(key);
+                }
+            }
+        }
+
+        keys.sort();
+        Ok(keys)
+    }
+}
+
+#[async_trait::async_trait]
+impl StorageService for LocalStorage {
+    async fn put(
+        &self,
+        key: &str,
+        data: Vec<u8>,
+        content_type: &str,
+        cache_control: Option<&str>,
+    ) -> Result<StoredObject> {
+        let size = data.len();
+        Self::put_in(&Self::root(), key, data, content_type, cache_control).await?;
+
+        Ok(StoredObject {
+            key: key.to_string(),
+            url: self.url(key),
+            size,
+            mime: content_type.to_string(),
+        })
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<StoredBlob>> {
+        Self::get_in(&Self::root(), key).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<bool> {
+        Self::delete_in(&Self::root(), key).await
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool> {
+        Self::exists_in(&Self::root(), key).await
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        Self::list_in(&Self::root(), prefix).await
+    }
+
+    fn url(&self, key: &str) -> String {
+        key.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_root(name: &str) -> PathBuf {
+        let root = env::temp_dir().join(format!("local-storage-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        root
+    }
+
+    #[tokio::test]
+    async fn test_local_storage_round_trip() {
+        let root = scratch_root("round-trip");
+
+        LocalStorage::put_in(
+            &root,
+            "./abc.jpg",
+            vec![1, 2, 3],
+            "image/jpeg",
+            Some("max-age=60"),
+        )
+        .await
+        .unwrap();
+        LocalStorage::put_in(&root, "pdf/abc.pdf", vec![4, 5], "application/pdf", None)
+            .await
+            .unwrap();
+
+        let blob = LocalStorage::get_in(&root, "abc.jpg")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(blob.data, vec![1, 2, 3]);
+        assert_eq!(blob.content_type, "image/jpeg");
+        assert_eq!(blob.cache_control.as_deref(), Some("max-age=60"));
+
+        let blob = LocalStorage::get_in(&root, "pdf/abc.pdf")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(blob.content_type, "application/pdf");
+        assert_eq!(blob.cache_control, None);
+
+        assert!(LocalStorage::exists_in(&root, "pdf/abc.pdf").await.unwrap());
+        assert!(!LocalStorage::exists_in(&root, "pdf").await.unwrap());
+        assert!(!LocalStorage::exists_in(&root, "missing.jpg").await.unwrap());
+
+        assert_eq!(
+            LocalStorage::list_in(&root, "").await.unwrap(),
+            vec!["abc.jpg".to_string(), "pdf/abc.pdf".to_string()]
+        );
+        assert_eq!(
+            LocalStorage::list_in(&root, "pdf/").await.unwrap(),
+            vec!["pdf/abc.pdf".to_string()]
+        );
+
+        assert!(LocalStorage::delete_in(&root, "abc.jpg").await.unwrap());
+        assert!(!LocalStorage::delete_in(&root, "abc.jpg").await.unwrap());
+        assert!(LocalStorage::get_in(&root, "abc.jpg")
+            .await
+            .unwrap()
+            .is_none());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_local_storage_rejects_escaping_keys() {
+        let root = scratch_root("escaping");
+
+        assert!(
+            LocalStorage::put_in(&root, "../abc.jpg", vec![], "image/jpeg", None)
+                .await
+                .is_err()
+        );
+        assert!(LocalStorage::get_in(&root, "pdf/../../etc/passwd")
+            .await
+            .is_err());
+        assert!(LocalStorage::get_in(&root, ".meta/abc.jpg").await.is_err());
+        assert!(LocalStorage::get_in(&root, "./").await.is_err());
+    }
+}
@@ -0,0 +1,180 @@
+use crate::storage::core::StoredBlob;
+use crate::storage::{Blob, StorageService, StoredObject, IMMUTABLE_CACHE_CONTROL};
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+#[derive(Clone)]
+pub(crate) struct MockStorageService {
+    should_succeed: bool,
+    storage: Arc<RwLock<HashMap<String, StoredBlob>>>,
+    upload_count: Arc<AtomicUsize>,
+}
+
+impl MockStorageService {
+    pub fn new(should_succeed: bool) -> Self {
+        Self {
+            should_succeed,
+            storage: Arc::new(RwLock::new(HashMap::new())),
+            upload_count: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn upload_count(&self) -> usize {
+        self.upload_count.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait::async_trait]
+impl StorageService for MockStorageService {
+    async fn put(
+        &self,
+        key: &str,
+        data: Vec<u8>,
+        content_type: &str,
+        cache_control: Option<&str>,
+    ) -> Result<StoredObject> {
+        self.upload_count.fetch_add(1, Ordering::SeqCst);
+
+        if self.should_succeed {
+            let size = data.len();
+            let mut storage = self.storage.write().await;
+            storage.insert(
+                key.to_string(),
+                StoredBlob {
+                    data,
+                    content_type: content_type.to_string(),
+                    cache_control: cache_control.map(str::to_string),
+                },
+            );
+            Ok(StoredObject {
+                key: key.to_string(),
+                url: self.url(key),
+                size,
+                mime: content_type.to_string(),
+            })
+        } else {
+            Err(anyhow!("Mock upload failed"))
+        }
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<StoredBlob>> {
+        let storage = self.storage.read().await;
+        Ok(storage.get(key).cloned())
+    }
+
+    async fn delete(&self, key: &str) -> Result<bool> {
+        let mut storage = self.storage.write().await;
+        Ok(storage.remove(key).is_some())
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool> {
+        let storage = self.storage.read().await;
+        Ok(storage.contains_key(key))
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        let storage = self.storage.read().await;
+        let mut keys: Vec<String> = storage
+            .keys()
+            .filter(|key| key.starts_with(prefix))
+            .cloned()
+            .collect();
+        keys.sort();
+        Ok(keys)
+    }
+
+    fn url(&self, key: &str) -> String {
+        format!("mock_url/{key}")
+    }
+}
+
+#[tokio::test]
+async fn test_successful_upload() {
+    let service = MockStorageService::new(true);
+    let blob = Blob {
+        key: "test.jpg".to_string(),
+        data: vec![1, 2, 3],
+        content_type: "image/jpeg".to_string(),
+        cache_control: Some(IMMUTABLE_CACHE_CONTROL.to_string()),
+        service: service.clone(),
+    };
+
+    let result = blob.upload().await;
+    assert!(result.is_ok());
+    assert_eq!(service.upload_count(), 1);
+    assert_eq!(
+        result.unwrap(),
+        StoredObject {
+            key: "test.jpg".to_string(),
+            url: "mock_url/test.jpg".to_string(),
+            size: 3,
+            mime: "image/jpeg".to_string(),
+        }
+    );
+
+    let stored = service.get("test.jpg").await.unwrap().unwrap();
+    assert_eq!(
+        stored.cache_control.as_deref(),
+        Some(IMMUTABLE_CACHE_CONTROL)
+    );
+}
+
+#[tokio::test]
+async fn test_existing_upload_is_skipped() {
+    let service = MockStorageService::new(true);
+    let blob = Blob {
+        key: "test.jpg".to_string(),
+        data: vec![1, 2, 3],
+        content_type: "image/jpeg".to_string(),
+        cache_control: None,
+        service: service.clone(),
+    };
+
+    let first = blob.upload().await.unwrap();
+    let second = blob.upload().await.unwrap();
+    assert_eq!(first, second);
+    assert_eq!(service.upload_count(), 1);
+}
+
+#[tokio::test]
+async fn test_failed_upload() {
+    let service = MockStorageService::new(false);
+    let blob = Blob {
+        key: "test.jpg".to_string(),
+        data: vec![1, 2, 3],
+        content_type: "image/jpeg".to_string(),
+        cache_control: None,
+        service: service.clone(),
+    };
+
+    let result = blob.upload().await;
+    assert!(result.is_err());
+    assert_eq!(service.upload_count(), 1);
+}
+
+#[tokio::test]
+async fn test_stored_blobs() {
+    let service = MockStorageService::new(true);
+    service
+        .put("pdf/test.pdf", vec![1, 2, 3], "application/pdf", None)
+        .await
+        .unwrap();
+    service
+        .put("test.jpg", vec![4], "image/jpeg", None)
+        .await
+        .unwrap();
+
+    let blob = service.get("pdf/test.pdf").await.unwrap().unwrap();
+    assert_eq!(blob.data, vec![1, 2, 3]);
+    assert_eq!(blob.content_type, "application/pdf");
+
+    assert!(service.exists("test.jpg").await.unwrap());
+    assert_eq!(service.list("pdf/").await.unwrap(), vec!["pdf/test.pdf"]);
+
+    assert!(service.delete("test.jpg").await.unwrap());
+    assert!(!service.exists("test.jpg").await.unwrap());
+    assert!(service.get("test.jpg").await.unwrap().is_none());
+}
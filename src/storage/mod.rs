@@ -0,0 +1,9 @@
+mod core;
+mod local;
+#[cfg(test)]
+pub(crate) mod mock;
+mod s3;
+
+pub use core::{Blob, StorageService, StoredObject, IMMUTABLE_CACHE_CONTROL};
+pub use local::LocalStorage;
+pub use s3::S3;
@@ -0,0 +1,361 @@
+use crate::storage::core::{StorageService, StoredBlob, StoredObject};
+use anyhow::{anyhow, Result};
+use s3::creds::Credentials;
+use s3::error::S3Error;
+use s3::Region;
+use std::env;
+use std::sync::Arc;
+
+const DEFAULT_BUCKET: &str = "vicpolsearches";
+const DEFAULT_REGION: &str = "ap-southeast-2";
+
+// The configuration is read once, when the app starts
+#[derive(Clone, Debug)]
+pub struct S3 {
+    config: Arc<S3Config>,
+}
+
+// Everything needed to talk to AWS or any S3-compatible service (MinIO, R2, B2)
+#[derive(Clone, Debug, PartialEq)]
+struct S3Config {
+    access_key: String,
+    secret_key: String,
+    bucket: String,
+    region: Region,
+    path_style: bool,
+    prefix: String,
+    public_url: Option<String>,
+}
+
+impl S3Config {
+    fn from_env() -> Result<Self> {
+        Self::from_lookup(|name| env::var(name).ok())
+    }
+
+    fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let var = |name: &str| lookup(name).filter(|value| !value.trim().is_empty());
+
+        let (Some(access_key), Some(secret_key)) = (
+            var("OBJECT_STORAGE_ACCESS_KEY_ID"),
+            var("OBJECT_STORAGE_SECRET_ACCESS_KEY"),
+        ) else {
+            return Err(anyhow!("No object storage credentials provided"));
+        };
+
+        let region_name = var("OBJECT_STORAGE_REGION").unwrap_or_else(|| DEFAULT_REGION.into());
+        let region = match var("OBJECT_STORAGE_ENDPOINT") {
+            Some(endpoint) => Region::Custom {
+                region: region_name,
+                endpoint: endpoint.trim_end_matches('/').to_string(),
+            },
+            None => region_name.parse()?,
+        };
+
+        Ok(Self {
+            access_key,
+            secret_key,
+            bucket: var("OBJECT_STORAGE_BUCKET").unwrap_or_else(|| DEFAULT_BUCKET.into()),
+            region,
+            path_style: var("OBJECT_STORAGE_PATH_STYLE")
+                .is_some_and(|value| matches!(value.to_lowercase().as_str(), "1" | "true" | "yes")),
+            prefix: var("OBJECT_STORAGE_PREFIX")
+                .map(|prefix| prefix.trim_matches('/').to_string())
+                .unwrap_or_default(),
+            public_url: var("OBJECT_STORAGE_PUBLIC_URL"),
+        })
+    }
+
+    fn bucket(&self) -> Result<Box<s3::Bucket>> {
+        let bucket = s3::Bucket::new(
+            &self.bucket,
+            self.region.clone(),
+            Credentials {
+                access_key: Some(self.access_key.clone()),
+                secret_key: Some(self.secret_key.clone()),
+                security_token: None,
+                session_token: None,
+                expiration: None,
+            },
+        )?;
+
+        if self.path_style {
+            return Ok(bucket.with_path_style());
+        }
+        Ok(bucket)
+    }
+
+    fn object_key(&self, key: &str) -> String {
+        let key = key.trim_start_matches("./").trim_start_matches('/');
+
+        if self.prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}/{key}", self.prefix)
+        }
+    }
+
+    fn strip_prefix<'a>(&self, object_key: &'a str) -> &'a str {
+        if self.prefix.is_empty() {
+            return object_key;
+        }
+
+        object_key
+            .strip_prefix(self.prefix.as_str())
+            .and_then(|key| key.strip_prefix('/'))
+            .unwrap_or(object_key)
+    }
+
+    // The app serves objects at the paths `public_url` gives out when there's no public URL, and
+    // those already have the prefix
+    fn served_key(&self, key: &str) -> String {
+        let key = key.trim_start_matches("./").trim_start_matches('/');
+        self.object_key(self.strip_prefix(key))
+    }
+
+    // Without a template the object key is returned as-is, to be served from under OBJECT_STORAGE_URL
+    fn public_url(&self, key: &str) -> String {
+        let object_key = self.object_key(key);
+
+        match &self.public_url {
+            Some(template) if template.contains("{key}") => template.replace("{key}", &object_key),
+            Some(base) => format!("{}/{object_key}", base.trim_end_matches('/')),
+            None => object_key,
+        }
+    }
+}
+
+impl S3 {
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            config: Arc::new(S3Config::from_env()?),
+        })
+    }
+
+    fn is_not_found(error: &S3Error) -> bool {
+        matches!(error, S3Error::HttpFailWithBody(404, _))
+    }
+}
+
+#[async_trait::async_trait]
+impl StorageService for S3 {
+    async fn put(
+        &self,
+        key: &str,
+        data: Vec<u8>,
+        content_type: &str,
+        cache_control: Option<&str>,
+    ) -> Result<StoredObject> {
+        let config = &self.config;
+        let mut bucket = config.bucket()?;
+
+        if let Some(cache_control) = cache_control {
+            bucket.add_header("Cache-Control", cache_control);
+        }
+
+        let _ = bucket
+            .put_object_with_content_type(config.object_key(key), &data, content_type)
+            .await?;
+        Ok(StoredObject {
+            key: key.to_string(),
+            url: config.public_url(key),
+            size: data.len(),
+            mime: content_type.to_string(),
+        })
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<StoredBlob>> {
+        let config = &self.config;
+        let bucket = config.bucket()?;
+
+        match bucket.get_object(config.served_key(key)).await {
+            Ok(response) => {
+                let headers = response.headers();
+                let content_type = headers
+                    .get("content-type")
+                    .cloned()
+                    .unwrap_or_else(|| "application/octet-stream".to_string());
+                Ok(Some(StoredBlob {
+                    data: response.bytes().to_vec(),
+                    content_type,
+                    cache_control: headers.get("cache-control").cloned(),
+                }))
+            }
+            Err(e) if Self::is_not_found(&e) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<bool> {
+        if !self.exists(key).await? {
+            return Ok(false);
+        }
+
+        let config = &self.config;
+        let bucket = config.bucket()?;
+        let _ = bucket.delete_object(config.object_key(key)).await?;
+        Ok(true)
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool> {
+        let config = &self.config;
+        let bucket = config.bucket()?;
+
+        match bucket.head_object(config.object_key(key)).await {
+            Ok((_, status)) => Ok(status == 200),
+            Err(e) if Self::is_not_found(&e) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        let config = &self.config;
+        let bucket = config.bucket()?;
+        let pages = bucket.list(config.object_key(prefix), None).await?;
+
+        Ok(pages
+            .into_iter()
+            .flat_map(|page| page.contents)
+            .map(|object| config.strip_prefix(&object.key).to_string())
+            .collect())
+    }
+
+    fn url(&self, key: &str) -> String {
+        self.config.public_url(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn config_from(vars: &[(&str, &str)]) -> Result<S3Config> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| ((*name).to_string(), (*value).to_string()))
+            .collect();
+        S3Config::from_lookup(|name| vars.get(name).cloned())
+    }
+
+    const CREDENTIALS: [(&str, &str); 2] = [
+        ("OBJECT_STORAGE_ACCESS_KEY_ID", "access"),
+        ("OBJECT_STORAGE_SECRET_ACCESS_KEY", "secret"),
+    ];
+
+    #[test]
+    fn test_default_config() {
+        let config = config_from(&CREDENTIALS).unwrap();
+
+        assert_eq!(config.bucket, "vicpolsearches");
+        assert_eq!(config.region, Region::ApSoutheast2);
+        assert!(!config.path_style);
+        assert_eq!(config.object_key("./abc.jpg"), "abc.jpg");
+        assert_eq!(config.public_url("./abc.jpg"), "abc.jpg");
+
+        assert!(config_from(&[]).is_err());
+        assert!(config_from(&[("OBJECT_STORAGE_ACCESS_KEY_ID", "access")]).is_err());
+    }
+
+    #[test]
+    fn test_minio_config() {
+        let mut vars = CREDENTIALS.to_vec();
+        vars.extend([
+            ("OBJECT_STORAGE_ENDPOINT", "http://localhost:9000/"),
+            ("OBJECT_STORAGE_REGION", "us-east-1"),
+            ("OBJECT_STORAGE_BUCKET", "gazettes"),
+            ("OBJECT_STORAGE_PATH_STYLE", "true"),
+            ("OBJECT_STORAGE_PREFIX", "/maps/"),
+        ]);
+        let config = config_from(&vars).unwrap();
+
+        assert_eq!(
+            config.region,
+            Region::Custom {
+                region: "us-east-1".to_string(),
+                endpoint: "http://localhost:9000".to_string(),
+            }
+        );
+        assert_eq!(config.bucket, "gazettes");
+        assert!(config.path_style);
+        assert_eq!(config.object_key("./abc.jpg"), "maps/abc.jpg");
+        assert_eq!(config.strip_prefix("maps/abc.jpg"), "abc.jpg");
+        assert_eq!(config.strip_prefix("mapsabc.jpg"), "mapsabc.jpg");
+
+        let bucket = config.bucket().unwrap();
+        assert!(bucket.is_path_style());
+        assert_eq!(bucket.url(), "http://localhost:9000/gazettes");
+    }
+
+    #[test]
+    fn test_public_url() {
+        let mut vars = CREDENTIALS.to_vec();
+        vars.extend([
+            ("OBJECT_STORAGE_REGION", "auto"),
+            (
+                "OBJECT_STORAGE_ENDPOINT",
+                "https://account.r2.cloudflarestorage.com",
+            ),
+            ("OBJECT_STORAGE_PREFIX", "maps"),
+            (
+                "OBJECT_STORAGE_PUBLIC_URL",
+                "https://cdn.example.org/{key}?v=1",
+            ),
+        ]);
+        let config = config_from(&vars).unwrap();
+        assert_eq!(
+            config.public_url("./abc.jpg"),
+            "https://cdn.example.org/maps/abc.jpg?v=1"
+        );
+
+        vars.pop();
+        vars.push(("OBJECT_STORAGE_PUBLIC_URL", "https://pub-1234.r2.dev/"));
+        let config = config_from(&vars).unwrap();
+        assert_eq!(
+            config.public_url("abc.jpg"),
+            "https://pub-1234.r2.dev/maps/abc.jpg"
+        );
+    }
+
+    #[test]
+    fn test_prefix_without_public_url() {
+        let mut vars = CREDENTIALS.to_vec();
+        vars.push(("OBJECT_STORAGE_PREFIX", "maps"));
+        let config = config_from(&vars).unwrap();
+
+        // Linked as /storage/maps/abc.jpg, which is read back from the same object
+        let url = config.public_url("abc.jpg");
+        assert_eq!(url, "maps/abc.jpg");
+        assert_eq!(config.served_key(&url), "maps/abc.jpg");
+        assert_eq!(config.served_key("abc.jpg"), "maps/abc.jpg");
+    }
+
+    // Run against a throwaway bucket, e.g. MinIO started with
+    // `docker run -p 9000:9000 minio/minio server /data` and OBJECT_STORAGE_* pointed at it
+    #[tokio::test]
+    #[ignore = "needs an S3-compatible service configured through OBJECT_STORAGE_* variables"]
+    async fn test_s3_round_trip() {
+        let service = S3::from_env().unwrap();
+        let key = "test/round-trip.txt";
+
+        let object = service
+            .put(key, b"round trip".to_vec(), "text/plain", Some("no-cache"))
+            .await
+            .unwrap();
+        assert_eq!(object.url, service.url(key));
+        assert_eq!(object.size, 10);
+
+        let blob = service.get(key).await.unwrap().unwrap();
+        assert_eq!(blob.data, b"round trip".to_vec());
+        assert_eq!(blob.content_type, "text/plain");
+        assert_eq!(blob.cache_control.as_deref(), Some("no-cache"));
+        assert!(service.exists(key).await.unwrap());
+        assert!(service
+            .list("test/")
+            .await
+            .unwrap()
+            .contains(&key.to_string()));
+
+        assert!(service.delete(key).await.unwrap());
+        assert!(!service.exists(key).await.unwrap());
+    }
+}
@@ -0,0 +1,429 @@
+use crate::db::core::DatabaseProvider;
+use crate::db::DatabaseConnection;
+use crate::utils::gazette::{make_hash, Gazette};
+use crate::utils::http::{client, is_web_address};
+use anyhow::Result;
+use chrono::{Datelike, Local, NaiveDate, Utc};
+use redis_macros::{FromRedisValue, ToRedisArgs};
+use regex::Regex;
+use reqwest::Url;
+use select::document::Document;
+use select::predicate::Name;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::env;
+
+const DEFAULT_ANNOUNCEMENTS_URI: &str = "https://www.police.vic.gov.au/news";
+// Left off a place's name when comparing, since the gazette and the geocoder rarely use them
+const GENERIC_AREA_WORDS: [&str; 6] = ["cbd", "city", "centre", "area", "precinct", "region"];
+
+// A designated area as Victoria Police announced it on their website
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, FromRedisValue, ToRedisArgs)]
+pub struct Announcement {
+    pub uri: String,
+    pub title: String,
+    pub area: Option<String>,
+    pub start: Option<NaiveDate>,
+    pub end: Option<NaiveDate>,
+    // Seconds since the epoch
+    pub scraped_at: i64,
+}
+
+impl Announcement {
+    // Only a notice published in the gazette, naming the same place, for some of the same days
+    pub fn matches(&self, gazette: &Gazette) -> bool {
+        gazette.source.is_gazetted() && self.same_time(gazette) && self.same_area(gazette)
+    }
+
+    fn same_time(&self, gazette: &Gazette) -> bool {
+        match (self.start, self.end, gazette.start, gazette.end) {
+            (Some(start), Some(end), Some(gazette_start), Some(gazette_end)) => {
+                start <= gazette_end && gazette_start <= end
+            }
+            _ => false,
+        }
+    }
+
+    // Compared with the area the notice names, or failing that the suburbs its places were found in
+    fn same_area(&self, gazette: &Gazette) -> bool {
+        let Some(place) = self
+            .area
+            .as_deref()
+            .map(place_name)
+            .filter(|place| !place.is_empty())
+        else {
+            return false;
+        };
+
+        let named = gazette
+            .area
+            .as_deref()
+            .map(place_name)
+            .filter(|name| !name.is_empty())
+            .is_some_and(|name| contains_words(&name, &place) || contains_words(&place, &name));
+        named
+            || gazette
+                .geocoded
+                .iter()
+                .filter_map(|point| point.result.formatted_address.as_deref())
+                .any(|address| contains_words(&words(address), &place))
+    }
+}
+
+// The announcements no gazette was found for, which is the point of collecting them
+pub fn unmatched<'a>(
+    announcements: &'a [Announcement],
+    gazettes: &[Gazette],
+) -> Vec<&'a Announcement> {
+    announcements
+        .iter()
+        .filter(|announcement| !gazettes.iter().any(|gazette| announcement.matches(gazette)))
+        .collect()
+}
+
+// Lowercase words only, so punctuation and spacing don't stop names matching
+fn words(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn place_name(area: &str) -> String {
+    words(area)
+        .split(' ')
+        .filter(|word| !GENERIC_AREA_WORDS.contains(word))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn contains_words(haystack: &str, needle: &str) -> bool {
+    !needle.is_empty() && format!(" {haystack} ").contains(&format!(" {needle} "))
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// The capitalised place after wording like "designated area declared in" or "Designated Area in
+// place for"
+fn area_from(text: &str) -> Option<String> {
+    let area_regex = Regex::new(
+        r"(?i:designated\s+(?:search\s+)?areas?(?:\s+(?:has\s+been|have\s+been|will\s+be|is|are))?(?:\s+(?:declared|in\s+place|operating))?\s+(?:in|for|at|across|around))\s+(?:the\s+)?([A-Z][\w'-]*(?:\s+[A-Z][\w'-]*)*)",
+    )
+    .ok()?;
+
+    area_regex
+        .captures(text)
+        .and_then(|cap| cap.get(1))
+        .map(|area| area.as_str().to_string())
+}
+
+// Announcements often give the year only once, so the others take it from the nearest date that
+// has one; "30 December to 2 January 2026" runs over the new year, so a date falling after the
+// dated one it comes before is from the year before, and one falling before the dated one it
+// follows is from the year after
+fn announced_dates(text: &str) -> Result<Vec<NaiveDate>> {
+    let date_regex = Regex::new(r"\b(\d{1,2}\s+[ADFJMNOS][a-z]+)(?:\s+(\d{4}))?\b")?;
+    let parse = |day_month: &str, year: i32| {
+        NaiveDate::parse_from_str(&format!("{day_month} {year}"), "%e %B %Y").ok()
+    };
+
+    let found: Vec<(&str, Option<NaiveDate>)> = date_regex
+        .captures_iter(text)
+        .filter_map(|cap| {
+            let day_month = cap.get(1)?.as_str();
+            let dated = cap
+                .get(2)
+                .and_then(|year| year.as_str().parse().ok())
+                .and_then(|year| parse(day_month, year));
+            Some((day_month, dated))
+        })
+        .collect();
+
+    Ok(found
+        .iter()
+        .enumerate()
+        .filter_map(|(index, (day_month, dated))| {
+            if dated.is_some() {
+                return *dated;
+            }
+            let next = found[index + 1..].iter().find_map(|(_, dated)| *dated);
+            let previous = found[..index].iter().rev().find_map(|(_, dated)| *dated);
+
+            match (next, previous) {
+                (Some(next), _) => parse(day_month, next.year())
+                    .filter(|date| *date <= next)
+                    .or_else(|| parse(day_month, next.year() - 1)),
+                (None, Some(previous)) => parse(day_month, previous.year())
+                    .filter(|date| *date >= previous)
+                    .or_else(|| parse(day_month, previous.year() + 1)),
+                (None, None) => parse(day_month, Local::now().year()),
+            }
+        })
+        .collect())
+}
+
+// Links on the news page that announce a designated area somewhere, with their addresses made
+// absolute; general pages about designated areas don't name a place, so they're left out, as are
+// links that aren't to a web page
+pub fn parse_listing(html: &str, base_uri: &str) -> Vec<(String, String)> {
+    let Ok(base_uri) = Url::parse(base_uri) else {
+        return vec![];
+    };
+    let mut seen = HashSet::new();
+
+    Document::from(html)
+        .find(Name("a"))
+        .filter_map(|element| {
+            let title = collapse_whitespace(&element.text());
+            area_from(&title)?;
+            let uri = base_uri.join(element.attr("href")?).ok()?.to_string();
+            is_web_address(&uri).then_some((title, uri))
+        })
+        .filter(|(_, uri)| seen.insert(uri.clone()))
+        .collect()
+}
+
+pub fn parse_announcement(uri: &str, title: &str, html: &str) -> Announcement {
+    let document = Document::from(html);
+    let text = document
+        .find(Name("article"))
+        .next()
+        .or_else(|| document.find(Name("main")).next())
+        .or_else(|| document.find(Name("body")).next())
+        .map(|node| collapse_whitespace(&node.text()))
+        .unwrap_or_default();
+
+    // Dates near the declaration rather than when the page was published or updated
+    let declared_text = text
+        .split(". ")
+        .filter(|sentence| {
+            let sentence = sentence.to_lowercase();
+            sentence.contains("designat") || sentence.contains("declar")
+        })
+        .collect::<Vec<_>>()
+        .join(". ");
+    let mut dates = announced_dates(&declared_text).unwrap_or_default();
+    if dates.is_empty() {
+        dates = announced_dates(&text).unwrap_or_default();
+    }
+    dates.sort();
+
+    Announcement {
+        uri: uri.to_string(),
+        title: title.to_string(),
+        area: area_from(title).or_else(|| area_from(&text)),
+        start: dates.first().copied(),
+        end: dates.last().copied(),
+        scraped_at: Utc::now().timestamp(),
+    }
+}
+
+async fn fetch_page(uri: &str) -> Result<String> {
+    Ok(client()
+        .get(uri)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?)
+}
+
+pub struct AnnouncementScraper<T>
+where
+    T: DatabaseProvider + Clone + Send + Sync,
+{
+    pub uri: String,
+    pub database_provider: T,
+}
+
+impl<T> AnnouncementScraper<T>
+where
+    T: DatabaseProvider + Clone + Send + Sync,
+{
+    // POLICE_ANNOUNCEMENTS_URL is the page listing them, which defaults to the police news page
+    pub fn from_env(database_provider: T) -> Self {
+        let uri = env::var("POLICE_ANNOUNCEMENTS_URL")
+            .ok()
+            .filter(|uri| !uri.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_ANNOUNCEMENTS_URI.to_string());
+
+        Self {
+            uri,
+            database_provider,
+        }
+    }
+
+    // Each announcement is only read once; the matching is done when the report is shown, so
+    // gazettes published later are still found
+    pub async fn update(&self) -> Result<Vec<String>> {
+        let listing = fetch_page(&self.uri).await?;
+        let db = DatabaseConnection {
+            provider: self.database_provider.clone(),
+        };
+        let known: HashSet<String> = db
+            .fetch_announcements()
+            .await?
+            .into_iter()
+            .map(|announcement| announcement.uri)
+            .collect();
+
+        let futures = parse_listing(&listing, &self.uri)
+            .into_iter()
+            .filter(|(_, uri)| !known.contains(uri))
+            .map(|(title, uri)| {
+                let db = &db;
+                async move {
+                    let page = fetch_page(&uri).await.ok()?;
+                    let announcement = parse_announcement(&uri, &title, &page);
+                    db.store_announcement(&make_hash(&uri), &announcement)
+                        .await
+                        .ok()?;
+                    Some(uri)
+                }
+            });
+        let stored = futures::future::join_all(futures)
+            .await
+            .into_iter()
+            .flatten()
+            .collect();
+
+        println!("Announcement Update Complete");
+        Ok(stored)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geocoder::core::{GeocodeResult, GeocodedPoint};
+    use crate::utils::gazette::NoticeSource;
+
+    const NEWS_URI: &str = "https://www.police.vic.gov.au/news";
+    const ANNOUNCEMENT_URI: &str =
+        "https://www.police.vic.gov.au/news/designated-area-declared-melbourne-cbd";
+
+    fn date(month: u32, day: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(2025, month, day)
+    }
+
+    fn announcement() -> Announcement {
+        parse_announcement(
+            ANNOUNCEMENT_URI,
+            "Designated area declared in Melbourne CBD",
+            include_str!("fixtures/police-announcement.html"),
+        )
+    }
+
+    #[test]
+    fn test_parse_listing() {
+        let links = parse_listing(include_str!("fixtures/police-news.html"), NEWS_URI);
+        assert_eq!(
+            links,
+            vec![
+                (
+                    "Designated area declared in Melbourne CBD".to_string(),
+                    ANNOUNCEMENT_URI.to_string()
+                ),
+                (
+                    "Designated Area in place for Dandenong".to_string(),
+                    "https://www.police.vic.gov.au/news/designated-area-in-place-dandenong"
+                        .to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_announcement() {
+        let announcement = announcement();
+        assert_eq!(announcement.area.as_deref(), Some("Melbourne CBD"));
+        // Not the day it was published
+        assert_eq!(announcement.start, date(10, 3));
+        assert_eq!(announcement.end, date(10, 5));
+    }
+
+    #[test]
+    fn test_parse_announcement_over_new_year() {
+        let announcement = parse_announcement(
+            "https://www.police.vic.gov.au/news/designated-area-declared-geelong",
+            "Designated area declared in Geelong",
+            include_str!("fixtures/police-announcement-new-year.html"),
+        );
+        assert_eq!(announcement.start, date(12, 30));
+        assert_eq!(announcement.end, NaiveDate::from_ymd_opt(2026, 1, 2));
+    }
+
+    #[test]
+    fn test_announced_dates() {
+        // The year given first carries on to the dates after it
+        assert_eq!(
+            announced_dates("from 3 October 2025 until 5 October").unwrap(),
+            vec![date(10, 3).unwrap(), date(10, 5).unwrap()]
+        );
+        assert_eq!(
+            announced_dates("from 30 December 2025 until 2 January").unwrap(),
+            vec![
+                date(12, 30).unwrap(),
+                NaiveDate::from_ymd_opt(2026, 1, 2).unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_matches() {
+        let gazette = Gazette {
+            uri: "http://www.gazette.vic.gov.au/gazette/Gazettes2025/GG2025S500.pdf".to_string(),
+            flagged: true,
+            start: date(10, 4),
+            end: date(10, 4),
+            area: Some("Melbourne".to_string()),
+            ..Default::default()
+        };
+        let announcement = announcement();
+        assert!(announcement.matches(&gazette));
+
+        // Found from the suburbs of its places when the notice doesn't name an area
+        let geocoded = Gazette {
+            area: None,
+            geocoded: vec![GeocodedPoint {
+                input: "Flinders Street".to_string(),
+                result: GeocodeResult {
+                    formatted_address: Some("Flinders St, Melbourne VIC 3000".to_string()),
+                    ..Default::default()
+                },
+                provider: "test".to_string(),
+            }],
+            ..gazette.clone()
+        };
+        assert!(announcement.matches(&geocoded));
+
+        let unmatched_gazettes = [
+            Gazette {
+                start: date(10, 10),
+                end: date(10, 12),
+                ..gazette.clone()
+            },
+            Gazette {
+                area: Some("Dandenong".to_string()),
+                ..gazette.clone()
+            },
+            Gazette {
+                source: NoticeSource::NonGazetted {
+                    submitted_by: "sam".to_string(),
+                    submitted_at: 0,
+                },
+                ..gazette.clone()
+            },
+        ];
+        for other in &unmatched_gazettes {
+            assert!(!announcement.matches(other));
+        }
+
+        let announcements = [announcement];
+        assert_eq!(unmatched(&announcements, &unmatched_gazettes).len(), 1);
+        assert!(unmatched(&announcements, &[gazette]).is_empty());
+    }
+}
@@ -0,0 +1,320 @@
+use anyhow::{anyhow, Result};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use redis_macros::{FromRedisValue, ToRedisArgs};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+const DEFAULT_SESSION_HOURS: u64 = 12;
+// Failed sign-ins are counted over this long, by account name and by address
+const LOGIN_WINDOW: Duration = Duration::from_secs(15 * 60);
+const MAX_FAILURES_PER_NAME: u32 = 5;
+const MAX_FAILURES_PER_ADDRESS: u32 = 20;
+// Each check takes a moment of CPU and some memory, so only a few run at once
+const CONCURRENT_PASSWORD_CHECKS: usize = 4;
+
+static FAILED_LOGINS: Mutex<FailedLogins> = Mutex::new(FailedLogins::new());
+static PASSWORD_CHECKS: Semaphore = Semaphore::const_new(CONCURRENT_PASSWORD_CHECKS);
+
+// Each role can do everything the ones before it can
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    // Can look around the admin pages
+    Viewer,
+    // Can change notices
+    Editor,
+    // Can delete notices and run updates
+    Admin,
+}
+
+impl FromStr for Role {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "viewer" => Ok(Self::Viewer),
+            "editor" => Ok(Self::Editor),
+            "admin" => Ok(Self::Admin),
+            _ => Err(anyhow!("Unknown role {value}")),
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Viewer => "viewer",
+            Self::Editor => "editor",
+            Self::Admin => "admin",
+        })
+    }
+}
+
+// Whoever is signed in, by a session or an API token
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, FromRedisValue, ToRedisArgs)]
+pub struct User {
+    pub name: String,
+    pub role: Role,
+}
+
+// A local account; only the Argon2 hash of the password is kept
+#[derive(Clone, Debug, Serialize, Deserialize, FromRedisValue, ToRedisArgs)]
+pub struct Account {
+    pub name: String,
+    pub role: Role,
+    pub password_hash: String,
+}
+
+impl Account {
+    pub fn new(name: &str, role: Role, password: &str) -> Result<Self> {
+        let salt = SaltString::generate(&mut OsRng);
+        let password_hash = Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map_err(|e| anyhow!("Could not hash password: {e}"))?
+            .to_string();
+
+        Ok(Self {
+            name: name.to_string(),
+            role,
+            password_hash,
+        })
+    }
+
+    pub fn verify(&self, password: &str) -> bool {
+        PasswordHash::new(&self.password_hash).is_ok_and(|hash| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+        })
+    }
+
+    pub fn user(&self) -> User {
+        User {
+            name: self.name.clone(),
+            role: self.role,
+        }
+    }
+}
+
+// Argon2 is slow on purpose, so it's run away from the async workers. Names without an account are
+// checked against a stand-in, so they take as long as a wrong password and don't give away which
+// names exist
+pub async fn check_password(account: Option<Account>, password: String) -> Option<Account> {
+    let _permit = PASSWORD_CHECKS.acquire().await.ok()?;
+
+    tokio::task::spawn_blocking(move || match account {
+        Some(account) => account.verify(&password).then_some(account),
+        None => {
+            if let Some(stand_in) = stand_in_account() {
+                let _ = stand_in.verify(&password);
+            }
+            None
+        }
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+fn stand_in_account() -> Option<&'static Account> {
+    static STAND_IN: OnceLock<Option<Account>> = OnceLock::new();
+    STAND_IN
+        .get_or_init(|| Account::new("", Role::Viewer, &new_session_id()).ok())
+        .as_ref()
+}
+
+// When each count started and how many have failed since
+struct FailedLogins {
+    counts: BTreeMap<String, (Instant, u32)>,
+}
+
+impl FailedLogins {
+    const fn new() -> Self {
+        Self {
+            counts: BTreeMap::new(),
+        }
+    }
+
+    fn keys(name: &str, address: IpAddr) -> [(String, u32); 2] {
+        [
+            (
+                format!("name:{}", name.to_lowercase()),
+                MAX_FAILURES_PER_NAME,
+            ),
+            (format!("address:{address}"), MAX_FAILURES_PER_ADDRESS),
+        ]
+    }
+
+    fn allowed(&self, name: &str, address: IpAddr, now: Instant) -> bool {
+        Self::keys(name, address).iter().all(|(key, limit)| {
+            self.counts.get(key).is_none_or(|(started, failures)| {
+                now.duration_since(*started) >= LOGIN_WINDOW || failures < limit
+            })
+        })
+    }
+
+    fn record(&mut self, name: &str, address: IpAddr, now: Instant) {
+        self.counts
+            .retain(|_, (started, _)| now.duration_since(*started) < LOGIN_WINDOW);
+        for (key, _) in Self::keys(name, address) {
+            self.counts.entry(key).or_insert((now, 0)).1 += 1;
+        }
+    }
+}
+
+pub fn login_allowed(name: &str, address: IpAddr) -> bool {
+    FAILED_LOGINS
+        .lock()
+        .is_ok_and(|failed| failed.allowed(name, address, Instant::now()))
+}
+
+pub fn record_failed_login(name: &str, address: IpAddr) {
+    if let Ok(mut failed) = FAILED_LOGINS.lock() {
+        failed.record(name, address, Instant::now());
+    }
+}
+
+// For scripts, which send it as a bearer token instead of signing in
+pub struct ApiToken {
+    user: User,
+    digest: Vec<u8>,
+}
+
+// API_TOKENS is a comma separated list of name:role:token; entries that don't parse are skipped
+pub fn api_tokens_from(value: &str) -> Vec<ApiToken> {
+    value
+        .split(',')
+        .filter_map(|entry| {
+            let mut parts = entry.trim().splitn(3, ':');
+            let (name, role, token) = (parts.next()?, parts.next()?, parts.next()?);
+            if name.is_empty() || token.is_empty() {
+                return None;
+            }
+            Some(ApiToken {
+                user: User {
+                    name: name.to_string(),
+                    role: role.parse().ok()?,
+                },
+                digest: Sha256::digest(token).to_vec(),
+            })
+        })
+        .collect()
+}
+
+pub fn api_tokens() -> Vec<ApiToken> {
+    env::var("API_TOKENS")
+        .map(|value| api_tokens_from(&value))
+        .unwrap_or_default()
+}
+
+// Comparing digests keeps the time taken from giving the tokens away
+pub fn token_user(tokens: &[ApiToken], token: &str) -> Option<User> {
+    let digest = Sha256::digest(token);
+    tokens
+        .iter()
+        .find(|candidate| candidate.digest == digest.as_slice())
+        .map(|candidate| candidate.user.clone())
+}
+
+pub fn new_session_id() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+pub fn session_ttl_seconds() -> u64 {
+    env::var("SESSION_TTL_HOURS")
+        .ok()
+        .and_then(|hours| hours.parse().ok())
+        .unwrap_or(DEFAULT_SESSION_HOURS)
+        * 3600
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roles() {
+        assert!(Role::Admin > Role::Editor);
+        assert!(Role::Editor > Role::Viewer);
+        assert_eq!("Editor".parse::<Role>().unwrap(), Role::Editor);
+        assert!("owner".parse::<Role>().is_err());
+    }
+
+    #[test]
+    fn test_account() {
+        let account = Account::new("sam", Role::Editor, "correct horse").unwrap();
+        assert!(!account.password_hash.contains("correct horse"));
+        assert!(account.verify("correct horse"));
+        assert!(!account.verify("battery staple"));
+    }
+
+    #[tokio::test]
+    async fn test_check_password() {
+        let account = Account::new("sam", Role::Editor, "correct horse").unwrap();
+        let checked = check_password(Some(account.clone()), "correct horse".to_string()).await;
+        assert_eq!(checked.map(|account| account.user()), Some(account.user()));
+        assert!(check_password(Some(account), "battery staple".to_string())
+            .await
+            .is_none());
+        assert!(check_password(None, "correct horse".to_string())
+            .await
+            .is_none());
+    }
+
+    #[test]
+    fn test_failed_logins() {
+        let mut failed = FailedLogins::new();
+        let address: IpAddr = "203.0.113.7".parse().unwrap();
+        let other: IpAddr = "203.0.113.8".parse().unwrap();
+        let now = Instant::now();
+
+        for _ in 0..MAX_FAILURES_PER_NAME {
+            assert!(failed.allowed("Sam", address, now));
+            failed.record("Sam", address, now);
+        }
+        // The name is locked from anywhere, but other names can still be tried
+        assert!(!failed.allowed("sam", other, now));
+        assert!(failed.allowed("alex", address, now));
+        assert!(failed.allowed("sam", address, now + LOGIN_WINDOW));
+
+        for attempt in 0..MAX_FAILURES_PER_ADDRESS {
+            failed.record(&format!("name{attempt}"), other, now);
+        }
+        assert!(!failed.allowed("alex", other, now));
+
+        failed.record("alex", address, now + LOGIN_WINDOW);
+        assert_eq!(failed.counts.len(), 2);
+    }
+
+    #[test]
+    fn test_api_tokens() {
+        let tokens = api_tokens_from("bot:editor:abc:123, broken, nobody:owner:xyz");
+        assert_eq!(tokens.len(), 1);
+
+        let user = token_user(&tokens, "abc:123").unwrap();
+        assert_eq!(user.name, "bot");
+        assert_eq!(user.role, Role::Editor);
+        assert!(token_user(&tokens, "abc").is_none());
+        assert!(token_user(&tokens, "xyz").is_none());
+    }
+
+    #[test]
+    fn test_session_id() {
+        let id = new_session_id();
+        assert_eq!(id.len(), 64);
+        assert_ne!(id, new_session_id());
+    }
+}
@@ -0,0 +1,473 @@
+use crate::geocoder::gazetteer::Gazetteer;
+use crate::geocoder::GeocodedPoint;
+use crate::utils::maptypes::{GeoPosition, MapPolygon};
+use regex::Regex;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+// A corner further than this from its street's nearest node isn't on the stretch we have
+const SNAP_DISTANCE_KM: f64 = 0.1;
+// Only street data this close to a pair of corners is searched
+const SEARCH_MARGIN_KM: f64 = 0.5;
+// A path much longer than the straight line has wandered off along some other stretch
+const MAX_DETOUR: f64 = 3.0;
+// The order the sides of an area are walked in, clockwise from the north
+const SIDES: [&str; 4] = ["north", "east", "south", "west"];
+
+#[derive(Clone, Debug)]
+struct Corner {
+    streets: [String; 2],
+    position: GeoPosition,
+}
+
+impl Corner {
+    fn shared_street(&self, other: &Corner) -> Option<&str> {
+        self.streets
+            .iter()
+            .find(|street| other.streets.contains(street))
+            .map(String::as_str)
+    }
+}
+
+// Notices bound areas by streets, so the edges between corners follow those streets rather
+// than cutting across; needs the gazetteer for street geometry
+pub async fn trace_boundary(points: &[GeocodedPoint], text: &str) -> Option<MapPolygon> {
+    let gazetteer = Gazetteer::shared().await.ok()?;
+    boundary_from(points, &boundary_streets(text), gazetteer)
+}
+
+// Notices often list the streets along each side, as "North: Flinders Street" or "to the north
+// by Flinders Street"; taken clockwise from the north, they give the order of the corners.
+// Streets named before their side, as in "Flinders Street to the north", aren't picked up
+pub fn boundary_streets(text: &str) -> Vec<String> {
+    let (Ok(side_regex), Ok(separator)) = (
+        Regex::new(
+            r"(?i)\bto\s+the\s+(north|east|south|west)(?:\s+by)?\b|\b(north|east|south|west)(?:ern)?(?:\s+(?:side|boundary))?\s*[:\-–—]",
+        ),
+        Regex::new(r"(?i)\s*(?:,|&|\band\b)\s*"),
+    ) else {
+        return vec![];
+    };
+
+    let markers: Vec<(usize, usize, usize)> = side_regex
+        .captures_iter(text)
+        .filter_map(|cap| {
+            let marker = cap.get(0)?;
+            let side = cap.get(1).or(cap.get(2))?.as_str().to_lowercase();
+            let rank = SIDES.iter().position(|name| *name == side)?;
+            Some((rank, marker.start(), marker.end()))
+        })
+        .collect();
+
+    let mut sides: Vec<(usize, Vec<String>)> = markers
+        .iter()
+        .enumerate()
+        .map(|(index, &(rank, _, end))| {
+            let until = markers.get(index + 1).map_or(text.len(), |next| next.1);
+            let listed = text[end..until]
+                .split([';', '.', '\n'])
+                .next()
+                .unwrap_or_default();
+            let streets: Vec<&str> = separator
+                .split(listed)
+                .map(str::trim)
+                .filter(|street| !street.is_empty())
+                .collect();
+            (rank, Gazetteer::street_names(&streets))
+        })
+        .collect();
+    // Streets on the same side stay in the order they're listed
+    sides.sort_by_key(|(rank, _)| *rank);
+
+    let mut streets: Vec<String> = Vec::new();
+    for street in sides.into_iter().flat_map(|(_, streets)| streets) {
+        if !streets.contains(&street) {
+            streets.push(street);
+        }
+    }
+    streets
+}
+
+pub fn boundary_from(
+    points: &[GeocodedPoint],
+    streets: &[String],
+    gazetteer: &Gazetteer,
+) -> Option<MapPolygon> {
+    let corners: Vec<Corner> = points
+        .iter()
+        .filter_map(|point| {
+            let (first, second) = Gazetteer::corner_streets(&point.input)?;
+            Some(Corner {
+                streets: [first, second],
+                position: point.result.position.clone(),
+            })
+        })
+        .collect();
+    if corners.len() < 3 {
+        return None;
+    }
+
+    let corners = by_sides(&corners, streets)
+        .or_else(|| chain(&corners))
+        .unwrap_or_else(|| clockwise(corners));
+    let mut ring = Vec::new();
+    for (index, corner) in corners.iter().enumerate() {
+        let next = &corners[(index + 1) % corners.len()];
+        ring.push(corner.position.clone());
+
+        // Corners that don't share a street are joined with a straight line
+        if let Some(street) = corner.shared_street(next) {
+            let path = trace(
+                gazetteer.street_lines(street),
+                &corner.position,
+                &next.position,
+            );
+            ring.extend(path.unwrap_or_default());
+        }
+    }
+    ring.push(corners[0].position.clone());
+
+    Some(MapPolygon { data: ring })
+}
+
+// A corner joins two streets listed one after the other, and comes where the second one starts;
+// gives up unless every corner is like that, which it isn't when a side is listed back to front
+fn by_sides(corners: &[Corner], streets: &[String]) -> Option<Vec<Corner>> {
+    let count = streets.len();
+    if count < 3 {
+        return None;
+    }
+
+    let mut keyed = corners
+        .iter()
+        .map(|corner| {
+            let first = streets
+                .iter()
+                .position(|street| *street == corner.streets[0])?;
+            let second = streets
+                .iter()
+                .position(|street| *street == corner.streets[1])?;
+            if (first + 1) % count == second {
+                Some((second, corner.clone()))
+            } else if (second + 1) % count == first {
+                Some((first, corner.clone()))
+            } else {
+                None
+            }
+        })
+        .collect::<Option<Vec<_>>>()?;
+    keyed.sort_by_key(|(key, _)| *key);
+    if keyed.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+        return None;
+    }
+
+    Some(keyed.into_iter().map(|(_, corner)| corner).collect())
+}
+
+// Notices walk the boundary, so each corner shares a street with the next, leaving along the
+// street it didn't arrive on; gives up if the corners don't link up like that
+fn chain(corners: &[Corner]) -> Option<Vec<Corner>> {
+    let mut remaining = corners.to_vec();
+    let mut ordered = vec![remaining.remove(0)];
+    let mut arrived_along: Option<String> = None;
+
+    while !remaining.is_empty() {
+        let current = ordered.last()?;
+        let (index, street) = remaining
+            .iter()
+            .enumerate()
+            .filter_map(|(index, corner)| {
+                let street = current.shared_street(corner)?;
+                let backtracking = arrived_along.as_deref() == Some(street);
+                let distance = current.position.distance_to(&corner.position);
+                Some(((backtracking, distance), index, street.to_string()))
+            })
+            .min_by(|(a, _, _), (b, _, _)| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)))
+            .map(|(_, index, street)| (index, street))?;
+
+        arrived_along = Some(street);
+        ordered.push(remaining.remove(index));
+    }
+
+    Some(ordered)
+}
+
+fn clockwise(mut corners: Vec<Corner>) -> Vec<Corner> {
+    let centre = MapPolygon {
+        data: corners
+            .iter()
+            .map(|corner| corner.position.clone())
+            .collect(),
+    }
+    .centre();
+    let bearing = |corner: &Corner| {
+        (corner.position.latitude - centre.latitude)
+            .atan2(corner.position.longitude - centre.longitude)
+    };
+
+    corners.sort_by(|a, b| bearing(b).total_cmp(&bearing(a)));
+    corners
+}
+
+// The shortest way along the street's own segments from one corner to the next, leaving out
+// the nodes the corners stand in for
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn trace<'a>(
+    lines: impl Iterator<Item = &'a [GeoPosition]>,
+    from: &GeoPosition,
+    to: &GeoPosition,
+) -> Option<Vec<GeoPosition>> {
+    let straight = from.distance_to(to);
+    let reach = straight + SEARCH_MARGIN_KM;
+    let nearby = lines.filter(|line| {
+        line.iter()
+            .any(|point| point.distance_to(from) <= reach || point.distance_to(to) <= reach)
+    });
+
+    // Streets are split into many ways, so nodes they share are merged by position
+    let mut nodes: Vec<GeoPosition> = Vec::new();
+    let mut ids: HashMap<(i64, i64), usize> = HashMap::new();
+    let mut edges: Vec<Vec<(usize, u64)>> = Vec::new();
+    for line in nearby {
+        let line_ids: Vec<usize> = line
+            .iter()
+            .map(|point| {
+                let key = (
+                    (point.latitude * 1e7).round() as i64,
+                    (point.longitude * 1e7).round() as i64,
+                );
+                *ids.entry(key).or_insert_with(|| {
+                    nodes.push(point.clone());
+                    edges.push(Vec::new());
+                    nodes.len() - 1
+                })
+            })
+            .collect();
+
+        for pair in line_ids.windows(2) {
+            // Millimetres, so costs can be ordered in the queue
+            let cost = (nodes[pair[0]].distance_to(&nodes[pair[1]]) * 1e6) as u64;
+            edges[pair[0]].push((pair[1], cost));
+            edges[pair[1]].push((pair[0], cost));
+        }
+    }
+
+    let start = nearest_node(&nodes, from)?;
+    let end = nearest_node(&nodes, to)?;
+
+    let mut best = vec![u64::MAX; nodes.len()];
+    let mut previous: Vec<Option<usize>> = vec![None; nodes.len()];
+    let mut queue = BinaryHeap::from([Reverse((0, start))]);
+    best[start] = 0;
+
+    while let Some(Reverse((cost, node))) = queue.pop() {
+        if node == end {
+            break;
+        }
+        if cost > best[node] {
+            continue;
+        }
+        for &(neighbour, step) in &edges[node] {
+            let next_cost = cost + step;
+            if next_cost < best[neighbour] {
+                best[neighbour] = next_cost;
+                previous[neighbour] = Some(node);
+                queue.push(Reverse((next_cost, neighbour)));
+            }
+        }
+    }
+
+    if best[end] == u64::MAX || best[end] as f64 / 1e6 > straight * MAX_DETOUR + SNAP_DISTANCE_KM {
+        return None;
+    }
+
+    let mut path = Vec::new();
+    let mut node = previous[end];
+    while let Some(current) = node.filter(|&current| current != start) {
+        path.push(nodes[current].clone());
+        node = previous[current];
+    }
+    path.reverse();
+
+    Some(path)
+}
+
+fn nearest_node(nodes: &[GeoPosition], position: &GeoPosition) -> Option<usize> {
+    nodes
+        .iter()
+        .enumerate()
+        .map(|(index, node)| (index, node.distance_to(position)))
+        .filter(|(_, distance)| *distance <= SNAP_DISTANCE_KM)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(index, _)| index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geocoder::GeocodeResult;
+
+    // An L-shaped block: Alpha Street along the bottom, Bravo Street up the east side with a
+    // bend in it, back along Charlie, up Delta, along Echo and down Foxtrot
+    const FIXTURE: &str = r#"{
+        "type": "FeatureCollection",
+        "features": [
+            {"type": "Feature", "properties": {"name": "Alpha Street"},
+             "geometry": {"type": "LineString", "coordinates": [[144.950, -37.820], [144.955, -37.820]]}},
+            {"type": "Feature", "properties": {"name": "Alpha Street"},
+             "geometry": {"type": "LineString", "coordinates": [[144.955, -37.820], [144.960, -37.820]]}},
+            {"type": "Feature", "properties": {"name": "Bravo Street"},
+             "geometry": {"type": "LineString", "coordinates": [[144.960, -37.820], [144.962, -37.8175], [144.960, -37.815]]}},
+            {"type": "Feature", "properties": {"name": "Charlie Street"},
+             "geometry": {"type": "LineString", "coordinates": [[144.960, -37.815], [144.955, -37.815]]}},
+            {"type": "Feature", "properties": {"name": "Delta Street"},
+             "geometry": {"type": "LineString", "coordinates": [[144.955, -37.815], [144.955, -37.810]]}},
+            {"type": "Feature", "properties": {"name": "Echo Street"},
+             "geometry": {"type": "LineString", "coordinates": [[144.955, -37.810], [144.950, -37.810]]}},
+            {"type": "Feature", "properties": {"name": "Foxtrot Street"},
+             "geometry": {"type": "LineString", "coordinates": [[144.950, -37.810], [144.950, -37.820]]}},
+            {"type": "Feature", "properties": {"name": "Bravo Street"},
+             "geometry": {"type": "LineString", "coordinates": [[145.500, -38.000], [145.500, -38.010]]}}
+        ]
+    }"#;
+
+    fn corner(input: &str, longitude: f64, latitude: f64) -> GeocodedPoint {
+        GeocodedPoint {
+            input: input.to_string(),
+            result: GeocodeResult {
+                position: GeoPosition::at(longitude, latitude),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    // Listed out of order, as the parser sometimes does
+    fn corners() -> Vec<GeocodedPoint> {
+        vec![
+            corner("Corner of Charlie and Delta Streets", 144.955, -37.815),
+            corner("Corner of Alpha and Foxtrot Streets", 144.950, -37.820),
+            corner("Corner of Echo and Foxtrot Streets", 144.950, -37.810),
+            corner("Corner of Alpha and Bravo Streets", 144.960, -37.820),
+            corner("Corner of Delta and Echo Streets", 144.955, -37.810),
+            corner("Corner of Bravo and Charlie Streets", 144.960, -37.815),
+        ]
+    }
+
+    #[test]
+    fn test_boundary_follows_streets() {
+        let gazetteer = Gazetteer::from_geojson(FIXTURE).unwrap();
+        let boundary = boundary_from(&corners(), &[], &gazetteer).unwrap();
+
+        // Six corners, the bend in Bravo Street and the join in Alpha Street, closed
+        assert_eq!(boundary.data.len(), 9);
+        assert_eq!(boundary.data.first(), boundary.data.last());
+        assert!(boundary.data.contains(&GeoPosition::at(144.962, -37.8175)));
+
+        // The notch the convex hull would swallow stays out
+        let notch = GeoPosition::at(144.9575, -37.8135);
+        assert!(!boundary.contains(&notch));
+        assert!(boundary.convex_hull().contains(&notch));
+        assert!(boundary.contains(&GeoPosition::at(144.952, -37.812)));
+        assert!(boundary.contains(&GeoPosition::at(144.9605, -37.8175)));
+    }
+
+    #[test]
+    fn test_boundary_needs_corners() {
+        let gazetteer = Gazetteer::from_geojson(FIXTURE).unwrap();
+        let points = vec![
+            corner("Corner of Alpha and Bravo Streets", 144.960, -37.820),
+            corner("Southern Cross Railway Station", 144.952, -37.818),
+        ];
+        assert!(boundary_from(&points, &[], &gazetteer).is_none());
+    }
+
+    #[test]
+    fn test_boundary_streets() {
+        let listed = "The area bounded by: North: Echo Street; East: Delta St, Charlie and Bravo \
+                      Streets; South: Alpha Street; West: Foxtrot Street.";
+        let walked = "The area bounded to the west by Foxtrot Street, to the north by Echo \
+                      Street, to the east by Delta Street, Charlie Street and Bravo Street, and \
+                      to the south by Alpha Street.";
+        let expected = [
+            "echo street",
+            "delta street",
+            "charlie street",
+            "bravo street",
+            "alpha street",
+            "foxtrot street",
+        ];
+
+        assert_eq!(boundary_streets(listed), expected);
+        assert_eq!(boundary_streets(walked), expected);
+        assert!(boundary_streets("Police may search people in the Melbourne CBD").is_empty());
+
+        let gazetteer = Gazetteer::from_geojson(FIXTURE).unwrap();
+        let boundary = boundary_from(&corners(), &boundary_streets(walked), &gazetteer).unwrap();
+        assert_eq!(
+            boundary.data.first(),
+            Some(&GeoPosition::at(144.950, -37.810))
+        );
+        assert_eq!(boundary.data.len(), 9);
+        assert!(!boundary.contains(&GeoPosition::at(144.9575, -37.8135)));
+
+        // Listing a side back to front leaves it to the corners
+        let reversed = [
+            "echo street",
+            "bravo street",
+            "charlie street",
+            "delta street",
+            "alpha street",
+            "foxtrot street",
+        ]
+        .map(str::to_string);
+        let fallback = boundary_from(&corners(), &reversed, &gazetteer).unwrap();
+        assert_eq!(fallback.data.len(), 9);
+        assert!(!fallback.contains(&GeoPosition::at(144.9575, -37.8135)));
+    }
+
+    #[test]
+    fn test_corner_order() {
+        let corners: Vec<Corner> = corners()
+            .iter()
+            .map(|point| {
+                let (first, second) = Gazetteer::corner_streets(&point.input).unwrap();
+                Corner {
+                    streets: [first, second],
+                    position: point.result.position.clone(),
+                }
+            })
+            .collect();
+
+        let chained = chain(&corners).unwrap();
+        for (index, corner) in chained.iter().enumerate() {
+            let next = &chained[(index + 1) % chained.len()];
+            assert!(corner.shared_street(next).is_some());
+        }
+
+        // Without shared streets, corners go clockwise: west, north, east, south
+        let compass = |longitude, latitude| Corner {
+            streets: [String::new(), String::new()],
+            position: GeoPosition::at(longitude, latitude),
+        };
+        let ordered = clockwise(vec![
+            compass(144.96, -37.83),
+            compass(144.95, -37.82),
+            compass(144.97, -37.82),
+            compass(144.96, -37.81),
+        ]);
+        let ordered: Vec<(f64, f64)> = ordered
+            .iter()
+            .map(|corner| (corner.position.longitude, corner.position.latitude))
+            .collect();
+        assert_eq!(
+            ordered,
+            [
+                (144.95, -37.82),
+                (144.96, -37.81),
+                (144.97, -37.82),
+                (144.96, -37.83)
+            ]
+        );
+    }
+}
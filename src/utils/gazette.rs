@@ -1,11 +1,21 @@
 use crate::db::core::DatabaseProvider;
 use crate::db::DatabaseConnection;
 use crate::geocoder::core::GeocoderProvider;
-use crate::geocoder::GeocoderRequest;
-use crate::image_service::{Image, ImageService};
+use crate::geocoder::{GeocodedPoint, GeocoderBatchRequest};
 use crate::location_parser::core::LocationParserService;
 use crate::location_parser::LocationParser;
-use crate::utils::maptypes::{MapPolygon, Sanitise};
+use crate::ocr::core::OcrService;
+use crate::ocr::{is_garbled, OcrRequest};
+use crate::renderer::core::PageRenderer;
+use crate::renderer::RenderRequest;
+use crate::storage::{Blob, StorageService, StoredObject, IMMUTABLE_CACHE_CONTROL};
+use crate::utils::geometry::{DisplayGeometry, GeometryEdit};
+use crate::utils::http::client;
+use crate::utils::mapimage::{
+    decode_pdf_image, find_vector_map_page, select_map, ImageVariant, MapImage, MapImageFormat,
+    IMAGE_SIZES,
+};
+use crate::utils::maptypes::{Corridor, MapPolygon, Sanitise, ShapeMode};
 use anyhow::{anyhow, Result};
 use chrono::{Datelike, Local, NaiveDate};
 use lopdf::Document;
@@ -14,6 +24,10 @@ use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_with::chrono;
 use sha1::{digest::core_api::CoreWrapper, Digest, Sha1, Sha1Core};
+use sha2::Sha256;
+use std::env;
+use std::fmt;
+use std::sync::Arc;
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize, FromRedisValue, ToRedisArgs)]
 #[allow(dead_code)]
@@ -21,24 +35,139 @@ pub struct Gazette {
     pub uri: String,
     pub title: Option<String>,
     pub img_uri: Option<String>,
+    pub img_width: Option<u32>,
+    pub img_height: Option<u32>,
+    pub img_format: Option<MapImageFormat>,
+    #[serde(default)]
+    pub img_variants: Vec<ImageVariant>,
     pub flagged: bool,
     pub polygon: Option<MapPolygon>,
     pub start: Option<NaiveDate>,
     pub end: Option<NaiveDate>,
+    pub ocr_text: Option<String>,
+    pub archive: Option<ArchivedPdf>,
+    #[serde(default)]
+    pub geocoded: Vec<GeocodedPoint>,
+    // The area traced along its bounding streets, when the street data allows
+    #[serde(default)]
+    pub boundary: Option<MapPolygon>,
+    // How the geocoded points are joined up when there's no boundary
+    #[serde(default)]
+    pub shape: ShapeMode,
+    // Set when the declaration covers a strip or a distance around places instead of an area
+    #[serde(default)]
+    pub corridor: Option<Corridor>,
+    // The operation area the notice names, which police announcements are matched against
+    #[serde(default)]
+    pub area: Option<String>,
+    // The shape the map draws, worked out from everything above when the notice is processed
+    #[serde(default)]
+    pub display: Option<DisplayGeometry>,
+    // Drawn instead of all of the above once a maintainer has corrected the shape
+    #[serde(default)]
+    pub geometry_override: Option<MapPolygon>,
+    #[serde(default)]
+    pub geometry_edits: Vec<GeometryEdit>,
+    #[serde(default)]
+    pub review: Review,
+    #[serde(default)]
+    pub source: NoticeSource,
+    // The PDF as given with a manual submission, or as downloaded from `uri` while the notice is
+    // processed; it's archived like any other, so it's never saved with the record
+    #[serde(skip)]
+    pub pdf: Option<PdfBytes>,
 }
 
-pub struct GazetteHandler<T, U, V, W>
+// Where we found out about a notice
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum NoticeSource {
+    #[default]
+    Gazette,
+    // Added by hand from somewhere else, such as the police website; these were never published
+    // in the Government Gazette, so they're shown apart from the rest
+    NonGazetted {
+        submitted_by: String,
+        submitted_at: i64,
+    },
+}
+
+impl NoticeSource {
+    pub fn is_gazetted(&self) -> bool {
+        *self == Self::Gazette
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct PdfBytes(pub Arc<Vec<u8>>);
+
+impl fmt::Debug for PdfBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PdfBytes({} bytes)", self.0.len())
+    }
+}
+
+// Whether a notice can be shown publicly yet
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", tag = "status")]
+pub enum Review {
+    // Shown straight away, since review was turned off when it was found
+    #[default]
+    Unreviewed,
+    Pending,
+    // Seconds since the epoch
+    Approved {
+        by: String,
+        at: i64,
+    },
+    // Hidden, but kept so the next update doesn't pick it up again
+    Rejected {
+        by: String,
+        at: i64,
+    },
+}
+
+impl Review {
+    // With REVIEW_NEW_NOTICES set, new notices wait for an editor to look at them
+    pub fn for_new_notice() -> Self {
+        let moderated = env::var("REVIEW_NEW_NOTICES")
+            .is_ok_and(|value| matches!(value.to_lowercase().as_str(), "1" | "true" | "yes"));
+        if moderated {
+            Self::Pending
+        } else {
+            Self::Unreviewed
+        }
+    }
+
+    pub fn is_public(&self) -> bool {
+        matches!(self, Self::Unreviewed | Self::Approved { .. })
+    }
+}
+
+// A copy of the gazette as we downloaded it, in case the original moves or disappears
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchivedPdf {
+    pub uri: String,
+    pub sha256: String,
+    pub size: usize,
+}
+
+pub struct GazetteHandler<T, U, V, W, X, Y>
 where
     T: DatabaseProvider,
-    U: ImageService,
+    U: StorageService,
     V: LocationParserService,
     W: GeocoderProvider,
+    X: OcrService,
+    Y: PageRenderer,
 {
     pub gazette: Gazette,
     pub database_provider: T,
-    pub image_service: U,
+    pub storage_service: U,
     pub location_parser: V,
     pub geocoder: W,
+    pub ocr_service: X,
+    pub page_renderer: Y,
 }
 
 pub fn make_hash(key: &str) -> String {
@@ -47,112 +176,296 @@ pub fn make_hash(key: &str) -> String {
     format!("{:x}", hasher.finalize())
 }
 
-impl<T, U, V, W> GazetteHandler<T, U, V, W>
+pub fn make_content_hash(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+// Suburb centroids stand in for places the geocoder couldn't find, so they stay out of the shape
+pub fn polygon_from(points: &[GeocodedPoint]) -> MapPolygon {
+    let mut polygon: Vec<_> = points
+        .iter()
+        .filter(|point| !point.result.is_locality())
+        .map(|point| point.result.position.clone())
+        .collect();
+    polygon.sanitise();
+    MapPolygon { data: polygon }
+}
+
+// Picks out "within 50 metres of", "a radius of 1 km" or "20 metres either side of"; a distance
+// with no mention of a line is taken to be around each place. Sentences exempting people or
+// places from the declaration are passed over, since their distances aren't the area's
+pub fn corridor_from(text: &str) -> Option<Corridor> {
+    let exemption = Regex::new(
+        r"(?i)\b(?:except|exempt(?:ed|ion)?|excluding|other than|unless|does not apply)\b",
+    )
+    .ok()?;
+    let text = Regex::new(r"[.;]\s+")
+        .ok()?
+        .split(text)
+        .filter(|sentence| !exemption.is_match(sentence))
+        .collect::<Vec<_>>()
+        .join(". ");
+    let text = text.as_str();
+
+    let distance = r"(\d+(?:\.\d+)?)\s*(kilomet(?:re|er)s?|km|met(?:re|er)s?|m)\b";
+    let either_side = Regex::new(&format!(
+        r"(?i){distance}\s+(?:on\s+)?(?:either|each|both)\s+sides?"
+    ))
+    .ok()?;
+    let within = Regex::new(&format!(
+        r"(?i)\b(?:within|radius of)\s+(?:a\s+)?{distance}"
+    ))
+    .ok()?;
+    let line =
+        Regex::new(r"(?i)\b(?:railway|rail|tram|train)\s+(?:line|corridor|track)s?\b").ok()?;
+
+    let (captures, is_line) = match either_side.captures(text) {
+        Some(captures) => (captures, true),
+        None => (within.captures(text)?, line.is_match(text)),
+    };
+    let value: f64 = captures.get(1)?.as_str().parse().ok()?;
+    let metres = if captures.get(2)?.as_str().to_lowercase().starts_with('k') {
+        value * 1000.0
+    } else {
+        value
+    };
+
+    Some(if is_line {
+        Corridor::Line { metres }
+    } else {
+        Corridor::Radius { metres }
+    })
+}
+
+impl<T, U, V, W, X, Y> GazetteHandler<T, U, V, W, X, Y>
 where
     T: DatabaseProvider + Clone,
-    U: ImageService + Copy,
+    U: StorageService + Clone,
     V: LocationParserService + Clone,
     W: GeocoderProvider + Clone,
+    X: OcrService + Clone,
+    Y: PageRenderer + Clone,
 {
     pub(crate) async fn save(&self) -> Result<bool> {
-        let mut hash = make_hash(&self.gazette.uri);
+        let db = DatabaseConnection {
+            provider: self.database_provider.clone(),
+        };
 
-        if self.gazette.flagged {
-            hash = format!("flagged:{hash}");
-        } else {
-            hash = format!("discarded:{hash}");
+        db.save_gazette(&self.gazette).await
+    }
+
+    pub(crate) async fn get_map(&self) -> Result<MapImage> {
+        let bytes = self.gazette.get_pdf_bytes().await?;
+        let pdf = Document::load_mem(&bytes)?;
+
+        if let Ok(map) = select_map(&pdf) {
+            return Ok(map);
         }
 
-        let db = DatabaseConnection {
-            provider: self.database_provider.clone(),
+        self.render_vector_map(&pdf, &bytes).await
+    }
+
+    async fn render_vector_map(&self, pdf: &Document, bytes: &[u8]) -> Result<MapImage> {
+        let page =
+            find_vector_map_page(pdf).ok_or(anyhow!("No map found in {}", &self.gazette.uri))?;
+
+        let request = RenderRequest {
+            pdf: bytes.to_vec(),
+            page,
+            service: self.page_renderer.clone(),
+        };
+        let png = request.render().await?;
+
+        MapImage::from_rendered_page(&png)
+    }
+
+    // Everything we store is keyed by a hash of its bytes, so reprocessing a gazette doesn't
+    // upload the same files again
+    async fn store(
+        &self,
+        data: Vec<u8>,
+        extension: &str,
+        content_type: &str,
+    ) -> Result<StoredObject> {
+        let blob = Blob {
+            key: format!("{}.{extension}", make_content_hash(&data)),
+            data,
+            content_type: content_type.to_string(),
+            cache_control: Some(IMMUTABLE_CACHE_CONTROL.to_string()),
+            service: self.storage_service.clone(),
         };
 
-        db.create_entry(&hash, &self.gazette).await?;
+        blob.upload().await
+    }
+
+    pub(crate) async fn try_upload_image(&self, map: &MapImage) -> Result<StoredObject> {
+        self.store(
+            map.data.clone(),
+            map.format.extension(),
+            map.format.mime_type(),
+        )
+        .await
+    }
 
-        Ok(true)
+    pub(crate) async fn try_archive_pdf(&self) -> Result<ArchivedPdf> {
+        let bytes = self.gazette.get_pdf_bytes().await?;
+        self.archive_pdf(bytes.to_vec()).await
     }
 
-    pub(crate) async fn try_upload_image(&self) -> Result<Option<String>> {
-        let hash = make_hash(&self.gazette.uri);
+    async fn archive_pdf(&self, data: Vec<u8>) -> Result<ArchivedPdf> {
+        let sha256 = make_content_hash(&data);
+        let object = self.store(data, "pdf", "application/pdf").await?;
+
+        Ok(ArchivedPdf {
+            uri: object.url,
+            sha256,
+            size: object.size,
+        })
+    }
 
-        if let Ok(map) = &self.gazette.extract_map().await {
-            let image = Image {
-                filename: format!("./{hash}.jpg"),
-                data: map.clone(),
-                service: self.image_service,
+    // Smaller copies for the list view
+    pub(crate) async fn try_upload_variants(&self, map: &MapImage) -> Result<Vec<ImageVariant>> {
+        let futures = IMAGE_SIZES.into_iter().map(|(name, width)| async move {
+            let Some(resized) = map.resize_to_width(width)? else {
+                return Ok(None);
             };
 
-            return image.upload().await;
-        }
-        Err(anyhow!("No map found in {}", &self.gazette.uri))
+            let object = self
+                .store(
+                    resized.data,
+                    resized.format.extension(),
+                    resized.format.mime_type(),
+                )
+                .await?;
+
+            Ok(Some(ImageVariant {
+                name: name.to_string(),
+                uri: object.url,
+                width: resized.width,
+                height: resized.height,
+            }))
+        });
+
+        futures::future::join_all(futures)
+            .await
+            .into_iter()
+            .filter_map(Result::transpose)
+            .collect()
     }
 
-    async fn get_operation_area(page_text: &str) -> Result<String> {
-        let area_regex = Regex::new(r"Planned Operation in (.*)")?;
+    // Only returns text when the PDF's own text layer is unusable, so it can be cached on the record
+    pub(crate) async fn get_ocr_text(&self) -> Result<Option<String>> {
+        let pdf = self.gazette.get_pdf().await?;
 
-        for cap in area_regex.captures_iter(&page_text) {
-            if let Some(area_string) = cap.get(1) {
-                return Ok(area_string.as_str().to_string());
+        if let Ok(text) = Gazette::extract_doc_text(&pdf) {
+            if !is_garbled(&text) {
+                return Ok(None);
             }
         }
 
-        Err(anyhow!("Could not find operation area"))
+        let page_numbers: Vec<u32> = pdf.get_pages().into_keys().collect();
+        let scans = Gazette::extract_page_scans(&pdf, &page_numbers);
+        if scans.is_empty() {
+            Err(anyhow!("No scanned pages found in {}", &self.gazette.uri))?;
+        }
+
+        let futures = scans.into_iter().map(|image| async move {
+            let request = OcrRequest {
+                image,
+                service: self.ocr_service.clone(),
+            };
+            request.recognise().await
+        });
+        let pages = futures::future::join_all(futures)
+            .await
+            .into_iter()
+            .collect::<Result<Vec<String>>>()?;
+
+        Ok(Some(pages.join("\n")))
     }
 
-    pub(crate) async fn get_polygon(&self) -> Result<Option<MapPolygon>> {
+    // The places the notice lists, along with the operation area they were looked up in
+    pub(crate) async fn get_geocoded_points(&self) -> Result<(Vec<GeocodedPoint>, String)> {
         let page_text = &self.gazette.get_doc_text().await?;
         let loc = LocationParser {
             provider: self.location_parser.clone(),
             locations: page_text.to_owned(),
         };
         let places = loc.parse_locations().await?;
-        let area = &Self::get_operation_area(&page_text).await?.clone();
-        let futures = places.into_iter().map(|place| async move {
-            let gc = GeocoderRequest {
-                service: self.geocoder.clone(),
-                input: place,
-                area: area.into(),
-            };
-            gc.geocode().await.unwrap_or_default()
-        });
-        let mut polygon = futures::future::join_all(futures).await;
-        polygon.sanitise();
-        Ok(Some(MapPolygon { data: polygon }))
+        let area = Gazette::operation_area(page_text)?;
+        let gc = GeocoderBatchRequest {
+            service: self.geocoder.clone(),
+            inputs: places,
+            area: area.clone(),
+        };
+        Ok((gc.geocode().await, area))
     }
 
     pub(crate) async fn get_date(&self) -> Result<(NaiveDate, NaiveDate)> {
         self.gazette.get_date().await
     }
+
+    pub(crate) async fn get_corridor(&self) -> Result<Option<Corridor>> {
+        Ok(corridor_from(&self.gazette.get_doc_text().await?))
+    }
 }
 
 impl Gazette {
-    async fn get_pdf(&self) -> Result<Document> {
-        let req = reqwest::get(&self.uri).await?;
-        let bytes = req.bytes().await?;
-        Document::load_mem(&bytes).map_err(Into::into)
-    }
+    fn operation_area(page_text: &str) -> Result<String> {
+        let area_regex = Regex::new(r"Planned Operation in (.*)")?;
+
+        for cap in area_regex.captures_iter(page_text) {
+            if let Some(area_string) = cap.get(1) {
+                return Ok(area_string.as_str().trim().to_string());
+            }
+        }
 
-    pub(crate) async fn extract_map(&self) -> Result<Vec<u8>> {
-        let mut images: Vec<lopdf::xobject::PdfImage> = Vec::new();
+        Err(anyhow!("Could not find operation area"))
+    }
 
-        let pdf = self.get_pdf().await?;
+    // Notices processed before the area was kept have it read from their text again
+    pub async fn fill_area(&mut self) {
+        if self.area.is_some() {
+            return;
+        }
+        if let Ok(page_text) = self.get_doc_text().await {
+            self.area = Self::operation_area(&page_text).ok();
+        }
+    }
 
-        for page in pdf.get_pages() {
-            if let Ok(page_images) = &mut pdf.get_page_images(page.1) {
-                images.append(page_images);
-            }
+    async fn get_pdf_bytes(&self) -> Result<Arc<Vec<u8>>> {
+        if let Some(pdf) = &self.pdf {
+            return Ok(pdf.0.clone());
         }
 
-        if let Some(map) = images.first() {
-            return Ok(Vec::from(map.content));
+        let req = client().get(&self.uri).send().await?;
+        let bytes = req.bytes().await?;
+        Ok(Arc::new(bytes.to_vec()))
+    }
+
+    // Every step of processing reads the PDF, so it's held on to rather than downloaded by each
+    pub(crate) async fn fetch_pdf(&mut self) -> Result<()> {
+        if self.pdf.is_none() {
+            self.pdf = Some(PdfBytes(self.get_pdf_bytes().await?));
         }
+        Ok(())
+    }
 
-        Err(anyhow!("No map found in {}", &self.uri))
+    async fn get_pdf(&self) -> Result<Document> {
+        let bytes = self.get_pdf_bytes().await?;
+        Document::load_mem(&bytes).map_err(Into::into)
     }
 
     pub(crate) async fn get_doc_text(&self) -> Result<String> {
+        if let Some(ocr_text) = &self.ocr_text {
+            return Ok(ocr_text.clone());
+        }
+
         let pdf = self.get_pdf().await?;
+        Self::extract_doc_text(&pdf)
+    }
 
+    pub(crate) fn extract_doc_text(pdf: &Document) -> Result<String> {
         let mut doc_text = String::new();
         for page in pdf.get_pages() {
             let page_text = &mut pdf.extract_text(&[page.0])?;
@@ -161,6 +474,26 @@ impl Gazette {
         Ok(doc_text)
     }
 
+    // A scanned page is just one big image per page, so every image on the page goes to the OCR service.
+    // They're re-encoded as PNG since the OCR service can't read raw PDF image streams
+    pub(crate) fn extract_page_scans(pdf: &Document, page_numbers: &[u32]) -> Vec<Vec<u8>> {
+        let pages = pdf.get_pages();
+
+        page_numbers
+            .iter()
+            .filter_map(|page_number| pages.get(page_number))
+            .filter_map(|page_id| pdf.get_page_images(*page_id).ok())
+            .flat_map(|images| {
+                images
+                    .iter()
+                    .filter_map(|image| decode_pdf_image(pdf, image).ok())
+                    .filter_map(|image| MapImage::encode(&image, MapImageFormat::Png).ok())
+                    .map(|scan| scan.data)
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
     fn parse_date_text(date_string: &str) -> Result<Vec<NaiveDate>> {
         let mut date_result = Vec::new();
         let date_regex = Regex::new(r"\b\d{1,2}\s+[ADFJMNOS]\w+(?:\s+\d{4})?\b")?;
@@ -168,10 +501,12 @@ impl Gazette {
 
         for cap in date_regex.captures_iter(date_string) {
             if let Some(date_str) = cap.get(0) {
-                if let Ok(parsed_date) = NaiveDate::parse_from_str(date_str.as_str(), "%e %B %Y").or_else(|_| {
-                    let with_year = format!("{} {}", date_str.as_str(), current_year);
-                    NaiveDate::parse_from_str(with_year.as_str(), "%e %B %Y")
-                }) {
+                if let Ok(parsed_date) = NaiveDate::parse_from_str(date_str.as_str(), "%e %B %Y")
+                    .or_else(|_| {
+                        let with_year = format!("{} {}", date_str.as_str(), current_year);
+                        NaiveDate::parse_from_str(with_year.as_str(), "%e %B %Y")
+                    })
+                {
                     date_result.push(parsed_date);
                 }
             }
@@ -181,14 +516,19 @@ impl Gazette {
     }
 
     pub(crate) async fn get_date(&self) -> Result<(NaiveDate, NaiveDate)> {
-        let pdf = self.get_pdf().await?;
+        let all_text = if let Some(ocr_text) = &self.ocr_text {
+            ocr_text.clone()
+        } else {
+            let pdf = self.get_pdf().await?;
 
-        let mut all_text = String::new();
-        for page in pdf.get_pages() {
-            if let Ok(page_text) = pdf.extract_text(&[page.0]) {
-                all_text.push_str(&page_text);
+            let mut all_text = String::new();
+            for page in pdf.get_pages() {
+                if let Ok(page_text) = pdf.extract_text(&[page.0]) {
+                    all_text.push_str(&page_text);
+                }
             }
-        }
+            all_text
+        };
 
         let declaration_start_index = all_text
             .find("This declaration will be in place")
@@ -199,13 +539,11 @@ impl Gazette {
         let declaration_end_index = search_text.find(". ").unwrap_or(search_text.len());
 
         let date_string = if search_text.starts_with("This declaration will be in place") {
-            search_text
-                ["This declaration will be in place".len()..declaration_end_index]
+            search_text["This declaration will be in place".len()..declaration_end_index]
                 .trim()
                 .to_string()
         } else if search_text.starts_with("This declaration will operate as follows") {
-            search_text
-                ["This declaration will operate as follows".len()..declaration_end_index]
+            search_text["This declaration will operate as follows".len()..declaration_end_index]
                 .trim()
                 .to_string()
         } else {
@@ -234,6 +572,37 @@ impl Gazette {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::db::mock::MockDatabaseProvider;
+    use crate::geocoder::mock::MockGeocoderProvider;
+    use crate::location_parser::mock::MockLocationParser;
+    use crate::ocr::mock::MockOcrService;
+    use crate::renderer::mock::MockPageRenderer;
+    use crate::storage::mock::MockStorageService;
+
+    type MockHandler = GazetteHandler<
+        MockDatabaseProvider,
+        MockStorageService,
+        MockLocationParser,
+        MockGeocoderProvider,
+        MockOcrService,
+        MockPageRenderer,
+    >;
+
+    // Every service mocked to succeed; the storage is passed in so a test can check its uploads
+    fn mock_handler(uri: &str, storage: MockStorageService) -> MockHandler {
+        GazetteHandler {
+            gazette: Gazette {
+                uri: uri.to_string(),
+                ..Default::default()
+            },
+            database_provider: MockDatabaseProvider::new(),
+            storage_service: storage,
+            location_parser: MockLocationParser::new(),
+            geocoder: MockGeocoderProvider,
+            ocr_service: MockOcrService::new(true),
+            page_renderer: MockPageRenderer::new(true),
+        }
+    }
 
     #[tokio::test]
     async fn test_parse_date() {
@@ -246,6 +615,68 @@ mod tests {
         assert!(date_range.0 < date_range.1);
     }
 
+    #[test]
+    fn test_review() {
+        // Notices saved before review existed stay public
+        let gazette: Gazette = serde_json::from_str(
+            r#"{"uri": "https://example.com/notice.pdf", "title": null, "img_uri": null,
+                "img_width": null, "img_height": null, "img_format": null, "flagged": true,
+                "polygon": null, "start": null, "end": null, "ocr_text": null, "archive": null}"#,
+        )
+        .unwrap();
+        assert_eq!(gazette.review, Review::Unreviewed);
+        assert!(gazette.review.is_public());
+
+        assert!(!Review::Pending.is_public());
+        let by = "sam".to_string();
+        assert!(Review::Approved {
+            by: by.clone(),
+            at: 0
+        }
+        .is_public());
+        assert!(!Review::Rejected { by, at: 0 }.is_public());
+    }
+
+    #[test]
+    fn test_corridor_from() {
+        assert_eq!(
+            corridor_from("all public places within 50 metres of Flinders Street Railway Station"),
+            Some(Corridor::Radius { metres: 50.0 })
+        );
+        assert_eq!(
+            corridor_from("within a radius of 1.5 km of the Bendigo Showgrounds"),
+            Some(Corridor::Radius { metres: 1500.0 })
+        );
+        assert_eq!(
+            corridor_from("the Frankston railway line and all land within 20m of it"),
+            Some(Corridor::Line { metres: 20.0 })
+        );
+        assert_eq!(
+            corridor_from("Smith Street and 30 metres on either side, between Gertrude Street and Alexandra Parade"),
+            Some(Corridor::Line { metres: 30.0 })
+        );
+        assert_eq!(
+            corridor_from("the area bound by Flinders Street, Spencer Street and La Trobe Street"),
+            None
+        );
+
+        // Distances in an exemption aren't the area's
+        let exempted = "The designated area is bound by Flinders Street, Spencer Street and \
+            La Trobe Street. This declaration does not apply to any person within 10 metres of \
+            their place of residence.";
+        assert_eq!(corridor_from(exempted), None);
+        assert_eq!(
+            corridor_from(&format!(
+                "{exempted} All land within 1.5 km of the Bendigo Showgrounds."
+            )),
+            Some(Corridor::Radius { metres: 1500.0 })
+        );
+        assert_eq!(
+            corridor_from("The Frankston railway line, except land within 5 metres of a residence"),
+            None
+        );
+    }
+
     #[test]
     fn test_date_regex() {
         let date_string = "1.00 pm on Monday 1 September, to 1.59 am on Saturday  11 October 2025";
@@ -299,25 +730,202 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_gazette_extraction() {
-        let gazette = GazetteHandler {
-            gazette: Gazette
-            {
-                uri: "http://www.gazette.vic.gov.au/gazette/Gazettes2025/GG2025S737.pdf".to_string(),
+    async fn test_cached_ocr_text() {
+        let gazette = Gazette {
+            uri: "http://localhost/not-a-real-gazette.pdf".to_string(),
+            ocr_text: Some("This declaration will be in place from 1.00 pm on Monday 1 September 2025, to 1.59 am on Saturday 11 October 2025. ".to_string()),
+            ..Default::default()
+        };
+
+        let doc_text = gazette.get_doc_text().await.unwrap();
+        assert_eq!(&doc_text, gazette.ocr_text.as_ref().unwrap());
+
+        let date_range = gazette.get_date().await.unwrap();
+        assert_eq!(date_range.0, NaiveDate::from_ymd_opt(2025, 9, 1).unwrap());
+        assert_eq!(date_range.1, NaiveDate::from_ymd_opt(2025, 10, 11).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_render_vector_map() {
+        use crate::utils::mapimage::tests::{document_with_pages, drawn_map};
+
+        let handler = mock_handler("", MockStorageService::new(true));
+
+        let pdf = document_with_pages(vec![(vec![], vec![]), (vec![], drawn_map(600))]);
+        assert!(select_map(&pdf).is_err());
+
+        let map = handler.render_vector_map(&pdf, &[]).await.unwrap();
+        assert_eq!(
+            (map.width, map.height),
+            (MockPageRenderer::WIDTH, MockPageRenderer::HEIGHT)
+        );
+
+        let pdf = document_with_pages(vec![(vec![], vec![])]);
+        assert!(handler.render_vector_map(&pdf, &[]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_upload_variants() {
+        use crate::utils::mapimage::tests::red_and_grey;
+        use image::{DynamicImage, RgbImage};
+
+        let handler = mock_handler(
+            "http://www.gazette.vic.gov.au/gazette/Gazettes2025/GG2025S737.pdf",
+            MockStorageService::new(true),
+        );
+
+        let source = DynamicImage::ImageRgb8(
+            RgbImage::from_raw(1000, 500, red_and_grey(1000, 500)).unwrap(),
+        );
+        let map = MapImage::encode(&source, MapImageFormat::Jpeg).unwrap();
+        let variants = handler.try_upload_variants(&map).await.unwrap();
+        let thumb = map.resize_to_width(256).unwrap().unwrap();
+
+        assert_eq!(variants.len(), 2);
+        assert_eq!(
+            variants[0].uri,
+            format!("mock_url/{}.jpg", make_content_hash(&thumb.data))
+        );
+        assert_eq!((variants[0].width, variants[0].height), (256, 128));
+        assert!(variants[1].uri.starts_with("mock_url/") && variants[1].uri.ends_with(".jpg"));
+        assert_eq!((variants[1].width, variants[1].height), (800, 400));
+
+        let source =
+            DynamicImage::ImageRgb8(RgbImage::from_raw(300, 300, red_and_grey(300, 300)).unwrap());
+        let map = MapImage::encode(&source, MapImageFormat::Jpeg).unwrap();
+        let variants = handler.try_upload_variants(&map).await.unwrap();
+
+        assert_eq!(variants.len(), 1);
+        assert_eq!(variants[0].name, "thumb");
+    }
+
+    #[tokio::test]
+    async fn test_archive_pdf() {
+        let handler = mock_handler(
+            "http://www.gazette.vic.gov.au/gazette/Gazettes2025/GG2025S737.pdf",
+            MockStorageService::new(true),
+        );
+
+        let archive = handler.archive_pdf(b"%PDF-1.5".to_vec()).await.unwrap();
+        let sha256 = make_content_hash(b"%PDF-1.5");
+
+        assert_eq!(archive.uri, format!("mock_url/{sha256}.pdf"));
+        assert_eq!(archive.size, 8);
+        assert_eq!(archive.sha256, sha256);
+        assert_eq!(archive.sha256.len(), 64);
+    }
+
+    #[tokio::test]
+    async fn test_fill_area() {
+        let mut scanned = Gazette {
+            uri: "http://localhost:9/GG2025S737.pdf".to_string(),
+            ocr_text: Some("Planned Operation in Melbourne CBD \nDeclared by".to_string()),
+            ..Default::default()
+        };
+        scanned.fill_area().await;
+        assert_eq!(scanned.area.as_deref(), Some("Melbourne CBD"));
+
+        // One already worked out is kept
+        let mut named = Gazette {
+            area: Some("Dandenong".to_string()),
+            ..scanned
+        };
+        named.fill_area().await;
+        assert_eq!(named.area.as_deref(), Some("Dandenong"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_pdf() {
+        let mut given = Gazette {
+            uri: "http://localhost:9/GG2025S737.pdf".to_string(),
+            pdf: Some(PdfBytes(Arc::new(b"%PDF-1.5".to_vec()))),
+            ..Default::default()
+        };
+        given.fetch_pdf().await.unwrap();
+        let bytes = given.get_pdf_bytes().await.unwrap();
+        assert_eq!(*bytes, b"%PDF-1.5".to_vec());
+        assert!(Arc::ptr_eq(&bytes, &given.pdf.as_ref().unwrap().0));
+
+        // Nothing is held when the download fails, so later steps try it again
+        let mut unreachable = Gazette { pdf: None, ..given };
+        assert!(unreachable.fetch_pdf().await.is_err());
+        assert!(unreachable.pdf.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_upload_image_is_deduplicated() {
+        use crate::utils::mapimage::tests::red_and_grey;
+        use image::{DynamicImage, RgbImage};
+
+        let storage = MockStorageService::new(true);
+        let handler = mock_handler("", storage.clone());
+
+        let source =
+            DynamicImage::ImageRgb8(RgbImage::from_raw(300, 300, red_and_grey(300, 300)).unwrap());
+        let map = MapImage::encode(&source, MapImageFormat::Png).unwrap();
+
+        let first = handler.try_upload_image(&map).await.unwrap();
+        let second = handler.try_upload_image(&map).await.unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(first.key, format!("{}.png", make_content_hash(&map.data)));
+        assert_eq!(first.mime, "image/png");
+        assert_eq!(first.size, map.data.len());
+        assert_eq!(storage.upload_count(), 1);
+
+        let stored = storage.get(&first.key).await.unwrap().unwrap();
+        assert_eq!(
+            stored.cache_control.as_deref(),
+            Some(IMMUTABLE_CACHE_CONTROL)
+        );
+    }
+
+    #[test]
+    fn test_polygon_leaves_out_localities() {
+        use crate::geocoder::{GeocodeResult, MatchType};
+        use crate::utils::maptypes::GeoPosition;
+
+        let point = |latitude, match_type| GeocodedPoint {
+            result: GeocodeResult {
+                position: GeoPosition {
+                    latitude,
+                    longitude: 144.96,
+                },
+                match_type,
                 ..Default::default()
             },
-            database_provider: crate::db::mock::MockDatabaseProvider::new(),
-            image_service: crate::image_service::mock::MockImageService::new(true),
-            location_parser: crate::location_parser::mock::MockLocationParser::new(),
-            geocoder: crate::geocoder::mock::MockGeocoderProvider { },
+            ..Default::default()
         };
+        let points = [
+            point(-37.81, MatchType::Intersection),
+            point(-37.82, MatchType::Locality),
+            point(-37.83, MatchType::Poi),
+        ];
+
+        let polygon = polygon_from(&points);
+        assert_eq!(polygon.data.len(), 2);
+        assert!(polygon
+            .data
+            .iter()
+            .all(|position| position.latitude != -37.82));
+    }
+
+    #[tokio::test]
+    async fn test_gazette_extraction() {
+        let gazette = mock_handler(
+            "http://www.gazette.vic.gov.au/gazette/Gazettes2025/GG2025S737.pdf",
+            MockStorageService::new(true),
+        );
 
-        let polygon = gazette.get_polygon().await.unwrap();
-        let image = gazette.try_upload_image().await.unwrap();
+        let (points, area) = gazette.get_geocoded_points().await.unwrap();
+        let map = gazette.get_map().await.unwrap();
+        let image = gazette.try_upload_image(&map).await.unwrap();
         let date_range = gazette.get_date().await.unwrap();
 
-        assert!(polygon.is_some());
-        assert!(image.is_some());
+        assert!(!polygon_from(&points).data.is_empty());
+        assert!(!area.is_empty());
+        assert!(!image.url.is_empty());
+        assert!(map.width > 0 && map.height > 0);
         assert!(date_range.0 <= date_range.1);
     }
 }
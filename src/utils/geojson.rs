@@ -1,4 +1,5 @@
-use crate::utils::maptypes::GeoPosition;
+use crate::utils::geometry::DisplayShape;
+use crate::utils::maptypes::{GeoPosition, MapPolygon};
 use serde::Serialize;
 
 #[derive(Serialize)]
@@ -8,6 +9,10 @@ pub enum GeoJsonGeometry {
     Point { coordinates: [f64; 2] },
     #[serde(rename = "Polygon")]
     Polygon { coordinates: Vec<Vec<[f64; 2]>> },
+    #[serde(rename = "MultiPolygon")]
+    MultiPolygon {
+        coordinates: Vec<Vec<Vec<[f64; 2]>>>,
+    },
 }
 
 #[derive(Serialize)]
@@ -29,9 +34,14 @@ pub struct GeoJsonFeature {
 pub struct GeoJsonProperties {
     pub title: Option<String>,
     pub uri: String,
+    pub document_uri: String,
     pub img_uri: Option<String>,
     pub start: String,
     pub end: String,
+    // Square metres, for anything drawn as a shape
+    pub area: Option<f64>,
+    // False for notices that were never published in the Government Gazette
+    pub gazetted: bool,
 }
 
 impl GeoJsonFeatureCollection {
@@ -48,3 +58,29 @@ impl From<GeoPosition> for [f64; 2] {
         [pos.longitude, pos.latitude]
     }
 }
+
+impl From<&MapPolygon> for Vec<[f64; 2]> {
+    fn from(polygon: &MapPolygon) -> Self {
+        polygon
+            .data
+            .iter()
+            .map(|point| [point.longitude, point.latitude])
+            .collect()
+    }
+}
+
+impl From<&DisplayShape> for GeoJsonGeometry {
+    fn from(shape: &DisplayShape) -> Self {
+        match shape {
+            DisplayShape::Point { position } => Self::Point {
+                coordinates: position.clone().into(),
+            },
+            DisplayShape::Polygon { ring } => Self::Polygon {
+                coordinates: vec![ring.into()],
+            },
+            DisplayShape::MultiPolygon { rings } => Self::MultiPolygon {
+                coordinates: rings.iter().map(|ring| vec![ring.into()]).collect(),
+            },
+        }
+    }
+}
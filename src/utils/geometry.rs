@@ -0,0 +1,353 @@
+use crate::utils::gazette::Gazette;
+use crate::utils::maptypes::{Corridor, GeoPosition, MapPolygon, ShapeMode};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::env;
+
+const DEFAULT_ISOLATION_KM: f64 = 5.0;
+const DEFAULT_MIN_NEIGHBOURS: usize = 2;
+const DEFAULT_OUTLIER_Z_SCORE: f64 = 3.0;
+const DEFAULT_OUTLIER_BUFFER_KM: f64 = 1.0;
+
+// How the geocoded points are cleaned up before they're drawn; it's stored with the result,
+// so a shape can be explained later even if the settings have changed since
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CleanupSettings {
+    // Points with fewer than `min_neighbours` others this close are dropped
+    pub isolation_km: f64,
+    pub min_neighbours: usize,
+    // Points whose nearest neighbour is unusually far compared to the rest are dropped, unless
+    // it's within the buffer; no z-score turns this off
+    pub outlier_z_score: Option<f64>,
+    pub outlier_buffer_km: f64,
+}
+
+impl Default for CleanupSettings {
+    fn default() -> Self {
+        Self {
+            isolation_km: DEFAULT_ISOLATION_KM,
+            min_neighbours: DEFAULT_MIN_NEIGHBOURS,
+            outlier_z_score: Some(DEFAULT_OUTLIER_Z_SCORE),
+            outlier_buffer_km: DEFAULT_OUTLIER_BUFFER_KM,
+        }
+    }
+}
+
+impl CleanupSettings {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let setting = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+
+        Self {
+            isolation_km: setting("GEOMETRY_ISOLATION_KM")
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(defaults.isolation_km),
+            min_neighbours: setting("GEOMETRY_MIN_NEIGHBOURS")
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(defaults.min_neighbours),
+            outlier_z_score: match setting("GEOMETRY_OUTLIER_Z_SCORE").as_deref() {
+                Some("off") => None,
+                Some(value) => value.parse().ok().or(defaults.outlier_z_score),
+                None => defaults.outlier_z_score,
+            },
+            outlier_buffer_km: setting("GEOMETRY_OUTLIER_BUFFER_KM")
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(defaults.outlier_buffer_km),
+        }
+    }
+
+    fn apply(&self, points: &MapPolygon) -> MapPolygon {
+        let mut cleaned = points.clone();
+        cleaned
+            .remove_identical_points()
+            .remove_isolated_points(self.isolation_km, self.min_neighbours);
+        if let Some(z_score) = self.outlier_z_score {
+            cleaned.remove_outliers_by_proximity(z_score, self.outlier_buffer_km);
+        }
+        cleaned
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", tag = "kind")]
+pub enum DisplayShape {
+    Point { position: GeoPosition },
+    Polygon { ring: MapPolygon },
+    MultiPolygon { rings: Vec<MapPolygon> },
+}
+
+// Where the shape came from, in the order they're preferred
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", tag = "from")]
+pub enum GeometrySource {
+    // Drawn by hand in the admin editor
+    Override,
+    Corridor { corridor: Corridor },
+    Boundary,
+    Points { shape: ShapeMode },
+    // Too few points survived the cleanup for a shape
+    Centroid,
+}
+
+// What the map draws for a notice, worked out once when it's processed
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DisplayGeometry {
+    pub shape: DisplayShape,
+    pub source: GeometrySource,
+    // Square metres, for anything drawn as a shape
+    pub area: Option<f64>,
+    pub cleanup: CleanupSettings,
+    // What the cleanup threw away; the points it started from stay on the gazette
+    #[serde(default)]
+    pub removed: Vec<GeoPosition>,
+}
+
+// A correction made by a maintainer; one without a ring put the computed shape back
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GeometryEdit {
+    pub ring: Option<MapPolygon>,
+    pub editor: String,
+    // Seconds since the epoch
+    pub edited_at: i64,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+impl GeometryEdit {
+    pub fn new(ring: Option<MapPolygon>, editor: &str, note: Option<String>) -> Self {
+        Self {
+            ring,
+            editor: editor.to_string(),
+            edited_at: Utc::now().timestamp(),
+            note: note.filter(|note| !note.trim().is_empty()),
+        }
+    }
+}
+
+// Records the edit and redraws the notice with the cleanup it was processed with
+pub fn apply_edit(gazette: &mut Gazette, edit: GeometryEdit) {
+    gazette.geometry_override.clone_from(&edit.ring);
+    gazette.geometry_edits.push(edit);
+    let cleanup = gazette
+        .display
+        .as_ref()
+        .map_or_else(CleanupSettings::from_env, |display| display.cleanup.clone());
+    gazette.display = display_geometry(gazette, &cleanup);
+}
+
+// A maintainer's override beats everything, then a corridor in the text, then a boundary traced
+// along the streets, and otherwise the cleaned-up points are joined the way the notice asks for
+pub fn display_geometry(gazette: &Gazette, cleanup: &CleanupSettings) -> Option<DisplayGeometry> {
+    let geometry = |shape, source, area, removed| DisplayGeometry {
+        shape,
+        source,
+        area,
+        cleanup: cleanup.clone(),
+        removed,
+    };
+
+    if let Some(ring) = &gazette.geometry_override {
+        return Some(geometry(
+            DisplayShape::Polygon { ring: ring.clone() },
+            GeometrySource::Override,
+            Some(ring.area()),
+            Vec::new(),
+        ));
+    }
+
+    let points = gazette.polygon.as_ref()?;
+
+    // Corridors keep every place, since the ends of a line are the furthest from the rest
+    if let Some(corridor) = gazette.corridor {
+        let rings = points.clone().remove_identical_points().corridor(corridor);
+        if !rings.is_empty() {
+            let area = rings.iter().map(MapPolygon::area).sum();
+            return Some(geometry(
+                DisplayShape::MultiPolygon { rings },
+                GeometrySource::Corridor { corridor },
+                Some(area),
+                Vec::new(),
+            ));
+        }
+    }
+
+    if let Some(boundary) = gazette
+        .boundary
+        .as_ref()
+        .filter(|boundary| boundary.data.len() > 3)
+    {
+        return Some(geometry(
+            DisplayShape::Polygon {
+                ring: boundary.clone(),
+            },
+            GeometrySource::Boundary,
+            Some(boundary.area()),
+            Vec::new(),
+        ));
+    }
+
+    // Cleanup that leaves nothing was wrong for this notice, such as corners far apart in
+    // the country, so the points are drawn as they are
+    let mut cleaned = cleanup.apply(points);
+    if cleaned.data.is_empty() {
+        cleaned = points.clone();
+    }
+    if cleaned.data.is_empty() {
+        return None;
+    }
+    let removed = points
+        .data
+        .iter()
+        .filter(|point| !cleaned.data.contains(point))
+        .cloned()
+        .collect();
+
+    Some(if cleaned.data.len() < 3 {
+        geometry(
+            DisplayShape::Point {
+                position: cleaned.centroid(),
+            },
+            GeometrySource::Centroid,
+            None,
+            removed,
+        )
+    } else {
+        let ring = cleaned.shaped(gazette.shape);
+        let area = ring.area();
+        geometry(
+            DisplayShape::Polygon { ring },
+            GeometrySource::Points {
+                shape: gazette.shape,
+            },
+            Some(area),
+            removed,
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A block of CBD corners, one of them twice and one geocoded to Geelong
+    fn gazette() -> Gazette {
+        Gazette {
+            polygon: Some(MapPolygon {
+                data: vec![
+                    GeoPosition::at(144.9525, -37.8183),
+                    GeoPosition::at(144.9668, -37.8176),
+                    GeoPosition::at(144.9730, -37.8105),
+                    GeoPosition::at(144.9730, -37.8105),
+                    GeoPosition::at(144.9589, -37.8115),
+                    GeoPosition::at(144.3617, -38.1499),
+                ],
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_display_geometry() {
+        let gazette = gazette();
+        let display = display_geometry(&gazette, &CleanupSettings::default()).unwrap();
+
+        assert_eq!(
+            display.source,
+            GeometrySource::Points {
+                shape: ShapeMode::Convex
+            }
+        );
+        assert_eq!(display.removed, vec![GeoPosition::at(144.3617, -38.1499)]);
+        assert_eq!(display.cleanup, CleanupSettings::default());
+        let DisplayShape::Polygon { ring } = &display.shape else {
+            panic!("Expected a polygon");
+        };
+        assert_eq!(ring.data.len(), 5);
+        assert!(display.area.unwrap() > 500_000.0);
+
+        // The raw points are left alone
+        assert_eq!(gazette.polygon.unwrap().data.len(), 6);
+    }
+
+    #[test]
+    fn test_display_geometry_sources() {
+        let corridor = Corridor::Radius { metres: 50.0 };
+        let display = display_geometry(
+            &Gazette {
+                corridor: Some(corridor),
+                ..gazette()
+            },
+            &CleanupSettings::default(),
+        )
+        .unwrap();
+        assert_eq!(display.source, GeometrySource::Corridor { corridor });
+        assert!(matches!(&display.shape, DisplayShape::MultiPolygon { rings } if rings.len() == 5));
+
+        // Too few points for a shape gives their centroid
+        let pair = Gazette {
+            polygon: Some(MapPolygon {
+                data: vec![
+                    GeoPosition::at(144.95, -37.82),
+                    GeoPosition::at(144.96, -37.82),
+                ],
+            }),
+            ..Default::default()
+        };
+        let display = display_geometry(&pair, &CleanupSettings::default()).unwrap();
+        assert_eq!(display.source, GeometrySource::Centroid);
+        assert!(display.area.is_none());
+
+        // Cleanup that would leave nothing is skipped
+        let strict = CleanupSettings {
+            isolation_km: 0.1,
+            ..Default::default()
+        };
+        let display = display_geometry(&gazette(), &strict).unwrap();
+        assert!(display.removed.is_empty());
+        assert!(matches!(&display.shape, DisplayShape::Polygon { .. }));
+
+        assert!(display_geometry(&Gazette::default(), &strict).is_none());
+    }
+
+    #[test]
+    fn test_geometry_override() {
+        let ring = MapPolygon {
+            data: vec![
+                GeoPosition::at(144.95, -37.82),
+                GeoPosition::at(144.95, -37.81),
+                GeoPosition::at(144.96, -37.81),
+            ],
+        };
+        let mut gazette = Gazette {
+            corridor: Some(Corridor::Radius { metres: 50.0 }),
+            ..gazette()
+        };
+        gazette.display = display_geometry(&gazette, &CleanupSettings::default());
+
+        apply_edit(
+            &mut gazette,
+            GeometryEdit::new(Some(ring.clone()), "maintainer", Some(" ".to_string())),
+        );
+        let display = gazette.display.as_ref().unwrap();
+        assert_eq!(display.source, GeometrySource::Override);
+        assert!(matches!(&display.shape, DisplayShape::Polygon { ring: drawn } if *drawn == ring));
+        assert!(gazette.geometry_edits[0].note.is_none());
+
+        // Clearing it goes back to the computed shape, and both edits are kept
+        apply_edit(&mut gazette, GeometryEdit::new(None, "maintainer", None));
+        assert!(gazette.geometry_override.is_none());
+        assert_eq!(gazette.geometry_edits.len(), 2);
+        assert!(matches!(
+            gazette.display.unwrap().source,
+            GeometrySource::Corridor { .. }
+        ));
+
+        // Notices without any points can still be drawn by hand
+        let mut gazette = Gazette::default();
+        apply_edit(
+            &mut gazette,
+            GeometryEdit::new(Some(ring), "maintainer", None),
+        );
+        assert!(gazette.display.is_some());
+    }
+}
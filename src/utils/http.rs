@@ -0,0 +1,201 @@
+use crate::db::core::DatabaseProvider;
+use crate::db::DatabaseConnection;
+use anyhow::{anyhow, Result};
+use chrono::Local;
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Client, RequestBuilder, Response, StatusCode, Url};
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tokio::sync::{Mutex as AsyncMutex, Semaphore};
+use tokio::time::Instant;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+// Long enough for a large gazette PDF
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+const MAX_RETRIES: u32 = 3;
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+static CLIENT: OnceLock<Client> = OnceLock::new();
+// Calls made since the last flush, by day then API
+static API_CALLS: Mutex<BTreeMap<(String, String), u64>> = Mutex::new(BTreeMap::new());
+
+// One client for the whole app, so connections are pooled and nothing waits forever
+pub fn client() -> &'static Client {
+    CLIENT.get_or_init(|| {
+        Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .unwrap_or_default()
+    })
+}
+
+// Anything else, like a javascript: address, can't be put in a link
+pub fn is_web_address(uri: &str) -> bool {
+    Url::parse(uri).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+// Each external API gets one of these as a static, shared by everything that calls it
+pub struct ApiLimiter {
+    name: &'static str,
+    min_interval: Duration,
+    permits: Semaphore,
+    last_request: AsyncMutex<Option<Instant>>,
+}
+
+impl ApiLimiter {
+    pub const fn new(name: &'static str, min_interval: Duration, max_concurrent: usize) -> Self {
+        Self {
+            name,
+            min_interval,
+            permits: Semaphore::const_new(max_concurrent),
+            last_request: AsyncMutex::const_new(None),
+        }
+    }
+
+    // Holding the lock while sleeping queues concurrent requests up behind each other
+    async fn wait_for_turn(&self) {
+        let mut last_request = self.last_request.lock().await;
+
+        if let Some(last) = *last_request {
+            let elapsed = last.elapsed();
+            if elapsed < self.min_interval {
+                tokio::time::sleep(self.min_interval - elapsed).await;
+            }
+        }
+        *last_request = Some(Instant::now());
+    }
+
+    // Every attempt is counted, since retried requests are billed too
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        let _permit = self.permits.acquire().await?;
+        let mut attempt = 0;
+
+        loop {
+            let this_attempt = request.try_clone().ok_or(anyhow!(
+                "Streaming requests to {} can't be retried",
+                self.name
+            ))?;
+
+            self.wait_for_turn().await;
+            record_api_call(self.name);
+            let response = this_attempt.send().await;
+
+            let retryable = match &response {
+                Ok(response) => is_retryable(response.status()),
+                Err(e) => e.is_timeout() || e.is_connect(),
+            };
+            if !retryable || attempt >= MAX_RETRIES {
+                return Ok(response?.error_for_status()?);
+            }
+
+            let delay = response
+                .ok()
+                .and_then(|response| retry_after(&response))
+                .unwrap_or_else(|| backoff(attempt));
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+// Only the delay-in-seconds form; APIs that send a date get the usual backoff
+fn retry_after(response: &Response) -> Option<Duration> {
+    let seconds = response
+        .headers()
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(Duration::from_secs(seconds).min(MAX_BACKOFF))
+}
+
+fn backoff(attempt: u32) -> Duration {
+    INITIAL_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_BACKOFF)
+}
+
+pub fn today() -> String {
+    Local::now().format("%Y-%m-%d").to_string()
+}
+
+fn record_api_call(name: &str) {
+    if let Ok(mut calls) = API_CALLS.lock() {
+        *calls.entry((today(), name.to_string())).or_default() += 1;
+    }
+}
+
+fn take_api_calls() -> BTreeMap<(String, String), u64> {
+    API_CALLS
+        .lock()
+        .map(|mut calls| std::mem::take(&mut *calls))
+        .unwrap_or_default()
+}
+
+// Adds the calls made since the last flush to the daily totals; anything the database
+// doesn't take is kept for next time
+pub async fn flush_api_calls<T>(db: &DatabaseConnection<T>) -> Result<()>
+where
+    T: DatabaseProvider,
+{
+    let mut failed = BTreeMap::new();
+
+    for ((day, name), calls) in take_api_calls() {
+        if db.record_api_calls(&day, &name, calls).await.is_err() {
+            failed.insert((day, name), calls);
+        }
+    }
+
+    if failed.is_empty() {
+        return Ok(());
+    }
+
+    let unsaved = failed.len();
+    if let Ok(mut calls) = API_CALLS.lock() {
+        for (key, count) in failed {
+            *calls.entry(key).or_default() += count;
+        }
+    }
+    Err(anyhow!("Could not save {unsaved} API call counts"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::mock::MockDatabaseProvider;
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(0), INITIAL_BACKOFF);
+        assert_eq!(backoff(2), INITIAL_BACKOFF * 4);
+        assert_eq!(backoff(20), MAX_BACKOFF);
+        assert!(is_retryable(StatusCode::TOO_MANY_REQUESTS));
+        assert!(is_retryable(StatusCode::BAD_GATEWAY));
+        assert!(!is_retryable(StatusCode::NOT_FOUND));
+    }
+
+    #[tokio::test]
+    async fn test_flush_api_calls() {
+        let db = DatabaseConnection {
+            provider: MockDatabaseProvider::new(),
+        };
+
+        record_api_call("test-api");
+        record_api_call("test-api");
+        flush_api_calls(&db).await.unwrap();
+        record_api_call("test-api");
+        flush_api_calls(&db).await.unwrap();
+
+        let calls = db.fetch_api_calls(&today()).await.unwrap();
+        assert_eq!(calls.get("test-api"), Some(&3));
+    }
+}
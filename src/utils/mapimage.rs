@@ -0,0 +1,887 @@
+use anyhow::{anyhow, Result};
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, GrayImage, ImageFormat, RgbImage};
+use lopdf::content::Content;
+use lopdf::xobject::PdfImage;
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::io::Cursor;
+use std::sync::Arc;
+
+// Anything smaller than this on either side is a crest, logo or signature rather than a map
+const MIN_MAP_DIMENSION: u32 = 200;
+// Channel spread above which a pixel counts as coloured rather than grey
+const COLOUR_THRESHOLD: u8 = 40;
+const JPEG_QUALITY: u8 = 85;
+// Below this many path operations a page is only rules and table borders, not a drawn map
+const MIN_MAP_PATH_OPERATIONS: usize = 200;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MapImageFormat {
+    #[default]
+    Jpeg,
+    Png,
+    Webp,
+}
+
+impl MapImageFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            MapImageFormat::Jpeg => "jpg",
+            MapImageFormat::Png => "png",
+            MapImageFormat::Webp => "webp",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            MapImageFormat::Jpeg => "image/jpeg",
+            MapImageFormat::Png => "image/png",
+            MapImageFormat::Webp => "image/webp",
+        }
+    }
+
+    fn from_env() -> Option<Self> {
+        match env::var("MAP_IMAGE_FORMAT").ok()?.to_lowercase().as_str() {
+            "jpeg" | "jpg" => Some(MapImageFormat::Jpeg),
+            "png" => Some(MapImageFormat::Png),
+            "webp" => Some(MapImageFormat::Webp),
+            _ => None,
+        }
+    }
+
+    // Scanned line art compresses far better (and without ringing) as PNG
+    fn preferred_for(image: &DynamicImage) -> Self {
+        match image {
+            DynamicImage::ImageLuma8(_) => MapImageFormat::Png,
+            _ => MapImageFormat::Jpeg,
+        }
+    }
+}
+
+// Thumbnails are shown at 128px in the list, so these leave room for high density screens
+pub const IMAGE_SIZES: [(&str, u32); 2] = [("thumb", 256), ("medium", 800)];
+pub const ORIGINAL_SIZE: &str = "original";
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageVariant {
+    pub name: String,
+    pub uri: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct MapImage {
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    pub format: MapImageFormat,
+    // The pixels it was encoded from, so smaller sizes aren't made from already compressed data
+    source: Arc<DynamicImage>,
+}
+
+impl MapImage {
+    pub fn encode(image: &DynamicImage, format: MapImageFormat) -> Result<Self> {
+        let mut data = Cursor::new(Vec::new());
+
+        match format {
+            MapImageFormat::Jpeg => {
+                let encoder = JpegEncoder::new_with_quality(&mut data, JPEG_QUALITY);
+                match image {
+                    DynamicImage::ImageLuma8(grey) => grey.write_with_encoder(encoder)?,
+                    _ => image.to_rgb8().write_with_encoder(encoder)?,
+                }
+            }
+            MapImageFormat::Png => image.write_to(&mut data, ImageFormat::Png)?,
+            MapImageFormat::Webp => image.write_to(&mut data, ImageFormat::WebP)?,
+        }
+
+        Ok(MapImage {
+            data: data.into_inner(),
+            width: image.width(),
+            height: image.height(),
+            format,
+            source: Arc::new(image.clone()),
+        })
+    }
+
+    fn encode_preferred(image: &DynamicImage) -> Result<Self> {
+        let format =
+            MapImageFormat::from_env().unwrap_or_else(|| MapImageFormat::preferred_for(image));
+        Self::encode(image, format)
+    }
+
+    pub fn from_rendered_page(png: &[u8]) -> Result<Self> {
+        let page = image::load_from_memory_with_format(png, ImageFormat::Png)?;
+        Self::encode_preferred(&page)
+    }
+
+    // Keeps the aspect ratio and format; returns None rather than upscaling a smaller image
+    pub fn resize_to_width(&self, width: u32) -> Result<Option<Self>> {
+        if self.width <= width {
+            return Ok(None);
+        }
+
+        let height = (u64::from(self.height) * u64::from(width) / u64::from(self.width)).max(1);
+        let resized = self
+            .source
+            .resize_exact(width, u32::try_from(height)?, FilterType::Triangle);
+
+        Self::encode(&resized, self.format).map(Some)
+    }
+}
+
+enum ColourSpace {
+    Grey,
+    Rgb,
+    Cmyk,
+    Indexed {
+        base: Box<ColourSpace>,
+        palette: Vec<u8>,
+    },
+}
+
+impl ColourSpace {
+    fn components(&self) -> usize {
+        match self {
+            ColourSpace::Grey | ColourSpace::Indexed { .. } => 1,
+            ColourSpace::Rgb => 3,
+            ColourSpace::Cmyk => 4,
+        }
+    }
+
+    fn resolve(pdf: &Document, object: &Object) -> Result<Self> {
+        let (_, object) = pdf.dereference(object)?;
+
+        match object {
+            Object::Name(name) => match name.as_slice() {
+                b"DeviceGray" | b"CalGray" | b"G" => Ok(ColourSpace::Grey),
+                b"DeviceRGB" | b"CalRGB" | b"RGB" => Ok(ColourSpace::Rgb),
+                b"DeviceCMYK" | b"CMYK" => Ok(ColourSpace::Cmyk),
+                other => Err(anyhow!(
+                    "Unsupported colour space {}",
+                    String::from_utf8_lossy(other)
+                )),
+            },
+            Object::Array(array) => {
+                let family = array
+                    .first()
+                    .ok_or(anyhow!("Empty colour space"))?
+                    .as_name()?;
+
+                match family {
+                    b"ICCBased" => {
+                        let (_, profile) =
+                            pdf.dereference(array.get(1).ok_or(anyhow!("ICC profile missing"))?)?;
+                        match profile.as_stream()?.dict.get(b"N")?.as_i64()? {
+                            1 => Ok(ColourSpace::Grey),
+                            3 => Ok(ColourSpace::Rgb),
+                            4 => Ok(ColourSpace::Cmyk),
+                            n => Err(anyhow!("Unsupported ICC component count {n}")),
+                        }
+                    }
+                    b"Indexed" | b"I" => {
+                        let base =
+                            Self::resolve(pdf, array.get(1).ok_or(anyhow!("Base missing"))?)?;
+                        let (_, lookup) =
+                            pdf.dereference(array.get(3).ok_or(anyhow!("Palette missing"))?)?;
+                        let palette = match lookup {
+                            Object::String(bytes, _) => bytes.clone(),
+                            Object::Stream(stream) => stream.get_plain_content()?,
+                            _ => Err(anyhow!("Unsupported palette"))?,
+                        };
+                        Ok(ColourSpace::Indexed {
+                            base: Box::new(base),
+                            palette,
+                        })
+                    }
+                    b"CalGray" => Ok(ColourSpace::Grey),
+                    b"CalRGB" => Ok(ColourSpace::Rgb),
+                    _ => Self::resolve(pdf, &Object::Name(family.to_vec())),
+                }
+            }
+            _ => Err(anyhow!("Unsupported colour space")),
+        }
+    }
+}
+
+// Filters which only wrap the data; whatever is left afterwards is either raw samples or an image codec
+fn is_transport_filter(filter: &[u8]) -> bool {
+    matches!(
+        filter,
+        b"FlateDecode"
+            | b"Fl"
+            | b"LZWDecode"
+            | b"LZW"
+            | b"ASCII85Decode"
+            | b"A85"
+            | b"RunLengthDecode"
+            | b"RL"
+    )
+}
+
+fn stream_filters(dict: &Dictionary) -> Vec<Vec<u8>> {
+    match dict.get(b"Filter") {
+        Ok(Object::Name(name)) => vec![name.clone()],
+        Ok(Object::Array(array)) => array
+            .iter()
+            .filter_map(|filter| filter.as_name().ok())
+            .map(<[u8]>::to_vec)
+            .collect(),
+        _ => vec![],
+    }
+}
+
+fn filter_params(dict: &Dictionary, index: usize) -> Option<&Dictionary> {
+    match dict.get(b"DecodeParms").ok()? {
+        Object::Dictionary(params) => Some(params),
+        Object::Array(params) => params.get(index)?.as_dict().ok(),
+        _ => None,
+    }
+}
+
+fn decode_run_length(input: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len() * 2);
+    let mut position = 0;
+
+    while let Some(&length) = input.get(position) {
+        match length {
+            128 => break,
+            0..=127 => {
+                let end = (position + 2 + length as usize).min(input.len());
+                output.extend_from_slice(&input[position + 1..end]);
+                position = end;
+            }
+            _ => {
+                if let Some(&byte) = input.get(position + 1) {
+                    output.extend(std::iter::repeat_n(byte, 257 - length as usize));
+                }
+                position += 2;
+            }
+        }
+    }
+
+    output
+}
+
+fn decode_transport(data: Vec<u8>, filter: &[u8], params: Option<&Dictionary>) -> Result<Vec<u8>> {
+    if matches!(filter, b"RunLengthDecode" | b"RL") {
+        return Ok(decode_run_length(&data));
+    }
+
+    let filter: &[u8] = match filter {
+        b"Fl" => b"FlateDecode",
+        b"LZW" => b"LZWDecode",
+        b"A85" => b"ASCII85Decode",
+        other => other,
+    };
+
+    // lopdf already knows how to undo these, it just won't do it for a stream that ends in an image codec
+    let mut dict = Dictionary::new();
+    dict.set("Filter", Object::Name(filter.to_vec()));
+    if let Some(params) = params {
+        dict.set("DecodeParms", Object::Dictionary(params.clone()));
+    }
+
+    Stream::new(dict, data)
+        .decompressed_content()
+        .map_err(Into::into)
+}
+
+fn dimensions(image: &PdfImage) -> Result<(u32, u32)> {
+    Ok((u32::try_from(image.width)?, u32::try_from(image.height)?))
+}
+
+// A Decode array of [1 0] swaps which sample value is drawn dark
+fn is_decode_inverted(dict: &Dictionary) -> bool {
+    dict.get(b"Decode")
+        .and_then(Object::as_array)
+        .ok()
+        .and_then(|decode| decode.first())
+        .and_then(|first| first.as_float().ok())
+        .is_some_and(|first| first > 0.5)
+}
+
+// Only pure 1D Group 3 (K = 0) and Group 4 (K < 0) can be decoded; anything else is refused
+// rather than read as the wrong one
+fn decode_ccitt(
+    data: &[u8],
+    params: Option<&Dictionary>,
+    decode_inverted: bool,
+    width: u32,
+    height: u32,
+) -> Result<GrayImage> {
+    let param = |key: &[u8]| params.and_then(|p| p.get(key).ok());
+    let flag = |key: &[u8]| {
+        param(key)
+            .and_then(|flag| flag.as_bool().ok())
+            .unwrap_or(false)
+    };
+    let k = param(b"K").and_then(|k| k.as_i64().ok()).unwrap_or(0);
+    let columns = param(b"Columns")
+        .and_then(|columns| columns.as_i64().ok())
+        .map_or(Ok(width), u32::try_from)?;
+    let columns = u16::try_from(columns)?;
+
+    if k > 0 {
+        Err(anyhow!(
+            "Unsupported CCITT K {k}, mixed 1D and 2D Group 3 coding"
+        ))?;
+    }
+    // Group 3 lines are found by their end of line codes, which skip the fill bits anyway
+    if k < 0 && flag(b"EncodedByteAlign") {
+        Err(anyhow!(
+            "Unsupported CCITT EncodedByteAlign for Group 4 data"
+        ))?;
+    }
+
+    // Black runs come out as 0 samples unless BlackIs1, and the Decode array can swap them back
+    let (black, white) = if flag(b"BlackIs1") != decode_inverted {
+        (255, 0)
+    } else {
+        (0, 255)
+    };
+    let mut pixels = Vec::with_capacity(width as usize * height as usize);
+    let mut push_line = |transitions: &[u16]| {
+        pixels.extend(
+            fax::decoder::pels(transitions, columns).map(|colour| match colour {
+                fax::Color::Black => black,
+                fax::Color::White => white,
+            }),
+        );
+    };
+
+    let decoded = if k < 0 {
+        fax::decoder::decode_g4(
+            data.iter().copied(),
+            columns,
+            u16::try_from(height).ok(),
+            push_line,
+        )
+    } else {
+        fax::decoder::decode_g3(data.iter().copied(), &mut push_line)
+    };
+    decoded.ok_or(anyhow!("Invalid CCITT data"))?;
+
+    let rows = u32::try_from(pixels.len() / columns as usize)?;
+    pixels.truncate(columns as usize * rows as usize);
+    GrayImage::from_raw(u32::from(columns), rows, pixels).ok_or(anyhow!("Invalid CCITT dimensions"))
+}
+
+// Unpacks 1, 2, 4 or 8 bit samples into one byte each; rows are padded to a whole byte in PDFs
+fn unpack_samples(
+    data: &[u8],
+    bits: usize,
+    samples_per_row: usize,
+    rows: usize,
+    scale: bool,
+) -> Result<Vec<u8>> {
+    if bits == 8 {
+        let expected = samples_per_row * rows;
+        return data
+            .get(..expected)
+            .map(<[u8]>::to_vec)
+            .ok_or(anyhow!("Image data is truncated"));
+    }
+    if !matches!(bits, 1 | 2 | 4) {
+        Err(anyhow!("Unsupported bits per component {bits}"))?;
+    }
+
+    let row_bytes = (samples_per_row * bits).div_ceil(8);
+    let max = (1u16 << bits) - 1;
+    let mut samples = Vec::with_capacity(samples_per_row * rows);
+
+    for row in data.chunks(row_bytes).take(rows) {
+        for index in 0..samples_per_row {
+            let bit = index * bits;
+            let byte = row.get(bit / 8).ok_or(anyhow!("Image data is truncated"))?;
+            let sample = u16::from(byte >> (8 - bits - bit % 8)) & max;
+            let sample = if scale { sample * 255 / max } else { sample };
+            samples.push(u8::try_from(sample)?);
+        }
+    }
+
+    if samples.len() < samples_per_row * rows {
+        Err(anyhow!("Image data is truncated"))?;
+    }
+    Ok(samples)
+}
+
+fn decode_samples(pdf: &Document, image: &PdfImage, data: &[u8]) -> Result<DynamicImage> {
+    let (width, height) = dimensions(image)?;
+    let is_mask = image
+        .origin_dict
+        .get(b"ImageMask")
+        .and_then(Object::as_bool)
+        .unwrap_or(false);
+    let colour_space = if is_mask {
+        ColourSpace::Grey
+    } else {
+        ColourSpace::resolve(pdf, image.origin_dict.get(b"ColorSpace")?)?
+    };
+    let bits = usize::try_from(if is_mask {
+        1
+    } else {
+        image.bits_per_component.unwrap_or(8)
+    })?;
+    let is_indexed = matches!(colour_space, ColourSpace::Indexed { .. });
+    let samples = unpack_samples(
+        data,
+        bits,
+        width as usize * colour_space.components(),
+        height as usize,
+        !is_indexed,
+    )?;
+
+    to_dynamic_image(colour_space, samples, width, height)
+}
+
+fn to_dynamic_image(
+    colour_space: ColourSpace,
+    samples: Vec<u8>,
+    width: u32,
+    height: u32,
+) -> Result<DynamicImage> {
+    let invalid = || anyhow!("Image dimensions don't match its data");
+
+    match colour_space {
+        ColourSpace::Grey => GrayImage::from_raw(width, height, samples)
+            .map(DynamicImage::ImageLuma8)
+            .ok_or_else(invalid),
+        ColourSpace::Rgb => RgbImage::from_raw(width, height, samples)
+            .map(DynamicImage::ImageRgb8)
+            .ok_or_else(invalid),
+        ColourSpace::Cmyk => {
+            let rgb = samples
+                .chunks_exact(4)
+                .flat_map(|cmyk| {
+                    let k = 255 - u16::from(cmyk[3]);
+                    [0, 1, 2]
+                        .map(|i| u8::try_from((255 - u16::from(cmyk[i])) * k / 255).unwrap_or(0))
+                })
+                .collect();
+            RgbImage::from_raw(width, height, rgb)
+                .map(DynamicImage::ImageRgb8)
+                .ok_or_else(invalid)
+        }
+        ColourSpace::Indexed { base, palette } => {
+            let components = base.components();
+            let expanded = samples
+                .iter()
+                .flat_map(|&index| {
+                    let start = index as usize * components;
+                    palette
+                        .get(start..start + components)
+                        .map(<[u8]>::to_vec)
+                        .unwrap_or_else(|| vec![0; components])
+                })
+                .collect();
+            to_dynamic_image(*base, expanded, width, height)
+        }
+    }
+}
+
+pub fn decode_pdf_image(pdf: &Document, image: &PdfImage) -> Result<DynamicImage> {
+    let filters = stream_filters(image.origin_dict);
+    let mut data = image.content.to_vec();
+
+    for (index, filter) in filters.iter().enumerate() {
+        let params = filter_params(image.origin_dict, index);
+
+        if is_transport_filter(filter) {
+            data = decode_transport(data, filter, params)?;
+            continue;
+        }
+
+        let (width, height) = dimensions(image)?;
+        return match filter.as_slice() {
+            b"DCTDecode" | b"DCT" => Ok(image::load_from_memory_with_format(
+                &data,
+                ImageFormat::Jpeg,
+            )?),
+            b"CCITTFaxDecode" | b"CCF" => Ok(DynamicImage::ImageLuma8(decode_ccitt(
+                &data,
+                params,
+                is_decode_inverted(image.origin_dict),
+                width,
+                height,
+            )?)),
+            // There's no decoder for these here, so they're named rather than read as raw samples
+            b"JBIG2Decode" | b"JPXDecode" => Err(anyhow!(
+                "Unsupported image filter {}, there is no decoder for it",
+                String::from_utf8_lossy(filter)
+            )),
+            other => Err(anyhow!(
+                "Unsupported image filter {}",
+                String::from_utf8_lossy(other)
+            )),
+        };
+    }
+
+    decode_samples(pdf, image, &data)
+}
+
+// Maps are large and drawn in colour; crests, logos and signatures are small or monochrome
+#[allow(clippy::cast_precision_loss)]
+fn map_score(image: &DynamicImage) -> Option<f64> {
+    if image.width() < MIN_MAP_DIMENSION || image.height() < MIN_MAP_DIMENSION {
+        return None;
+    }
+
+    let rgb = image.to_rgb8();
+    let pixels = rgb.pixels().count().max(1);
+    let coloured = rgb
+        .pixels()
+        .filter(|pixel| {
+            let max = pixel.0.iter().max().unwrap_or(&0);
+            let min = pixel.0.iter().min().unwrap_or(&0);
+            max - min > COLOUR_THRESHOLD
+        })
+        .count();
+
+    let area = f64::from(image.width()) * f64::from(image.height());
+    Some(area * (1.0 + coloured as f64 / pixels as f64))
+}
+
+pub fn select_map(pdf: &Document) -> Result<MapImage> {
+    let (_, map) = pdf
+        .get_pages()
+        .into_values()
+        .filter_map(|page_id| pdf.get_page_images(page_id).ok())
+        .flatten()
+        .filter_map(|image| decode_pdf_image(pdf, &image).ok())
+        .filter_map(|decoded| map_score(&decoded).map(|score| (score, decoded)))
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .ok_or(anyhow!("No map-like image found"))?;
+
+    MapImage::encode_preferred(&map)
+}
+
+fn count_path_operations(content: &[u8]) -> usize {
+    Content::decode(content).map_or(0, |content| {
+        content
+            .operations
+            .iter()
+            .filter(|operation| {
+                matches!(
+                    operation.operator.as_str(),
+                    "m" | "l" | "c" | "v" | "y" | "re" | "h"
+                )
+            })
+            .count()
+    })
+}
+
+// Maps are often drawn inside a form XObject, so those count towards the page as well
+fn page_path_operations(pdf: &Document, page_id: ObjectId) -> usize {
+    let page_operations = pdf
+        .get_page_content(page_id)
+        .map_or(0, |content| count_path_operations(&content));
+
+    let form_operations = pdf
+        .get_dictionary(page_id)
+        .and_then(|page| pdf.get_dict_in_dict(page, b"Resources"))
+        .and_then(|resources| pdf.get_dict_in_dict(resources, b"XObject"))
+        .map_or(0, |xobjects| {
+            xobjects
+                .iter()
+                .filter_map(|(_, xobject)| pdf.dereference(xobject).ok())
+                .filter_map(|(_, xobject)| xobject.as_stream().ok())
+                .filter(|stream| {
+                    stream
+                        .dict
+                        .get(b"Subtype")
+                        .and_then(Object::as_name)
+                        .is_ok_and(|subtype| subtype == b"Form")
+                })
+                .filter_map(|stream| stream.get_plain_content().ok())
+                .map(|content| count_path_operations(&content))
+                .sum()
+        });
+
+    page_operations + form_operations
+}
+
+// Maps drawn as vector paths have no image to extract, so find the page doing the most drawing instead
+pub fn find_vector_map_page(pdf: &Document) -> Option<u32> {
+    pdf.get_pages()
+        .into_iter()
+        .map(|(number, page_id)| (number, page_path_operations(pdf, page_id)))
+        .filter(|(_, operations)| *operations >= MIN_MAP_PATH_OPERATIONS)
+        .max_by_key(|(_, operations)| *operations)
+        .map(|(number, _)| number)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use lopdf::dictionary;
+
+    fn image_stream(width: i64, height: i64, colour_space: &str, content: Vec<u8>) -> Stream {
+        Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => width,
+                "Height" => height,
+                "ColorSpace" => colour_space,
+                "BitsPerComponent" => 8,
+            },
+            content,
+        )
+    }
+
+    fn document_with_images(images: Vec<Stream>) -> Document {
+        document_with_pages(vec![(images, vec![])])
+    }
+
+    pub(crate) fn document_with_pages(pages: Vec<(Vec<Stream>, Vec<u8>)>) -> Document {
+        let mut pdf = Document::with_version("1.5");
+        let pages_id = pdf.new_object_id();
+
+        let mut kids: Vec<Object> = vec![];
+        for (images, content) in pages {
+            let mut xobjects = Dictionary::new();
+            for (index, image) in images.into_iter().enumerate() {
+                xobjects.set(format!("Im{index}"), pdf.add_object(image));
+            }
+
+            let content_id = pdf.add_object(Stream::new(dictionary! {}, content));
+            kids.push(
+                pdf.add_object(dictionary! {
+                    "Type" => "Page",
+                    "Parent" => pages_id,
+                    "Contents" => content_id,
+                    "Resources" => dictionary! { "XObject" => xobjects },
+                })
+                .into(),
+            );
+        }
+
+        let count = i64::try_from(kids.len()).unwrap();
+        pdf.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => kids,
+                "Count" => count,
+            }),
+        );
+        let catalog_id = pdf.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        pdf.trailer.set("Root", catalog_id);
+        pdf
+    }
+
+    pub(crate) fn red_and_grey(width: u32, height: u32) -> Vec<u8> {
+        (0..width * height)
+            .flat_map(|i| {
+                if i % 2 == 0 {
+                    [220, 20, 20]
+                } else {
+                    [128, 128, 128]
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_select_map_prefers_large_colour_image() {
+        let logo = image_stream(64, 64, "DeviceRGB", red_and_grey(64, 64));
+        let grey = image_stream(400, 300, "DeviceGray", vec![128; 400 * 300]);
+        let mut map = image_stream(400, 300, "DeviceRGB", red_and_grey(400, 300));
+        map.compress().unwrap();
+
+        let pdf = document_with_images(vec![logo, grey, map]);
+        let selected = select_map(&pdf).unwrap();
+
+        assert_eq!(selected.width, 400);
+        assert_eq!(selected.height, 300);
+        assert_eq!(selected.format, MapImageFormat::Jpeg);
+
+        let decoded =
+            image::load_from_memory_with_format(&selected.data, ImageFormat::Jpeg).unwrap();
+        assert_eq!(decoded.width(), 400);
+    }
+
+    #[test]
+    fn test_select_map_decodes_dct() {
+        let source =
+            DynamicImage::ImageRgb8(RgbImage::from_raw(320, 240, red_and_grey(320, 240)).unwrap());
+        let jpeg = MapImage::encode(&source, MapImageFormat::Jpeg).unwrap();
+        let mut stream = image_stream(320, 240, "DeviceRGB", jpeg.data);
+        stream.dict.set("Filter", "DCTDecode");
+
+        let pdf = document_with_images(vec![stream]);
+        let selected = select_map(&pdf).unwrap();
+
+        assert_eq!((selected.width, selected.height), (320, 240));
+    }
+
+    #[test]
+    fn test_select_map_rejects_logos() {
+        let logo = image_stream(64, 64, "DeviceRGB", red_and_grey(64, 64));
+        let pdf = document_with_images(vec![logo]);
+
+        assert!(select_map(&pdf).is_err());
+    }
+
+    pub(crate) fn drawn_map(segments: usize) -> Vec<u8> {
+        let mut content = String::from("1 0 0 RG 2 w\n10 10 m\n");
+        for i in 0..segments {
+            content += &format!("{} {} l\n", 10 + i % 400, 10 + (i * 7) % 500);
+        }
+        content += "h S\n";
+        content.into_bytes()
+    }
+
+    #[test]
+    fn test_find_vector_map_page() {
+        let text_page =
+            b"BT /F1 12 Tf 72 720 Td (Control of Weapons Act 1990) Tj ET\n0 0 m 500 0 l S".to_vec();
+        let pdf = document_with_pages(vec![
+            (vec![], text_page.clone()),
+            (vec![], drawn_map(50)),
+            (vec![], drawn_map(600)),
+        ]);
+        assert_eq!(find_vector_map_page(&pdf), Some(3));
+
+        let pdf = document_with_pages(vec![(vec![], text_page), (vec![], drawn_map(50))]);
+        assert_eq!(find_vector_map_page(&pdf), None);
+    }
+
+    #[test]
+    fn test_resize_to_width() {
+        let source =
+            DynamicImage::ImageRgb8(RgbImage::from_raw(400, 300, red_and_grey(400, 300)).unwrap());
+        let map = MapImage::encode(&source, MapImageFormat::Png).unwrap();
+
+        let thumbnail = map.resize_to_width(100).unwrap().unwrap();
+        assert_eq!((thumbnail.width, thumbnail.height), (100, 75));
+        assert_eq!(thumbnail.format, MapImageFormat::Png);
+        assert!(thumbnail.data.len() < map.data.len());
+
+        let decoded = image::load_from_memory(&thumbnail.data).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (100, 75));
+
+        assert!(map.resize_to_width(400).unwrap().is_none());
+
+        // A JPEG is scaled from the pixels it was made from, so it's only compressed once
+        let map = MapImage::encode(&source, MapImageFormat::Jpeg).unwrap();
+        let thumbnail = map.resize_to_width(100).unwrap().unwrap();
+        let scaled = source.resize_exact(100, 75, FilterType::Triangle);
+        assert_eq!(
+            thumbnail.data,
+            MapImage::encode(&scaled, MapImageFormat::Jpeg)
+                .unwrap()
+                .data
+        );
+    }
+
+    #[test]
+    fn test_unpack_samples() {
+        let samples = unpack_samples(&[0b1010_0000, 0b0101_0000], 1, 4, 2, true).unwrap();
+        assert_eq!(samples, vec![255, 0, 255, 0, 0, 255, 0, 255]);
+
+        let indices = unpack_samples(&[0x1f], 4, 2, 1, false).unwrap();
+        assert_eq!(indices, vec![1, 15]);
+
+        assert!(unpack_samples(&[0xff], 8, 4, 1, true).is_err());
+    }
+
+    // Two rows, each black on the left half and white on the right, in Group 4 coding
+    fn half_black_g4() -> Vec<u8> {
+        let mut encoder = fax::encoder::Encoder::new(fax::VecWriter::new());
+        for _ in 0..2 {
+            let row = (0..16).map(|x| {
+                if x < 8 {
+                    fax::Color::Black
+                } else {
+                    fax::Color::White
+                }
+            });
+            encoder.encode_line(row, 16).unwrap();
+        }
+        encoder.finish().unwrap().finish()
+    }
+
+    fn ccitt_stream(params: Dictionary) -> Stream {
+        let mut stream = image_stream(16, 2, "DeviceGray", half_black_g4());
+        stream.dict.set("BitsPerComponent", 1);
+        stream.dict.set("Filter", "CCITTFaxDecode");
+        stream.dict.set("DecodeParms", params);
+        stream
+    }
+
+    fn decode_first_image(stream: Stream) -> Result<DynamicImage> {
+        let pdf = document_with_images(vec![stream]);
+        let (_, page_id) = pdf.get_pages().into_iter().next().unwrap();
+        let images = pdf.get_page_images(page_id).unwrap();
+        decode_pdf_image(&pdf, &images[0])
+    }
+
+    #[test]
+    fn test_decode_ccitt() {
+        let decoded = decode_first_image(ccitt_stream(dictionary! { "K" => -1, "Columns" => 16 }))
+            .unwrap()
+            .into_luma8();
+        assert_eq!(decoded.dimensions(), (16, 2));
+        assert_eq!(
+            (decoded.get_pixel(0, 1)[0], decoded.get_pixel(15, 1)[0]),
+            (0, 255)
+        );
+
+        let black_is_1 = dictionary! { "K" => -1, "Columns" => 16, "BlackIs1" => true };
+        let inverted = decode_first_image(ccitt_stream(black_is_1.clone()))
+            .unwrap()
+            .into_luma8();
+        assert_eq!(
+            (inverted.get_pixel(0, 0)[0], inverted.get_pixel(15, 0)[0]),
+            (255, 0)
+        );
+
+        // Scanners often pair BlackIs1 with a Decode array that swaps it back
+        let mut stream = ccitt_stream(black_is_1);
+        stream.dict.set("Decode", vec![1.into(), 0.into()]);
+        let restored = decode_first_image(stream).unwrap().into_luma8();
+        assert_eq!(restored, decoded);
+    }
+
+    #[test]
+    fn test_decode_rejects_unsupported_coding() {
+        let error = |stream| decode_first_image(stream).unwrap_err().to_string();
+
+        assert!(error(ccitt_stream(dictionary! { "K" => 4 })).contains("CCITT K 4"));
+        assert!(error(ccitt_stream(
+            dictionary! { "K" => -1, "EncodedByteAlign" => true }
+        ))
+        .contains("EncodedByteAlign"));
+
+        for filter in ["JBIG2Decode", "JPXDecode"] {
+            let mut stream = image_stream(16, 2, "DeviceGray", vec![0; 32]);
+            stream.dict.set("Filter", filter);
+            assert!(error(stream).contains(filter));
+        }
+    }
+
+    #[test]
+    fn test_decode_run_length() {
+        let decoded = decode_run_length(&[2, b'a', b'b', b'c', 254, b'z', 128]);
+        assert_eq!(decoded, b"abczzz".to_vec());
+    }
+
+    #[test]
+    fn test_preferred_format() {
+        let grey = DynamicImage::ImageLuma8(GrayImage::new(10, 10));
+        let colour = DynamicImage::ImageRgb8(RgbImage::new(10, 10));
+
+        assert_eq!(MapImageFormat::preferred_for(&grey), MapImageFormat::Png);
+        assert_eq!(MapImageFormat::preferred_for(&colour), MapImageFormat::Jpeg);
+    }
+}
@@ -1,6 +1,21 @@
+use crate::utils::projection::GridPosition;
+use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
+use std::env;
+use std::f64::consts::PI;
 use std::fmt;
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+const EARTH_RADIUS_KM: f64 = 6371.0;
+// Fine enough for a buffer's rounded ends to look round at street scale
+const ARC_STEP: f64 = PI / 16.0;
+// Bends sharper than this on the inside get both offsets instead of a spike where they meet
+const MIN_MITRE: f64 = 0.1;
+
+// How far the concave hull digs in, where higher stays closer to the convex hull; this is just
+// low enough to cut a right-angled corner out of a block described by its corners
+pub const DEFAULT_CONCAVITY: f64 = 1.5;
 
 #[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
 pub struct GeoPosition {
@@ -12,11 +27,69 @@ pub trait Sanitise {
     fn sanitise(&mut self);
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct MapPolygon {
     pub data: Vec<GeoPosition>,
 }
 
+// How the geocoded points are joined up into the shape drawn on the map
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", tag = "mode")]
+pub enum ShapeMode {
+    #[default]
+    Convex,
+    Concave {
+        concavity: f64,
+    },
+    // The points as the parser listed them, which it's asked to do clockwise
+    Ordered,
+}
+
+// A notice covering a strip or the surrounds of a place rather than an area
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", tag = "kind")]
+pub enum Corridor {
+    // This far either side of the places, joined in order, like a railway line
+    Line { metres: f64 },
+    // This far around each place separately
+    Radius { metres: f64 },
+}
+
+impl ShapeMode {
+    pub fn from_env() -> Self {
+        env::var("POLYGON_SHAPE")
+            .ok()
+            .filter(|mode| !mode.is_empty())
+            .and_then(|mode| mode.parse().ok())
+            .unwrap_or_default()
+    }
+}
+
+// `convex`, `ordered`, `concave` or `concave:<concavity>`
+impl FromStr for ShapeMode {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let (mode, concavity) = match value.split_once(':') {
+            Some((mode, concavity)) => (mode, Some(concavity.trim())),
+            None => (value, None),
+        };
+
+        match (mode.trim().to_lowercase().as_str(), concavity) {
+            ("convex", None) => Ok(Self::Convex),
+            ("ordered", None) => Ok(Self::Ordered),
+            ("concave", None) => Ok(Self::Concave {
+                concavity: DEFAULT_CONCAVITY,
+            }),
+            ("concave", Some(concavity)) => match concavity.parse::<f64>() {
+                Ok(concavity) if concavity > 0.0 => Ok(Self::Concave { concavity }),
+                _ => Err(anyhow!("Concavity must be a positive number")),
+            },
+            _ => Err(anyhow!("Unknown shape {value}")),
+        }
+    }
+}
+
 impl From<MapPolygon> for String {
     fn from(value: MapPolygon) -> String {
         format!(
@@ -53,6 +126,115 @@ impl MapPolygon {
         }
     }
 
+    // The centre of mass of the area rather than the average of its corners, which leans
+    // towards wherever the corners bunch up; without an area it's the average after all
+    #[allow(clippy::cast_precision_loss)]
+    pub fn centroid(&self) -> GeoPosition {
+        let (origin, ring) = self.grid_ring();
+        let doubled_area = doubled_grid_area(&ring);
+
+        if ring.len() < 3 || doubled_area.abs() < f64::EPSILON {
+            let count = ring.len() as f64;
+            return GridPosition {
+                easting: origin.easting
+                    + ring.iter().map(|point| point.easting).sum::<f64>() / count,
+                northing: origin.northing
+                    + ring.iter().map(|point| point.northing).sum::<f64>() / count,
+            }
+            .to_position();
+        }
+
+        let (easting, northing) = (0..ring.len()).fold((0.0, 0.0), |(easting, northing), edge| {
+            let (a, b) = (ring[edge], ring[(edge + 1) % ring.len()]);
+            let cross = a.easting.mul_add(b.northing, -b.easting * a.northing);
+            (
+                (a.easting + b.easting).mul_add(cross, easting),
+                (a.northing + b.northing).mul_add(cross, northing),
+            )
+        });
+
+        GridPosition {
+            easting: origin.easting + easting / (3.0 * doubled_area),
+            northing: origin.northing + northing / (3.0 * doubled_area),
+        }
+        .to_position()
+    }
+
+    // Square metres on the ground, whichever way the ring runs
+    pub fn area(&self) -> f64 {
+        let (_, ring) = self.grid_ring();
+        if ring.len() < 3 {
+            return 0.0;
+        }
+
+        let scale = self.centroid().to_grid().scale_factor();
+        doubled_grid_area(&ring).abs() / 2.0 / (scale * scale)
+    }
+
+    // Metres on the ground around the ring, closing it if it isn't already
+    pub fn perimeter(&self) -> f64 {
+        let (origin, ring) = self.grid_ring();
+        if ring.len() < 2 {
+            return 0.0;
+        }
+
+        (0..ring.len())
+            .map(|edge| {
+                let (a, b) = (ring[edge], ring[(edge + 1) % ring.len()]);
+                let middle = a.midpoint(b);
+                let scale = GridPosition {
+                    easting: origin.easting + middle.easting,
+                    northing: origin.northing + middle.northing,
+                }
+                .scale_factor();
+                a.distance_to(b) / scale
+            })
+            .sum()
+    }
+
+    // Without the closing point, and measured from the first corner so the shoelace isn't
+    // multiplying millions of metres together
+    fn grid_ring(&self) -> (GridPosition, Vec<GridPosition>) {
+        let mut ring: Vec<GridPosition> = self.data.iter().map(GeoPosition::to_grid).collect();
+        if ring.len() > 1 && ring.first() == ring.last() {
+            ring.pop();
+        }
+
+        let origin = ring.first().copied().unwrap_or_default();
+        let ring = ring
+            .into_iter()
+            .map(|point| GridPosition {
+                easting: point.easting - origin.easting,
+                northing: point.northing - origin.northing,
+            })
+            .collect();
+        (origin, ring)
+    }
+
+    // Ray casting, treating the points as a ring whether or not it's closed
+    pub fn contains(&self, position: &GeoPosition) -> bool {
+        let mut inside = false;
+        let mut previous = match self.data.last() {
+            Some(last) => last,
+            None => return false,
+        };
+
+        for point in &self.data {
+            if (point.latitude > position.latitude) != (previous.latitude > position.latitude) {
+                let crossing = (previous.longitude - point.longitude)
+                    * (position.latitude - point.latitude)
+                    / (previous.latitude - point.latitude)
+                    + point.longitude;
+                if position.longitude < crossing {
+                    inside = !inside;
+                }
+            }
+            previous = point;
+        }
+
+        inside
+    }
+
     #[allow(clippy::float_cmp)]
     pub fn convex_hull(&self) -> Self {
         let points = self.data.clone();
@@ -62,45 +244,51 @@ impl MapPolygon {
             };
         }
 
+        // Angles and turns are measured on the grid, where they're true to the ground
+        let grid: Vec<GridPosition> = points.iter().map(GeoPosition::to_grid).collect();
+
         let mut bottom_point = 0;
         for i in 1..points.len() {
-            if points[i].latitude < points[bottom_point].latitude
-                || (points[i].latitude == points[bottom_point].latitude
-                    && points[i].longitude < points[bottom_point].longitude)
+            if grid[i].northing < grid[bottom_point].northing
+                || (grid[i].northing == grid[bottom_point].northing
+                    && grid[i].easting < grid[bottom_point].easting)
             {
                 bottom_point = i;
             }
         }
 
-        let mut hull_points: Vec<(f64, GeoPosition)> = points
-            .iter()
-            .enumerate()
-            .filter(|&(i, _)| i != bottom_point)
-            .map(|(_, p)| {
-                let dx = p.longitude - points[bottom_point].longitude;
-                let dy = p.latitude - points[bottom_point].latitude;
+        let mut hull_points: Vec<(f64, usize)> = (0..points.len())
+            .filter(|&i| i != bottom_point)
+            .map(|i| {
+                let dx = grid[i].easting - grid[bottom_point].easting;
+                let dy = grid[i].northing - grid[bottom_point].northing;
                 let angle = (dy).atan2(dx);
-                (angle, p.clone())
+                (angle, i)
             })
             .collect();
 
         hull_points.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
 
-        let mut hull = vec![points[bottom_point].clone()];
+        let mut hull_indices = vec![bottom_point];
 
         for (_angle, point) in hull_points {
-            while hull.len() >= 2 {
-                let next_to_top = &hull[hull.len() - 2];
-                let top = &hull[hull.len() - 1];
+            while hull_indices.len() >= 2 {
+                let next_to_top = grid[hull_indices[hull_indices.len() - 2]];
+                let top = grid[hull_indices[hull_indices.len() - 1]];
 
-                if GeoPosition::orientation(next_to_top, top, &point) > 0.0 {
+                if next_to_top.orientation(top, grid[point]) > 0.0 {
                     break;
                 }
-                hull.pop();
+                hull_indices.pop();
             }
-            hull.push(point);
+            hull_indices.push(point);
         }
 
+        let mut hull: Vec<GeoPosition> = hull_indices
+            .into_iter()
+            .map(|i| points[i].clone())
+            .collect();
+
         if !hull.is_empty() && hull[0] != *hull.last().unwrap() {
             hull.push(hull[0].clone());
         }
@@ -108,6 +296,155 @@ impl MapPolygon {
         MapPolygon { data: hull }
     }
 
+    // Digs into the convex hull one edge at a time, like an alpha shape: an edge is replaced by
+    // two through the nearest point that's closer to it than to the edges either side, when the
+    // edge is `concavity` times longer than the shorter of the two and doing so leaves every
+    // point inside without crossing itself
+    pub fn concave_hull(&self, concavity: f64) -> Self {
+        let hull = self.convex_hull();
+        if hull.data.len() < 4 {
+            return hull;
+        }
+
+        // Everything is kept as indices, so the corners come back exactly as they went in
+        let mut points = hull.data[..hull.data.len() - 1].to_vec();
+        let mut ring: Vec<usize> = (0..points.len()).collect();
+        let mut inner = Vec::new();
+        for point in &self.data {
+            if !points.contains(point) {
+                inner.push(points.len());
+                points.push(point.clone());
+            }
+        }
+        let grid: Vec<GridPosition> = points.iter().map(GeoPosition::to_grid).collect();
+
+        let mut edge = 0;
+        while edge < ring.len() {
+            let corner = |offset: usize| grid[ring[(edge + offset) % ring.len()]];
+            let (previous, start, end, next) =
+                (corner(ring.len() - 1), corner(0), corner(1), corner(2));
+            let outline: Vec<GridPosition> = ring.iter().map(|&index| grid[index]).collect();
+            let length = start.distance_to(end);
+
+            let candidate = inner
+                .iter()
+                .enumerate()
+                .filter(|&(_, &index)| {
+                    let point = grid[index];
+                    let gap = point.distance_to_segment(start, end);
+                    length / start.distance_to(point).min(end.distance_to(point)) >= concavity
+                        && gap < point.distance_to_segment(previous, start)
+                        && gap < point.distance_to_segment(end, next)
+                })
+                .filter(|&(position, &index)| {
+                    let point = grid[index];
+                    !inner.iter().enumerate().any(|(other, &inside)| {
+                        other != position && in_triangle([start, point, end], grid[inside])
+                    }) && !crosses_ring(&outline, start, point)
+                        && !crosses_ring(&outline, point, end)
+                })
+                .min_by(|(_, &a), (_, &b)| {
+                    grid[a]
+                        .distance_to_segment(start, end)
+                        .partial_cmp(&grid[b].distance_to_segment(start, end))
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })
+                .map(|(position, _)| position);
+
+            // The new edge from the start is checked again before moving on
+            match candidate {
+                Some(position) => {
+                    let index = inner.remove(position);
+                    ring.insert(edge + 1, index);
+                }
+                None => edge += 1,
+            }
+        }
+
+        let mut data: Vec<GeoPosition> = ring.iter().map(|&index| points[index].clone()).collect();
+        data.push(data[0].clone());
+        MapPolygon { data }
+    }
+
+    // Joins the points in the order given; a ring that crosses itself means the order was
+    // wrong after all, so it falls back to the convex hull
+    pub fn ordered_ring(&self) -> Self {
+        let mut ring = self.data.clone();
+        ring.dedup();
+        if ring.len() > 1 && ring.first() == ring.last() {
+            ring.pop();
+        }
+        if ring.len() < 3 {
+            return MapPolygon { data: ring };
+        }
+
+        let grid: Vec<GridPosition> = ring.iter().map(GeoPosition::to_grid).collect();
+        let crosses_itself = (0..grid.len())
+            .any(|edge| crosses_ring(&grid, grid[edge], grid[(edge + 1) % grid.len()]));
+        if crosses_itself {
+            return self.convex_hull();
+        }
+
+        ring.push(ring[0].clone());
+        MapPolygon { data: ring }
+    }
+
+    pub fn shaped(&self, mode: ShapeMode) -> Self {
+        match mode {
+            ShapeMode::Convex => self.convex_hull(),
+            ShapeMode::Concave { concavity } => self.concave_hull(concavity),
+            ShapeMode::Ordered => self.ordered_ring(),
+        }
+    }
+
+    // Treats the points as a line and widens it by `metres` either side with rounded ends;
+    // a single point becomes a circle
+    pub fn buffered(&self, metres: f64) -> Self {
+        let mut line = self.data.clone();
+        line.dedup();
+        let Some(origin) = line.first() else {
+            return MapPolygon { data: line };
+        };
+
+        // Metres on the ground are a little longer or shorter on the grid
+        let grid_metres = metres * origin.to_grid().scale_factor();
+        let line: Vec<(f64, f64)> = line
+            .iter()
+            .map(|point| {
+                let grid = point.to_grid();
+                (grid.easting, grid.northing)
+            })
+            .collect();
+        let mut outline = buffer_line(&line, grid_metres);
+        outline.push(outline[0]);
+
+        MapPolygon {
+            data: outline
+                .into_iter()
+                .map(|(easting, northing)| GridPosition { easting, northing }.to_position())
+                .collect(),
+        }
+    }
+
+    pub fn corridor(&self, corridor: Corridor) -> Vec<Self> {
+        match corridor {
+            Corridor::Line { metres } => vec![self.buffered(metres)],
+            Corridor::Radius { metres } => self
+                .data
+                .iter()
+                .map(|point| {
+                    MapPolygon {
+                        data: vec![point.clone()],
+                    }
+                    .buffered(metres)
+                })
+                .collect(),
+        }
+        .into_iter()
+        .filter(|shape| !shape.data.is_empty())
+        .collect()
+    }
+
     #[allow(clippy::cast_precision_loss)]
     pub fn remove_outliers_by_proximity(&mut self, threshold: f64, buffer_km: f64) -> &mut Self {
         if self.data.len() < 4 {
@@ -135,6 +472,10 @@ impl MapPolygon {
             .sum::<f64>()
             / nearest_distances.len() as f64;
         let std_dev = variance.sqrt();
+        // Evenly spaced points have no outliers, and a z-score would divide by zero
+        if std_dev <= f64::EPSILON {
+            return self;
+        }
 
         let filtered_data: Vec<GeoPosition> = self
             .data
@@ -227,8 +568,17 @@ impl Sanitise for Vec<GeoPosition> {
 }
 
 impl GeoPosition {
-    fn distance_to(&self, other: &GeoPosition) -> f64 {
-        let r = 6371.0; // Earth's radius in kilometers
+    // Longitude first, the way the test fixtures write their points
+    #[cfg(test)]
+    pub(crate) fn at(longitude: f64, latitude: f64) -> Self {
+        Self {
+            latitude,
+            longitude,
+        }
+    }
+
+    pub(crate) fn distance_to(&self, other: &GeoPosition) -> f64 {
+        let r = EARTH_RADIUS_KM;
 
         let d_lat = (other.latitude - self.latitude).to_radians();
         let d_lng = (other.longitude - self.longitude).to_radians();
@@ -240,11 +590,135 @@ impl GeoPosition {
 
         r * c
     }
+}
 
-    fn orientation(p: &GeoPosition, q: &GeoPosition, r: &GeoPosition) -> f64 {
-        (q.longitude - p.longitude) * (r.latitude - p.latitude)
-            - (q.latitude - p.latitude) * (r.longitude - p.longitude)
+// Down the left of the line and back up the right, so the outline runs clockwise
+fn buffer_line(line: &[(f64, f64)], metres: f64) -> Vec<(f64, f64)> {
+    let normals: Vec<(f64, f64)> = line
+        .windows(2)
+        .map(|pair| {
+            let (dx, dy) = (pair[1].0 - pair[0].0, pair[1].1 - pair[0].1);
+            let length = dx.hypot(dy);
+            (-dy / length, dx / length)
+        })
+        .collect();
+
+    let mut outline = Vec::new();
+    if normals.is_empty() {
+        outline.push((line[0].0 + metres, line[0].1));
+        arc(line[0], (1.0, 0.0), 2.0 * PI, metres, &mut outline);
+        return outline;
     }
+
+    offset_side(line, &normals, metres, &mut outline);
+    let reversed: Vec<(f64, f64)> = line.iter().rev().copied().collect();
+    let reversed_normals: Vec<(f64, f64)> = normals.iter().rev().map(|(x, y)| (-x, -y)).collect();
+    offset_side(&reversed, &reversed_normals, metres, &mut outline);
+    outline
+}
+
+// The left-hand side of the line, rounding the outside of bends and the far end
+fn offset_side(
+    line: &[(f64, f64)],
+    normals: &[(f64, f64)],
+    metres: f64,
+    outline: &mut Vec<(f64, f64)>,
+) {
+    let offset = |point: (f64, f64), normal: (f64, f64)| {
+        (
+            normal.0.mul_add(metres, point.0),
+            normal.1.mul_add(metres, point.1),
+        )
+    };
+    outline.push(offset(line[0], normals[0]));
+
+    for (vertex, pair) in normals.windows(2).enumerate() {
+        let point = line[vertex + 1];
+        let (before, after) = (pair[0], pair[1]);
+        let turn = before.0.mul_add(after.1, -before.1 * after.0);
+        let alignment = before.0.mul_add(after.0, before.1 * after.1);
+
+        if turn < 0.0 {
+            let sweep = (before.1.atan2(before.0) - after.1.atan2(after.0)).rem_euclid(2.0 * PI);
+            outline.push(offset(point, before));
+            arc(point, before, sweep, metres, outline);
+            outline.push(offset(point, after));
+        } else if 1.0 + alignment < MIN_MITRE {
+            outline.push(offset(point, before));
+            outline.push(offset(point, after));
+        } else {
+            // Where the offsets of the two segments meet on the inside of the bend
+            let mitre = metres / (1.0 + alignment);
+            outline.push((
+                (before.0 + after.0).mul_add(mitre, point.0),
+                (before.1 + after.1).mul_add(mitre, point.1),
+            ));
+        }
+    }
+
+    let (end, last) = (line[line.len() - 1], normals[normals.len() - 1]);
+    outline.push(offset(end, last));
+    arc(end, last, PI, metres, outline);
+}
+
+// The points strictly between the ends of a clockwise arc starting at `from`
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss
+)]
+fn arc(
+    centre: (f64, f64),
+    from: (f64, f64),
+    sweep: f64,
+    metres: f64,
+    outline: &mut Vec<(f64, f64)>,
+) {
+    let start = from.1.atan2(from.0);
+    let steps = (sweep / ARC_STEP).ceil() as usize;
+
+    for step in 1..steps {
+        let angle = (step as f64 / steps as f64).mul_add(-sweep, start);
+        outline.push((
+            angle.cos().mul_add(metres, centre.0),
+            angle.sin().mul_add(metres, centre.1),
+        ));
+    }
+}
+
+// Whether the segment properly crosses an edge of the ring; edges it shares an end with don't count
+fn crosses_ring(ring: &[GridPosition], start: GridPosition, end: GridPosition) -> bool {
+    (0..ring.len()).any(|edge| {
+        let (a, b) = (ring[edge], ring[(edge + 1) % ring.len()]);
+        if [a, b].iter().any(|point| *point == start || *point == end) {
+            return false;
+        }
+
+        let sides = |p: GridPosition, q: GridPosition, r: GridPosition, s: GridPosition| {
+            p.orientation(q, r) * p.orientation(q, s) < 0.0
+        };
+        sides(start, end, a, b) && sides(a, b, start, end)
+    })
+}
+
+// The shoelace formula, positive when the ring runs anticlockwise
+fn doubled_grid_area(ring: &[GridPosition]) -> f64 {
+    (0..ring.len())
+        .map(|edge| {
+            let (a, b) = (ring[edge], ring[(edge + 1) % ring.len()]);
+            a.easting.mul_add(b.northing, -b.easting * a.northing)
+        })
+        .sum()
+}
+
+// Strictly inside, so points on an edge don't stop it being dug out
+fn in_triangle([a, b, c]: [GridPosition; 3], point: GridPosition) -> bool {
+    let turns = [
+        a.orientation(b, point),
+        b.orientation(c, point),
+        c.orientation(a, point),
+    ];
+    turns.iter().all(|turn| *turn > 0.0) || turns.iter().all(|turn| *turn < 0.0)
 }
 
 impl Display for GeoPosition {
@@ -252,3 +726,233 @@ impl Display for GeoPosition {
         write!(f, "[{:?}, {:?}]", self.latitude, self.longitude)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An L-shaped block listed clockwise from the south-west, missing its north-east quarter
+    fn reference() -> Vec<GeoPosition> {
+        vec![
+            GeoPosition::at(144.950, -37.820),
+            GeoPosition::at(144.950, -37.810),
+            GeoPosition::at(144.955, -37.810),
+            GeoPosition::at(144.955, -37.815),
+            GeoPosition::at(144.960, -37.815),
+            GeoPosition::at(144.960, -37.820),
+        ]
+    }
+
+    fn same_vertices(polygon: &MapPolygon, expected: &[GeoPosition]) -> bool {
+        polygon.data.first() == polygon.data.last()
+            && polygon.data.len() == expected.len() + 1
+            && expected.iter().all(|point| polygon.data.contains(point))
+    }
+
+    #[test]
+    fn test_shapes_against_reference() {
+        let points = MapPolygon { data: reference() };
+        let notch = GeoPosition::at(144.9565, -37.8135);
+        let inside = GeoPosition::at(144.952, -37.818);
+
+        let convex = points.shaped(ShapeMode::Convex);
+        assert!(convex.contains(&notch));
+        assert!(!convex.data.contains(&GeoPosition::at(144.955, -37.815)));
+
+        let concave = points.shaped(ShapeMode::Concave {
+            concavity: DEFAULT_CONCAVITY,
+        });
+        assert!(same_vertices(&concave, &reference()));
+        assert!(!concave.contains(&notch));
+        assert!(concave.contains(&inside));
+
+        // Too loose to dig at all
+        let loose = points.shaped(ShapeMode::Concave { concavity: 5.0 });
+        assert_eq!(loose.data, convex.data);
+
+        let mut ring = reference();
+        ring.push(ring[0].clone());
+        assert_eq!(points.shaped(ShapeMode::Ordered).data, ring);
+    }
+
+    #[test]
+    fn test_concave_hull_keeps_points_inside() {
+        let mut data = reference();
+        data.extend([
+            GeoPosition::at(144.951, -37.811),
+            GeoPosition::at(144.954, -37.816),
+            GeoPosition::at(144.959, -37.819),
+            GeoPosition::at(144.952, -37.8195),
+        ]);
+        let points = MapPolygon { data };
+
+        // Tight enough to dig past the corners, but never leaving a point out or crossing over
+        let concave = points.concave_hull(1.0);
+        let ring: Vec<GridPosition> = concave.data[..concave.data.len() - 1]
+            .iter()
+            .map(GeoPosition::to_grid)
+            .collect();
+        assert!(!(0..ring.len()).any(|edge| crosses_ring(
+            &ring,
+            ring[edge],
+            ring[(edge + 1) % ring.len()]
+        )));
+        for point in &points.data {
+            assert!(concave.data.contains(point) || concave.contains(point));
+        }
+    }
+
+    #[test]
+    fn test_ordered_ring_falls_back() {
+        // The same block with two corners swapped crosses itself
+        let mut data = reference();
+        data.swap(1, 2);
+        let points = MapPolygon { data };
+
+        assert_eq!(points.ordered_ring().data, points.convex_hull().data);
+    }
+
+    #[test]
+    fn test_buffered_point() {
+        let station = GeoPosition::at(144.9525, -37.8183);
+        let circle = MapPolygon {
+            data: vec![station.clone(), station.clone()],
+        }
+        .buffered(50.0);
+
+        assert_eq!(circle.data.first(), circle.data.last());
+        assert!(circle.data.len() > 16);
+        for point in &circle.data {
+            assert!((point.distance_to(&station) - 0.05).abs() < 0.001);
+        }
+        assert!(circle.contains(&station));
+    }
+
+    #[test]
+    fn test_buffered_line() {
+        // A line east then north, bending left
+        let line = MapPolygon {
+            data: vec![
+                GeoPosition::at(144.950, -37.820),
+                GeoPosition::at(144.960, -37.820),
+                GeoPosition::at(144.960, -37.810),
+            ],
+        };
+        let corridor = line.buffered(100.0);
+        assert_eq!(corridor.data.first(), corridor.data.last());
+
+        // Within the buffer along each leg, around the bend and past the ends
+        assert!(corridor.contains(&GeoPosition::at(144.955, -37.8195)));
+        assert!(corridor.contains(&GeoPosition::at(144.955, -37.8205)));
+        assert!(corridor.contains(&GeoPosition::at(144.9605, -37.815)));
+        assert!(corridor.contains(&GeoPosition::at(144.9607, -37.8206)));
+        assert!(corridor.contains(&GeoPosition::at(144.9495, -37.820)));
+        assert!(corridor.contains(&GeoPosition::at(144.960, -37.8095)));
+
+        // But not beyond it
+        assert!(!corridor.contains(&GeoPosition::at(144.955, -37.8175)));
+        assert!(!corridor.contains(&GeoPosition::at(144.955, -37.822)));
+        assert!(!corridor.contains(&GeoPosition::at(144.9620, -37.815)));
+        assert!(!corridor.contains(&GeoPosition::at(144.948, -37.820)));
+        assert!(!corridor.contains(&GeoPosition::at(144.9585, -37.8115)));
+
+        let radius = line.corridor(Corridor::Radius { metres: 100.0 });
+        assert_eq!(radius.len(), 3);
+        assert!(!radius
+            .iter()
+            .any(|circle| circle.contains(&GeoPosition::at(144.955, -37.820))));
+    }
+
+    // The cell between two parallels and two meridians, from the ellipsoid's radii of curvature
+    fn cell_area(south: f64, size: f64) -> f64 {
+        let latitude = (south + size / 2.0).to_radians();
+        let squared_eccentricity = 0.006_694_380_022_9;
+        let denominator = 1.0 - squared_eccentricity * latitude.sin().powi(2);
+        let meridian = 6_378_137.0 * (1.0 - squared_eccentricity) / denominator.powf(1.5);
+        let prime_vertical = 6_378_137.0 / denominator.sqrt();
+
+        meridian * prime_vertical * latitude.cos() * size.to_radians().powi(2)
+    }
+
+    #[test]
+    fn test_area_and_perimeter() {
+        let cell = MapPolygon {
+            data: vec![
+                GeoPosition::at(144.95, -37.82),
+                GeoPosition::at(144.96, -37.82),
+                GeoPosition::at(144.96, -37.81),
+                GeoPosition::at(144.95, -37.81),
+                GeoPosition::at(144.95, -37.82),
+            ],
+        };
+        let expected = cell_area(-37.82, 0.01);
+        assert!((cell.area() - expected).abs() / expected < 1e-4);
+
+        // About 1112 m north to south and 880 m east to west
+        let perimeter = cell.perimeter();
+        assert!((perimeter - 2.0 * (1_110.0 + 880.0)).abs() < 10.0);
+        let sides: f64 = (0..4)
+            .map(|edge| cell.data[edge].distance_to(&cell.data[edge + 1]) * 1000.0)
+            .sum();
+        assert!((perimeter - sides).abs() / sides < 0.005);
+
+        // Which way round the ring runs, or whether it's closed, doesn't matter
+        let mut reversed = cell.data[..4].to_vec();
+        reversed.reverse();
+        let reversed = MapPolygon { data: reversed };
+        assert!((reversed.area() - cell.area()).abs() < 1e-3);
+        assert!((reversed.perimeter() - perimeter).abs() < 1e-6);
+
+        assert!(MapPolygon { data: vec![] }.area().abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_centroid() {
+        // The L is three equal squares, so its centroid is the average of their centres
+        let block = MapPolygon { data: reference() };
+        let expected = GeoPosition {
+            latitude: (-37.8175 - 37.8125 - 37.8175) / 3.0,
+            longitude: (144.9525 + 144.9525 + 144.9575) / 3.0,
+        };
+        assert!(block.centroid().distance_to(&expected) < 0.001);
+
+        // The average of the corners is pulled towards the notch
+        assert!(block.centre().distance_to(&expected) > 0.05);
+
+        let pair = MapPolygon {
+            data: vec![
+                GeoPosition::at(144.95, -37.82),
+                GeoPosition::at(144.96, -37.82),
+            ],
+        };
+        assert!(
+            pair.centroid()
+                .distance_to(&GeoPosition::at(144.955, -37.82))
+                < 0.001
+        );
+    }
+
+    #[test]
+    fn test_shape_mode() {
+        assert_eq!("convex".parse::<ShapeMode>().unwrap(), ShapeMode::Convex);
+        assert_eq!(
+            " Ordered ".parse::<ShapeMode>().unwrap(),
+            ShapeMode::Ordered
+        );
+        assert_eq!(
+            "concave".parse::<ShapeMode>().unwrap(),
+            ShapeMode::Concave {
+                concavity: DEFAULT_CONCAVITY
+            }
+        );
+        assert_eq!(
+            "concave:2.5".parse::<ShapeMode>().unwrap(),
+            ShapeMode::Concave { concavity: 2.5 }
+        );
+        assert!("concave:-1".parse::<ShapeMode>().is_err());
+        assert!("round".parse::<ShapeMode>().is_err());
+
+        let stored = serde_json::to_string(&ShapeMode::Concave { concavity: 2.5 }).unwrap();
+        assert_eq!(stored, r#"{"mode":"concave","concavity":2.5}"#);
+    }
+}
@@ -1,4 +1,11 @@
+pub mod announcements;
+pub mod auth;
+pub mod boundary;
 pub mod gazette;
 pub mod geojson;
+pub mod geometry;
+pub mod http;
+pub mod mapimage;
 pub mod maptypes;
+pub mod projection;
 pub mod updater;
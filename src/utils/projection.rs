@@ -0,0 +1,213 @@
+use crate::utils::maptypes::GeoPosition;
+
+// MGA zone 55 on GDA2020: transverse Mercator on the GRS80 ellipsoid. Its scale error stays
+// under half a percent across Victoria, which is far less than the geocoders' own
+const SEMI_MAJOR_AXIS: f64 = 6_378_137.0;
+const FLATTENING: f64 = 1.0 / 298.257_222_101;
+const CENTRAL_MERIDIAN: f64 = 147.0;
+const CENTRAL_SCALE_FACTOR: f64 = 0.9996;
+const FALSE_EASTING: f64 = 500_000.0;
+const FALSE_NORTHING: f64 = 10_000_000.0;
+// Close enough to the ellipsoid for the scale factor, which only needs its first term
+const MEAN_RADIUS: f64 = 6_371_000.0;
+
+// Metres east and north on the MGA zone 55 grid
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GridPosition {
+    pub easting: f64,
+    pub northing: f64,
+}
+
+// Krüger's series to fourth order in n, as given in the GDA2020 technical manual, which is
+// good to well under a millimetre
+struct Series {
+    eccentricity: f64,
+    rectifying_radius: f64,
+    alpha: [f64; 4],
+    beta: [f64; 4],
+}
+
+impl Series {
+    fn grs80() -> Self {
+        let n = FLATTENING / (2.0 - FLATTENING);
+        let (n2, n3, n4) = (n.powi(2), n.powi(3), n.powi(4));
+
+        Self {
+            eccentricity: (FLATTENING * (2.0 - FLATTENING)).sqrt(),
+            rectifying_radius: SEMI_MAJOR_AXIS / (1.0 + n) * (1.0 + n2 / 4.0 + n4 / 64.0),
+            alpha: [
+                n / 2.0 - 2.0 * n2 / 3.0 + 5.0 * n3 / 16.0 + 41.0 * n4 / 180.0,
+                13.0 * n2 / 48.0 - 3.0 * n3 / 5.0 + 557.0 * n4 / 1440.0,
+                61.0 * n3 / 240.0 - 103.0 * n4 / 140.0,
+                49561.0 * n4 / 161_280.0,
+            ],
+            beta: [
+                n / 2.0 - 2.0 * n2 / 3.0 + 37.0 * n3 / 96.0 - n4 / 360.0,
+                n2 / 48.0 + n3 / 15.0 - 437.0 * n4 / 1440.0,
+                17.0 * n3 / 480.0 - 37.0 * n4 / 840.0,
+                4397.0 * n4 / 161_280.0,
+            ],
+        }
+    }
+}
+
+// Both directions add up the same trigonometric series, with opposite signs
+#[allow(clippy::cast_precision_loss)]
+fn sum_series(coefficients: &[f64; 4], xi: f64, eta: f64, sign: f64) -> (f64, f64) {
+    coefficients
+        .iter()
+        .enumerate()
+        .fold((xi, eta), |(north, east), (index, coefficient)| {
+            let order = 2.0 * (index + 1) as f64;
+            (
+                (sign * coefficient * (order * xi).sin()).mul_add((order * eta).cosh(), north),
+                (sign * coefficient * (order * xi).cos()).mul_add((order * eta).sinh(), east),
+            )
+        })
+}
+
+impl GeoPosition {
+    pub fn to_grid(&self) -> GridPosition {
+        let series = Series::grs80();
+        let e = series.eccentricity;
+        let latitude = self.latitude.to_radians();
+        let longitude = (self.longitude - CENTRAL_MERIDIAN).to_radians();
+
+        // Conformal latitude, then the spherical transverse Mercator
+        let conformal = (latitude.sin().atanh() - e * (e * latitude.sin()).atanh()).sinh();
+        let xi = conformal.atan2(longitude.cos());
+        let eta = (longitude.sin() / conformal.hypot(longitude.cos())).asinh();
+        let (xi, eta) = sum_series(&series.alpha, xi, eta, 1.0);
+
+        let scale = CENTRAL_SCALE_FACTOR * series.rectifying_radius;
+        GridPosition {
+            easting: scale.mul_add(eta, FALSE_EASTING),
+            northing: scale.mul_add(xi, FALSE_NORTHING),
+        }
+    }
+}
+
+impl GridPosition {
+    pub fn to_position(self) -> GeoPosition {
+        let series = Series::grs80();
+        let e = series.eccentricity;
+        let scale = CENTRAL_SCALE_FACTOR * series.rectifying_radius;
+
+        let xi = (self.northing - FALSE_NORTHING) / scale;
+        let eta = (self.easting - FALSE_EASTING) / scale;
+        let (xi, eta) = sum_series(&series.beta, xi, eta, -1.0);
+
+        let conformal = xi.sin() / eta.sinh().hypot(xi.cos());
+        let longitude = eta.sinh().atan2(xi.cos());
+
+        // Newton's method back from the conformal latitude, which settles in a few steps
+        let mut tangent = conformal;
+        for _ in 0..10 {
+            let sigma = (e * (e * tangent / tangent.hypot(1.0)).atanh()).sinh();
+            let estimate = tangent * sigma.hypot(1.0) - sigma * tangent.hypot(1.0);
+            let step = (conformal - estimate) / estimate.hypot(1.0)
+                * (1.0 - e * e).mul_add(tangent * tangent, 1.0)
+                / ((1.0 - e * e) * tangent.hypot(1.0));
+            tangent += step;
+            if step.abs() < 1e-12 {
+                break;
+            }
+        }
+
+        GeoPosition {
+            latitude: tangent.atan().to_degrees(),
+            longitude: CENTRAL_MERIDIAN + longitude.to_degrees(),
+        }
+    }
+
+    pub fn distance_to(self, other: Self) -> f64 {
+        (other.easting - self.easting).hypot(other.northing - self.northing)
+    }
+
+    // How much the grid stretches distances here, growing away from the central meridian
+    pub fn scale_factor(self) -> f64 {
+        let offset = (self.easting - FALSE_EASTING) / (CENTRAL_SCALE_FACTOR * MEAN_RADIUS);
+        CENTRAL_SCALE_FACTOR * (offset * offset).mul_add(0.5, 1.0)
+    }
+
+    pub fn midpoint(self, other: Self) -> Self {
+        Self {
+            easting: (self.easting + other.easting) / 2.0,
+            northing: (self.northing + other.northing) / 2.0,
+        }
+    }
+
+    pub fn distance_to_segment(self, start: Self, end: Self) -> f64 {
+        let (east, north) = (end.easting - start.easting, end.northing - start.northing);
+        let length = east.mul_add(east, north * north);
+        let along = if length > 0.0 {
+            (self.easting - start.easting).mul_add(east, (self.northing - start.northing) * north)
+                / length
+        } else {
+            0.0
+        }
+        .clamp(0.0, 1.0);
+
+        self.distance_to(Self {
+            easting: along.mul_add(east, start.easting),
+            northing: along.mul_add(north, start.northing),
+        })
+    }
+
+    // Positive when `third` is to the left of the line from `self` through `second`
+    pub fn orientation(self, second: Self, third: Self) -> f64 {
+        (second.easting - self.easting).mul_add(
+            third.northing - self.northing,
+            -(second.northing - self.northing) * (third.easting - self.easting),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Flinders Peak, the worked example in the GDA2020 technical manual
+    fn flinders_peak() -> GeoPosition {
+        GeoPosition {
+            latitude: -(37.0 + 57.0 / 60.0 + 3.7203 / 3600.0),
+            longitude: 144.0 + 25.0 / 60.0 + 29.5244 / 3600.0,
+        }
+    }
+
+    #[test]
+    fn test_to_grid() {
+        let grid = flinders_peak().to_grid();
+        assert!((grid.easting - 273_741.297).abs() < 0.001);
+        assert!((grid.northing - 5_796_489.777).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for position in [
+            flinders_peak(),
+            GeoPosition {
+                latitude: -37.8176,
+                longitude: 144.9668,
+            },
+            GeoPosition {
+                latitude: -36.1,
+                longitude: 141.1,
+            },
+        ] {
+            let back = position.to_grid().to_position();
+            assert!((back.latitude - position.latitude).abs() < 1e-9);
+            assert!((back.longitude - position.longitude).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_scale_factor() {
+        let central = GridPosition {
+            easting: FALSE_EASTING,
+            northing: 5_800_000.0,
+        };
+        assert!((central.scale_factor() - CENTRAL_SCALE_FACTOR).abs() < 1e-12);
+        assert!(flinders_peak().to_grid().scale_factor() > CENTRAL_SCALE_FACTOR);
+    }
+}
@@ -1,50 +1,165 @@
 use crate::db::core::DatabaseProvider;
 use crate::db::DatabaseConnection;
 use crate::geocoder::core::GeocoderProvider;
-use crate::image_service::ImageService;
 use crate::location_parser::core::LocationParserService;
-use crate::utils::gazette::{make_hash, Gazette, GazetteHandler};
+use crate::ocr::core::OcrService;
+use crate::ocr::{is_garbled, OcrRequest};
+use crate::renderer::core::PageRenderer;
+use crate::storage::StorageService;
+use crate::utils::announcements::AnnouncementScraper;
+use crate::utils::boundary::trace_boundary;
+use crate::utils::gazette::{make_hash, polygon_from, Gazette, GazetteHandler, PdfBytes, Review};
+use crate::utils::geometry::{display_geometry, CleanupSettings};
+use crate::utils::http::{client, flush_api_calls};
+use crate::utils::mapimage::{ImageVariant, ORIGINAL_SIZE};
+use crate::utils::maptypes::ShapeMode;
 use anyhow::Result;
 use futures::stream::StreamExt;
 use select::document::Document;
 use select::predicate::Name;
+use std::sync::Arc;
 
 const FIRST_PAGE: u32 = 1;
 const TARGET_TEXT: &str = "Control of Weapons Act 1990";
 
 #[derive(Clone, Debug)]
-pub struct ServiceConfig<T, U, V, W>
+pub struct ServiceConfig<T, U, V, W, X, Y>
 where
     T: DatabaseProvider + Clone + Send + Sync,
-    U: ImageService + Clone + Send + Sync,
+    U: StorageService + Clone + Send + Sync,
     V: LocationParserService + Clone + Send + Sync,
     W: GeocoderProvider + Clone + Send + Sync,
+    X: OcrService + Clone + Send + Sync,
+    Y: PageRenderer + Clone + Send + Sync,
 {
     pub database_provider: T,
-    pub image_service: U,
+    pub storage_service: U,
     pub location_parser: V,
     pub geocoder: W,
+    pub ocr_service: X,
+    pub page_renderer: Y,
+}
+
+impl<T, U, V, W, X, Y> ServiceConfig<T, U, V, W, X, Y>
+where
+    T: DatabaseProvider + Clone + Send + Sync,
+    U: StorageService + Clone + Send + Sync,
+    V: LocationParserService + Clone + Send + Sync,
+    W: GeocoderProvider + Clone + Send + Sync,
+    X: OcrService + Clone + Send + Sync,
+    Y: PageRenderer + Clone + Send + Sync,
+{
+    // Works out everything shown for a flagged notice and saves it; any step that fails is left
+    // out. Text and dates already on the notice, as given with a manual submission, are kept
+    pub async fn process(&self, gazette: Gazette) -> Result<Gazette> {
+        let submitted = !gazette.source.is_gazetted() && gazette.pdf.is_some();
+        let mut gazette_handler = GazetteHandler {
+            gazette,
+            database_provider: self.database_provider.clone(),
+            storage_service: self.storage_service.clone(),
+            location_parser: self.location_parser.clone(),
+            geocoder: self.geocoder.clone(),
+            ocr_service: self.ocr_service.clone(),
+            page_renderer: self.page_renderer.clone(),
+        };
+
+        // Left to each step to try again if it fails here
+        let _ = gazette_handler.gazette.fetch_pdf().await;
+
+        if let Ok(map) = gazette_handler.get_map().await {
+            if let Ok(img) = gazette_handler.try_upload_image(&map).await {
+                let mut variants = gazette_handler
+                    .try_upload_variants(&map)
+                    .await
+                    .unwrap_or_default();
+                variants.push(ImageVariant {
+                    name: ORIGINAL_SIZE.to_string(),
+                    uri: img.url.clone(),
+                    width: map.width,
+                    height: map.height,
+                });
+
+                gazette_handler.gazette.img_uri = Some(img.url);
+                gazette_handler.gazette.img_width = Some(map.width);
+                gazette_handler.gazette.img_height = Some(map.height);
+                gazette_handler.gazette.img_format = Some(map.format);
+                gazette_handler.gazette.img_variants = variants;
+            }
+        }
+
+        if let Ok(archive) = gazette_handler.try_archive_pdf().await {
+            gazette_handler.gazette.archive = Some(archive);
+        }
+
+        if gazette_handler.gazette.ocr_text.is_none() {
+            if let Ok(ocr_text) = gazette_handler.get_ocr_text().await {
+                gazette_handler.gazette.ocr_text = ocr_text;
+            }
+        }
+
+        // A PDF given with a notice added by hand can't be downloaded again from its address, so
+        // its text is kept to be read later
+        if gazette_handler.gazette.ocr_text.is_none() && submitted {
+            if let Ok(text) = gazette_handler.gazette.get_doc_text().await {
+                gazette_handler.gazette.ocr_text = Some(text);
+            }
+        }
+
+        if let Ok((points, area)) = gazette_handler.get_geocoded_points().await {
+            gazette_handler.gazette.area = Some(area);
+            gazette_handler.gazette.polygon = Some(polygon_from(&points));
+            let text = gazette_handler
+                .gazette
+                .get_doc_text()
+                .await
+                .unwrap_or_default();
+            gazette_handler.gazette.boundary = trace_boundary(&points, &text).await;
+            gazette_handler.gazette.shape = ShapeMode::from_env();
+            gazette_handler.gazette.geocoded = points;
+        }
+
+        if let Ok(corridor) = gazette_handler.get_corridor().await {
+            gazette_handler.gazette.corridor = corridor;
+        }
+
+        gazette_handler.gazette.display =
+            display_geometry(&gazette_handler.gazette, &CleanupSettings::from_env());
+
+        if gazette_handler.gazette.start.is_none() || gazette_handler.gazette.end.is_none() {
+            if let Ok(date) = gazette_handler.get_date().await {
+                gazette_handler.gazette.start = Some(date.0);
+                gazette_handler.gazette.end = Some(date.1);
+            }
+        }
+
+        gazette_handler.save().await?;
+        Ok(gazette_handler.gazette)
+    }
 }
 
 #[derive(Clone, Debug)]
-pub struct Updater<T, U, V, W>
+pub struct Updater<T, U, V, W, X, Y>
 where
     T: DatabaseProvider + Clone + Send + Sync,
-    U: ImageService + Copy + Send + Sync,
+    U: StorageService + Clone + Send + Sync,
     V: LocationParserService + Clone + Send + Sync,
     W: GeocoderProvider + Clone + Send + Sync,
+    X: OcrService + Clone + Send + Sync,
+    Y: PageRenderer + Clone + Send + Sync,
 {
     pub uri: String,
     pub base_uri: String,
-    pub config: ServiceConfig<T, U, V, W>,
+    pub config: ServiceConfig<T, U, V, W, X, Y>,
 }
 
-impl<T, U, V, W> Updater<T, U, V, W>
+impl<T, U, V, W, X, Y> Updater<T, U, V, W, X, Y>
 where
     T: DatabaseProvider + Clone + Send + Sync,
-    U: ImageService + Copy + Send + Sync,
+    U: StorageService + Clone + Send + Sync,
     V: LocationParserService + Clone + Send + Sync,
     W: GeocoderProvider + Clone + Send + Sync,
+    X: OcrService + Clone + Send + Sync,
+    Y: PageRenderer + Clone + Send + Sync,
 {
     pub async fn update(&self) -> Result<Vec<String>> {
         let results = self.parse_webpage().await?;
@@ -59,7 +174,7 @@ where
 
                 match db.has_entry(&hash).await {
                     Ok(false) => match self.filter_result(&result).await {
-                        Ok(is_flagged) => Some((result, is_flagged)),
+                        Ok(flagged_pdf) => Some((result, flagged_pdf)),
                         Err(_) => None,
                     },
                     _ => None,
@@ -72,36 +187,19 @@ where
         let (flagged, discarded): (Vec<_>, Vec<_>) = filtered_results
             .into_iter()
             .flatten()
-            .partition(|(_result, is_flagged)| *is_flagged);
+            .partition(|(_result, flagged_pdf)| flagged_pdf.is_some());
 
-        let flagged_futures = flagged.into_iter().map(|((title, uri), _)| async move {
-            let mut gazette_handler = GazetteHandler {
-                gazette: Gazette {
-                    uri: uri.clone(),
-                    title: Some(title),
-                    flagged: true,
-                    ..Default::default()
-                },
-                database_provider: self.config.database_provider.clone(),
-                image_service: self.config.image_service,
-                location_parser: self.config.location_parser.clone(),
-                geocoder: self.config.geocoder.clone(),
+        let flagged_futures = flagged.into_iter().map(|((title, uri), pdf)| async move {
+            let gazette = Gazette {
+                uri: uri.clone(),
+                title: Some(title),
+                flagged: true,
+                review: Review::for_new_notice(),
+                pdf,
+                ..Default::default()
             };
 
-            if let Ok(img) = gazette_handler.try_upload_image().await {
-                gazette_handler.gazette.img_uri = img;
-            }
-
-            if let Ok(polygon) = gazette_handler.get_polygon().await {
-                gazette_handler.gazette.polygon = polygon;
-            }
-
-            if let Ok(date) = gazette_handler.get_date().await {
-                gazette_handler.gazette.start = Some(date.0);
-                gazette_handler.gazette.end = Some(date.1);
-            }
-
-            let _ = gazette_handler.save().await;
+            let _ = self.config.process(gazette).await;
             uri
         });
 
@@ -113,9 +211,11 @@ where
                     ..Default::default()
                 },
                 database_provider: self.config.database_provider.clone(),
-                image_service: self.config.image_service,
+                storage_service: self.config.storage_service.clone(),
                 location_parser: self.config.location_parser.clone(),
                 geocoder: self.config.geocoder.clone(),
+                ocr_service: self.config.ocr_service.clone(),
+                page_renderer: self.config.page_renderer.clone(),
             };
 
             let _ = gazette_handler.save().await;
@@ -126,12 +226,28 @@ where
             futures::future::join_all(discarded_futures)
         );
 
+        // Police announcements are checked against the notices, so they're collected in the same run
+        let scraper = AnnouncementScraper::from_env(self.config.database_provider.clone());
+        if let Err(e) = scraper.update().await {
+            eprintln!("{e}");
+        }
+
+        // Totals of paid API calls are kept so spend can be tracked; an update makes all of them
+        let db = DatabaseConnection {
+            provider: self.config.database_provider.clone(),
+        };
+        if let Err(e) = flush_api_calls(&db).await {
+            eprintln!("{e}");
+        }
+
         println!("PDF Update Complete");
         Ok(flagged_uris)
     }
 
     async fn parse_webpage(&self) -> Result<Vec<(String, String)>> {
-        let response = reqwest::get(&self.uri)
+        let response = client()
+            .get(&self.uri)
+            .send()
             .await?
             .error_for_status()?
             .text()
@@ -144,21 +260,40 @@ where
                     .attr("href")
                     .map(|href| (element.inner_html(), format!("{}{}", self.base_uri, href)))
             })
-            .filter(|(_, url)| std::path::Path::new(url)
-                .extension()
-                .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf")))
+            .filter(|(_, url)| {
+                std::path::Path::new(url)
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"))
+            })
             .collect();
 
         Ok(pdf_list)
     }
 
-    async fn filter_result(&self, chunk: &(String, String)) -> Result<bool> {
+    // Gives back the PDF of a notice under the act, so processing it doesn't download it again
+    async fn filter_result(&self, chunk: &(String, String)) -> Result<Option<PdfBytes>> {
         let (_, uri) = chunk;
 
-        let pdf_bytes = reqwest::get(uri).await?.bytes().await?;
+        let pdf_bytes = client().get(uri).send().await?.bytes().await?;
         let pdf = lopdf::Document::load_mem(&pdf_bytes)?;
-        let page_text = pdf.extract_text(&[FIRST_PAGE])?;
+        let page_text = match pdf.extract_text(&[FIRST_PAGE]) {
+            Ok(page_text) if !is_garbled(&page_text) => page_text,
+            _ => {
+                let futures = Gazette::extract_page_scans(&pdf, &[FIRST_PAGE])
+                    .into_iter()
+                    .map(|image| async move {
+                        let request = OcrRequest {
+                            image,
+                            service: self.config.ocr_service.clone(),
+                        };
+                        request.recognise().await.unwrap_or_default()
+                    });
+                futures::future::join_all(futures).await.join("\n")
+            }
+        };
 
-        Ok(page_text.contains(TARGET_TEXT))
+        Ok(page_text
+            .contains(TARGET_TEXT)
+            .then(|| PdfBytes(Arc::new(pdf_bytes.to_vec()))))
     }
 }
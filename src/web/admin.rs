@@ -0,0 +1,319 @@
+use crate::db::core::DatabaseProvider;
+use crate::db::redis::RedisProvider;
+use crate::db::DatabaseConnection;
+use crate::geocoder::core::GeocoderProvider;
+use crate::location_parser::core::LocationParserService;
+use crate::ocr::core::OcrService;
+use crate::renderer::core::PageRenderer;
+use crate::storage::StorageService;
+use crate::utils::auth::{Role, User};
+use crate::utils::gazette::{make_hash, Gazette, Review};
+use crate::utils::geojson::GeoJsonGeometry;
+use crate::utils::geometry::{
+    apply_edit, display_geometry, CleanupSettings, DisplayShape, GeometryEdit,
+};
+use crate::utils::maptypes::{GeoPosition, MapPolygon};
+use crate::utils::updater::{ServiceConfig, Updater};
+use crate::web::templates::base::base_template;
+use crate::web::templates::styles::get_styles;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+    Extension, Json,
+};
+use chrono::DateTime;
+use maud::{html, Markup, PreEscaped};
+use serde::Deserialize;
+use std::cmp::Reverse;
+
+#[derive(Deserialize)]
+pub struct GeometryUpdate {
+    // No ring puts the computed shape back
+    ring: Option<Vec<GeoPosition>>,
+    note: Option<String>,
+}
+
+pub(crate) async fn find_gazette(hash: &str) -> Result<Gazette, StatusCode> {
+    let db = DatabaseConnection {
+        provider: RedisProvider,
+    };
+    match db.fetch_entry(hash).await {
+        Ok(Some(gazette)) => Ok(gazette),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::SERVICE_UNAVAILABLE),
+    }
+}
+
+pub(crate) async fn save_gazette(gazette: &Gazette) -> Result<(), StatusCode> {
+    let db = DatabaseConnection {
+        provider: RedisProvider,
+    };
+    db.save_gazette(gazette)
+        .await
+        .map(|_| ())
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)
+}
+
+pub(crate) fn signed_in_as(user: &User) -> Markup {
+    html! {
+        form.signed-in method="post" action="/logout" {
+            span.published { "Signed in as " (user.name) " (" (user.role) ")" }
+            button type="submit" { "Sign out" }
+        }
+    }
+}
+
+pub async fn admin_index(Extension(user): Extension<User>) -> Response {
+    let db = DatabaseConnection {
+        provider: RedisProvider,
+    };
+    let Ok(mut gazettes) = db.fetch_entries().await else {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    };
+    gazettes.sort_by_key(|gz| Reverse(gz.start));
+    let pending = gazettes
+        .iter()
+        .filter(|gz| gz.review == Review::Pending)
+        .count();
+
+    base_template(&html! {
+        div.center {
+            (signed_in_as(&user))
+            span.heading { "Notices" }
+            a href="/admin/review" { (pending) " waiting for review" }
+            @if user.role >= Role::Editor {
+                " · "
+                a href="/admin/submit" { "Add a notice that wasn't gazetted" }
+            }
+            @if user.role >= Role::Admin {
+                form.editor method="post" action="/admin/update" {
+                    button type="submit" { "Check the gazette for new notices" }
+                }
+            }
+            ul.admin {
+                @for gz in &gazettes {
+                    li {
+                        a href=(format!("/admin/gazettes/{}", make_hash(&gz.uri))) {
+                            span.title { (gz.title.as_deref().unwrap_or(&gz.uri)) }
+                            @if gz.geometry_override.is_some() {
+                                span.published { "Shape corrected by hand" }
+                            }
+                            @if !gz.source.is_gazetted() {
+                                span.unofficial { "Not published in the Government Gazette" }
+                            }
+                            @match &gz.review {
+                                Review::Pending => span.published { "Waiting for review" },
+                                Review::Rejected { by, .. } => span.published { "Turned down by " (by) },
+                                _ => {}
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        (get_styles())
+    })
+    .into_response()
+}
+
+pub async fn geometry_editor(
+    Extension(user): Extension<User>,
+    Path(hash): Path<String>,
+) -> Response {
+    let gazette = match find_gazette(&hash).await {
+        Ok(gazette) => gazette,
+        Err(status) => return status.into_response(),
+    };
+    let can_edit = user.role >= Role::Editor;
+
+    // The computed shape is drawn underneath for reference, and is where editing starts
+    let computed = display_geometry(
+        &Gazette {
+            geometry_override: None,
+            ..gazette.clone()
+        },
+        &gazette
+            .display
+            .as_ref()
+            .map(|display| display.cleanup.clone())
+            .unwrap_or_default(),
+    );
+    let ring = gazette.geometry_override.clone().or_else(|| {
+        computed.as_ref().and_then(|display| match &display.shape {
+            DisplayShape::Polygon { ring } => Some(ring.clone()),
+            _ => None,
+        })
+    });
+    let reference = computed.map_or_else(
+        || "null".to_string(),
+        |display| {
+            serde_json::to_string(&GeoJsonGeometry::from(&display.shape))
+                .unwrap_or_else(|_| "null".to_string())
+        },
+    );
+    let editing = ring.as_ref().map_or_else(Vec::new, Vec::<[f64; 2]>::from);
+
+    base_template(&html! {
+        div.center {
+            (signed_in_as(&user))
+            span.heading { "Correct the shape" }
+            span.subheading { (gazette.title.as_deref().unwrap_or(&gazette.uri)) }
+            @if can_edit {
+                span.update {
+                    "Drag a corner to move it, click the map to add one, and right-click a corner to remove it. The dashed outline is the shape worked out from the notice."
+                }
+            }
+            div #map {}
+            @if can_edit {
+                div.editor {
+                    input #note type="text" placeholder="What was wrong with it?";
+                    button #save { "Save shape" }
+                    @if gazette.geometry_override.is_some() {
+                        button #clear { "Go back to the computed shape" }
+                    }
+                }
+                form.editor method="post" action=(format!("/admin/gazettes/{hash}/reprocess")) {
+                    button type="submit" { "Redraw with the current cleanup settings" }
+                }
+            }
+            @if user.role >= Role::Admin {
+                form.editor method="post" action=(format!("/admin/gazettes/{hash}/delete")) {
+                    button type="submit" { "Take this notice off the map" }
+                }
+            }
+            span.heading { "History" }
+            ul.history {
+                @for edit in gazette.geometry_edits.iter().rev() {
+                    li {
+                        span {
+                            span.title {
+                                @if edit.ring.is_some() { "Shape drawn" } @else { "Override cleared" }
+                                " by " (edit.editor)
+                            }
+                            span.published {
+                                @if let Some(at) = DateTime::from_timestamp(edit.edited_at, 0) {
+                                    (at.format("%e %B %Y %H:%M UTC"))
+                                }
+                            }
+                            @if let Some(note) = &edit.note {
+                                span.uri { (note) }
+                            }
+                        }
+                    }
+                }
+                @if gazette.geometry_edits.is_empty() {
+                    li { "No edits yet" }
+                }
+            }
+        }
+        (get_styles())
+        script {
+            (PreEscaped(include_str!("js/map-init.js")))
+            (PreEscaped(format!(
+                "let referenceShape = {reference};
+                let editingRing = {};
+                let canEdit = {can_edit};
+                let saveUri = '/admin/gazettes/{hash}/geometry';",
+                serde_json::to_string(&editing).unwrap_or_else(|_| "[]".to_string())
+            )))
+            (PreEscaped(include_str!("js/geometry-editor.js")))
+        }
+    })
+    .into_response()
+}
+
+pub async fn save_geometry(
+    Extension(user): Extension<User>,
+    Path(hash): Path<String>,
+    Json(update): Json<GeometryUpdate>,
+) -> Response {
+    let mut gazette = match find_gazette(&hash).await {
+        Ok(gazette) => gazette,
+        Err(status) => return status.into_response(),
+    };
+
+    let ring = update.ring.map(|data| MapPolygon { data });
+    if ring.as_ref().is_some_and(|ring| !valid_ring(ring)) {
+        return (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "A shape needs at least three corners",
+        )
+            .into_response();
+    }
+
+    apply_edit(
+        &mut gazette,
+        GeometryEdit::new(ring, &user.name, update.note),
+    );
+    match save_gazette(&gazette).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(status) => status.into_response(),
+    }
+}
+
+// Redraws one notice with the current GEOMETRY_* settings, reading its operation area again if it
+// was saved without one; a hand-drawn shape still wins
+pub async fn reprocess_gazette(Path(hash): Path<String>) -> Response {
+    let mut gazette = match find_gazette(&hash).await {
+        Ok(gazette) => gazette,
+        Err(status) => return status.into_response(),
+    };
+
+    gazette.fill_area().await;
+    gazette.display = display_geometry(&gazette, &CleanupSettings::from_env());
+    match save_gazette(&gazette).await {
+        Ok(()) => Redirect::to(&format!("/admin/gazettes/{hash}")).into_response(),
+        Err(status) => status.into_response(),
+    }
+}
+
+// Kept as a discarded notice, so the next update doesn't bring it back
+pub async fn delete_gazette(Path(hash): Path<String>) -> Response {
+    let gazette = match find_gazette(&hash).await {
+        Ok(gazette) => gazette,
+        Err(status) => return status.into_response(),
+    };
+    let discarded = Gazette {
+        flagged: false,
+        ..gazette
+    };
+    match save_gazette(&discarded).await {
+        Ok(()) => Redirect::to("/admin").into_response(),
+        Err(status) => status.into_response(),
+    }
+}
+
+// Runs in the background; the notices appear in the list when it's done
+pub async fn trigger_update<T, U, V, W, X, Y>(
+    State(state): State<ServiceConfig<T, U, V, W, X, Y>>,
+) -> Response
+where
+    T: DatabaseProvider + Clone + Send + Sync + 'static,
+    U: StorageService + Clone + Send + Sync + 'static,
+    V: LocationParserService + Clone + Copy + Send + Sync + 'static,
+    W: GeocoderProvider + Clone + Send + Sync + 'static,
+    X: OcrService + Clone + Copy + Send + Sync + 'static,
+    Y: PageRenderer + Clone + Copy + Send + Sync + 'static,
+{
+    let updater = Updater {
+        uri: "http://www.gazette.vic.gov.au/gazette_bin/gazette_archives.cfm".to_string(),
+        base_uri: "http://www.gazette.vic.gov.au".to_string(),
+        config: state,
+    };
+    tokio::spawn(async move {
+        let _ = updater.update().await;
+    });
+
+    Redirect::to("/admin").into_response()
+}
+
+fn valid_ring(ring: &MapPolygon) -> bool {
+    ring.data.len() >= 3
+        && ring.data.iter().all(|point| {
+            point.latitude.is_finite()
+                && point.longitude.is_finite()
+                && point.latitude.abs() <= 90.0
+                && point.longitude.abs() <= 180.0
+        })
+}
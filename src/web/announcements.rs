@@ -0,0 +1,71 @@
+use crate::db::redis::RedisProvider;
+use crate::db::DatabaseConnection;
+use crate::utils::announcements::{unmatched, Announcement};
+use crate::web::templates::base::base_template;
+use crate::web::templates::components::{footer_section, header_section};
+use crate::web::templates::styles::get_styles;
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use maud::{html, Markup};
+
+fn announcement_item(announcement: &Announcement) -> Markup {
+    html! {
+        li {
+            div {
+                a href=(announcement.uri) target="_blank" {
+                    span.title { (announcement.title) }
+                    @match (&announcement.start, &announcement.end) {
+                        (Some(start), Some(end)) if start == end => {
+                            span.time { "Announced for " (start.format("%A %e %B %Y")) }
+                        }
+                        (Some(start), Some(end)) => {
+                            span.time {
+                                "Announced from " (start.format("%A %e %B %Y")) " to " (end.format("%A %e %B %Y"))
+                            }
+                        }
+                        _ => {
+                            span.time { "No dates found in the announcement" }
+                        }
+                    }
+                    span.uri { (announcement.uri) }
+                }
+            }
+        }
+    }
+}
+
+// Designated areas Victoria Police announced on their website that we can't find a gazette for
+pub async fn unmatched_announcements() -> Response {
+    let db = DatabaseConnection {
+        provider: RedisProvider,
+    };
+    let (Ok(announcements), Ok(gazettes)) =
+        (db.fetch_announcements().await, db.fetch_entries().await)
+    else {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    };
+    let unmatched = unmatched(&announcements, &gazettes);
+
+    base_template(&html! {
+        div.center {
+            (header_section())
+            span.heading { "Announced without a gazette" }
+            span.subheading {
+                "Designated areas announced on the Victoria Police website with no notice in the Government Gazette for the same place and days"
+            }
+            span.unofficial {
+                (unmatched.len()) " of " (announcements.len()) " announcements couldn't be matched. Matching is done by the place and dates in each announcement, so check the gazette before relying on one"
+            }
+            ul {
+                @for announcement in &unmatched {
+                    (announcement_item(announcement))
+                }
+            }
+            (footer_section())
+        }
+        (get_styles())
+    })
+    .into_response()
+}
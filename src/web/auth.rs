@@ -0,0 +1,246 @@
+use crate::db::core::DatabaseProvider;
+use crate::db::redis::RedisProvider;
+use crate::db::DatabaseConnection;
+use crate::utils::auth::{
+    api_tokens, check_password, login_allowed, new_session_id, record_failed_login,
+    session_ttl_seconds, token_user, Role, User,
+};
+use crate::web::templates::base::base_template;
+use crate::web::templates::styles::get_styles;
+use axum::{
+    extract::{ConnectInfo, Query, Request},
+    http::{header, HeaderMap, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
+    Form,
+};
+use maud::{html, Markup};
+use serde::Deserialize;
+use std::net::SocketAddr;
+
+const SESSION_COOKIE: &str = "session";
+
+#[derive(Deserialize)]
+pub struct LoginQuery {
+    next: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct LoginForm {
+    name: String,
+    password: String,
+    next: Option<String>,
+}
+
+fn cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .find_map(|pair| {
+            let (key, value) = pair.trim().split_once('=')?;
+            (key == name).then_some(value)
+        })
+}
+
+// Only paths on this site, so signing in can't be used to send people elsewhere
+fn local_path(next: Option<&str>) -> &str {
+    next.filter(|path| path.starts_with('/') && !path.starts_with("//"))
+        .unwrap_or("/admin")
+}
+
+// Scripts send an API token, and people in a browser have a session cookie
+async fn current_user(headers: &HeaderMap) -> Option<User> {
+    if let Some(token) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+    {
+        return token_user(&api_tokens(), token.trim());
+    }
+
+    let id = cookie(headers, SESSION_COOKIE)?;
+    let db = DatabaseConnection {
+        provider: RedisProvider,
+    };
+    session_user(&db, id).await
+}
+
+// The account is looked up again each time, so removing it or changing its role applies straight
+// away rather than once the session expires
+async fn session_user<T: DatabaseProvider>(db: &DatabaseConnection<T>, id: &str) -> Option<User> {
+    let user = db.fetch_session(id).await.ok().flatten()?;
+    match db.fetch_account(&user.name).await {
+        Ok(Some(account)) => Some(account.user()),
+        Ok(None) => {
+            let _ = db.delete_session(id).await;
+            None
+        }
+        Err(_) => None,
+    }
+}
+
+// Wrapped around each group of admin routes with the least role that group needs; the user is
+// passed on to the handlers as an extension
+pub async fn require(role: Role, mut request: Request, next: Next) -> Response {
+    let Some(user) = current_user(request.headers()).await else {
+        if request.method() == Method::GET {
+            let path = request.uri().path().to_string();
+            return Redirect::to(&format!("/login?next={path}")).into_response();
+        }
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    if user.role < role {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    request.extensions_mut().insert(user);
+    next.run(request).await
+}
+
+fn login_template(next: &str, error: Option<&str>) -> Markup {
+    base_template(&html! {
+        div.center {
+            span.heading { "Sign in" }
+            @if let Some(error) = error {
+                span.update { (error) }
+            }
+            form.login method="post" action="/login" {
+                input type="hidden" name="next" value=(next);
+                input type="text" name="name" placeholder="Name" autocomplete="username" required;
+                input type="password" name="password" placeholder="Password" autocomplete="current-password" required;
+                button type="submit" { "Sign in" }
+            }
+        }
+        (get_styles())
+    })
+}
+
+pub async fn login_page(Query(query): Query<LoginQuery>) -> Markup {
+    login_template(local_path(query.next.as_deref()), None)
+}
+
+pub async fn login(
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    Form(form): Form<LoginForm>,
+) -> Response {
+    let next = local_path(form.next.as_deref());
+    let name = form.name.trim();
+    let address = address.ip();
+    if !login_allowed(name, address) {
+        let error = "Too many attempts to sign in, try again later";
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            login_template(next, Some(error)),
+        )
+            .into_response();
+    }
+
+    let db = DatabaseConnection {
+        provider: RedisProvider,
+    };
+    let Ok(account) = db.fetch_account(name).await else {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    };
+    let Some(account) = check_password(account, form.password).await else {
+        record_failed_login(name, address);
+        let error = "That name and password don't match an account";
+        return (StatusCode::UNAUTHORIZED, login_template(next, Some(error))).into_response();
+    };
+
+    let id = new_session_id();
+    let ttl_seconds = session_ttl_seconds();
+    if db
+        .store_session(&id, &account.user(), ttl_seconds)
+        .await
+        .is_err()
+    {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    }
+
+    (
+        [(
+            header::SET_COOKIE,
+            format!(
+                "{SESSION_COOKIE}={id}; Path=/; Max-Age={ttl_seconds}; HttpOnly; Secure; SameSite=Strict"
+            ),
+        )],
+        Redirect::to(next),
+    )
+        .into_response()
+}
+
+pub async fn logout(headers: HeaderMap) -> Response {
+    if let Some(id) = cookie(&headers, SESSION_COOKIE) {
+        let db = DatabaseConnection {
+            provider: RedisProvider,
+        };
+        let _ = db.delete_session(id).await;
+    }
+
+    (
+        [(
+            header::SET_COOKIE,
+            format!("{SESSION_COOKIE}=; Path=/; Max-Age=0; HttpOnly; Secure; SameSite=Strict"),
+        )],
+        Redirect::to("/"),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::mock::MockDatabaseProvider;
+    use crate::utils::auth::Account;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_cookie() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::COOKIE,
+            HeaderValue::from_static("theme=dark; session=abc123"),
+        );
+        assert_eq!(cookie(&headers, SESSION_COOKIE), Some("abc123"));
+        assert!(cookie(&headers, "missing").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_session_user() {
+        let db = DatabaseConnection {
+            provider: MockDatabaseProvider::new(),
+        };
+        let account = Account::new("sam", Role::Editor, "correct horse").unwrap();
+        db.store_account(&account).await.unwrap();
+        db.store_session("abc", &account.user(), 60).await.unwrap();
+        assert_eq!(session_user(&db, "abc").await, Some(account.user()));
+
+        // A change of role applies to the session already signed in
+        let viewer = Account {
+            role: Role::Viewer,
+            ..account
+        };
+        db.store_account(&viewer).await.unwrap();
+        assert_eq!(
+            session_user(&db, "abc").await.map(|user| user.role),
+            Some(Role::Viewer)
+        );
+
+        db.delete_account("sam").await.unwrap();
+        assert!(session_user(&db, "abc").await.is_none());
+        assert!(db.fetch_session("abc").await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_local_path() {
+        assert_eq!(
+            local_path(Some("/admin/gazettes/abc")),
+            "/admin/gazettes/abc"
+        );
+        assert_eq!(local_path(Some("//example.com")), "/admin");
+        assert_eq!(local_path(Some("https://example.com")), "/admin");
+        assert_eq!(local_path(None), "/admin");
+    }
+}
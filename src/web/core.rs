@@ -2,38 +2,103 @@ use crate::db::core::DatabaseProvider;
 use crate::db::redis::RedisProvider;
 use crate::db::DatabaseConnection;
 use crate::geocoder::core::GeocoderProvider;
-use crate::image_service::ImageService;
 use crate::location_parser::core::LocationParserService;
-use crate::utils::geojson::{
-    GeoJsonFeature, GeoJsonFeatureCollection, GeoJsonGeometry, GeoJsonProperties,
-};
+use crate::ocr::core::OcrService;
+use crate::renderer::core::PageRenderer;
+use crate::storage::StorageService;
+use crate::utils::auth::Role;
+use crate::utils::gazette::{make_hash, Gazette};
+use crate::utils::geojson::{GeoJsonFeature, GeoJsonFeatureCollection, GeoJsonProperties};
+use crate::utils::geometry::{display_geometry, CleanupSettings};
+use crate::utils::http::is_web_address;
+use crate::utils::mapimage::ImageVariant;
 use crate::utils::updater::{ServiceConfig, Updater};
+use crate::web::admin::{
+    admin_index, delete_gazette, geometry_editor, reprocess_gazette, save_geometry, trigger_update,
+};
+use crate::web::announcements::unmatched_announcements;
+use crate::web::auth::{login, login_page, logout, require};
+use crate::web::review::{approve_notice, reject_notice, review_notice, review_queue};
+use crate::web::submit::{submit_notice, submit_page, MAX_SUBMISSION_BYTES};
 use crate::web::templates::base::base_template;
-use crate::web::templates::components::{footer_section, header_section, list_section, map_section, notice_section, update_notice};
+use crate::web::templates::components::{
+    footer_section, header_section, list_section, map_section, notice_section, update_notice,
+};
 use crate::web::templates::styles::get_styles;
 use axum::{
     self,
-    extract::State,
+    extract::{DefaultBodyLimit, Path, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
     response::sse::{Event, Sse},
-    routing::get,
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post},
     Router,
 };
 use chrono::{Local, TimeDelta};
 use futures::stream::Stream;
 use maud::{html, Markup, PreEscaped};
 use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::Instant;
 use std::{convert::Infallible, env, time::Duration};
 
-pub async fn start_server<T, U, V, W>(config: ServiceConfig<T, U, V, W>)
+// Visitors keep the list fresh, but an update costs OCR and paid API calls, so they only start
+// one this often; admins can start one whenever from /admin
+const VISITOR_UPDATE_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
+static LAST_VISITOR_UPDATE: Mutex<Option<Instant>> = Mutex::new(None);
+
+pub async fn start_server<T, U, V, W, X, Y>(config: ServiceConfig<T, U, V, W, X, Y>)
 where
     T: DatabaseProvider + Clone + Send + Sync + 'static,
-    U: ImageService + Clone + Copy + Send + Sync + 'static,
+    U: StorageService + Clone + Send + Sync + 'static,
     V: LocationParserService + Clone + Copy + Send + Sync + 'static,
-    W: GeocoderProvider + Clone + Copy + Send + Sync + 'static,
+    W: GeocoderProvider + Clone + Send + Sync + 'static,
+    X: OcrService + Clone + Copy + Send + Sync + 'static,
+    Y: PageRenderer + Clone + Copy + Send + Sync + 'static,
 {
+    // Each group of admin routes is open to its role and the ones above it
+    let viewer_routes = Router::new()
+        .route("/admin", get(admin_index))
+        .route("/admin/gazettes/{hash}", get(geometry_editor))
+        .route("/admin/review", get(review_queue))
+        .route("/admin/review/{hash}", get(review_notice))
+        .route_layer(middleware::from_fn(|request: Request, next: Next| {
+            require(Role::Viewer, request, next)
+        }));
+    let editor_routes = Router::new()
+        .route("/admin/gazettes/{hash}/geometry", post(save_geometry))
+        .route("/admin/gazettes/{hash}/reprocess", post(reprocess_gazette))
+        .route("/admin/review/{hash}/approve", post(approve_notice))
+        .route("/admin/review/{hash}/reject", post(reject_notice))
+        .route(
+            "/admin/submit",
+            get(submit_page)
+                .post(submit_notice)
+                .layer(DefaultBodyLimit::max(MAX_SUBMISSION_BYTES)),
+        )
+        .route_layer(middleware::from_fn(|request: Request, next: Next| {
+            require(Role::Editor, request, next)
+        }));
+    let admin_routes = Router::new()
+        .route("/admin/gazettes/{hash}/delete", post(delete_gazette))
+        .route("/admin/update", post(trigger_update))
+        .route_layer(middleware::from_fn(|request: Request, next: Next| {
+            require(Role::Admin, request, next)
+        }));
+
     let app = Router::new()
         .route("/", get(landing))
         .route("/data", get(list_sse))
+        .route("/announcements", get(unmatched_announcements))
+        .route("/pdf/{hash}", get(archived_pdf))
+        .route("/storage/{*key}", get(stored_blob))
+        .route("/login", get(login_page).post(login))
+        .route("/logout", post(logout))
+        .merge(viewer_routes)
+        .merge(editor_routes)
+        .merge(admin_routes)
         .with_state(config);
 
     let port: u16 = env::var("PORT")
@@ -44,17 +109,25 @@ where
     let address = SocketAddr::from(([0, 0, 0, 0, 0, 0, 0, 0], port));
 
     let listener = tokio::net::TcpListener::bind(&address).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    // Sign-in attempts are limited by the address they come from
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
 }
 
-async fn list_sse<T, U, V, W>(
-    State(state): State<ServiceConfig<T, U, V, W>>,
+async fn list_sse<T, U, V, W, X, Y>(
+    State(state): State<ServiceConfig<T, U, V, W, X, Y>>,
 ) -> Sse<impl Stream<Item = Result<Event, Infallible>>>
 where
     T: DatabaseProvider + Clone + Send + Sync + 'static,
-    U: ImageService + Clone + Copy + Send + Sync + 'static,
+    U: StorageService + Clone + Send + Sync + 'static,
     V: LocationParserService + Clone + Copy + Send + Sync + 'static,
-    W: GeocoderProvider + Clone + Copy + Send + Sync + 'static,
+    W: GeocoderProvider + Clone + Send + Sync + 'static,
+    X: OcrService + Clone + Copy + Send + Sync + 'static,
+    Y: PageRenderer + Clone + Copy + Send + Sync + 'static,
 {
     let updater = Updater {
         uri: "http://www.gazette.vic.gov.au/gazette_bin/gazette_archives.cfm".to_string(),
@@ -62,22 +135,28 @@ where
         config: state.clone(),
     };
 
+    let update_due = LAST_VISITOR_UPDATE
+        .lock()
+        .is_ok_and(|mut last| visitor_update_due(&mut last, Instant::now()));
+
     let (tx, rx) = tokio::sync::mpsc::channel(32);
     let stream = tokio_stream::wrappers::ReceiverStream::new(rx);
 
     tokio::spawn(async move {
-        let update_task = tokio::spawn(async move {
-            let _ = updater.update().await;
-        });
+        if update_due {
+            let update_task = tokio::spawn(async move {
+                let _ = updater.update().await;
+            });
 
-        while !update_task.is_finished() {
-            let _ = tx
-                .send(Ok(Event::default().data("updating...").event("heartbeat")))
-                .await;
-            tokio::time::sleep(Duration::from_secs(1)).await;
-        }
+            while !update_task.is_finished() {
+                let _ = tx
+                    .send(Ok(Event::default().data("updating...").event("heartbeat")))
+                    .await;
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
 
-        let _ = update_task.await;
+            let _ = update_task.await;
+        }
 
         let data_future = render_list();
         let polygons_future = fetch_polygons();
@@ -99,6 +178,89 @@ where
     )
 }
 
+// Claims the next visitor update when the last one started long enough ago
+fn visitor_update_due(last: &mut Option<Instant>, now: Instant) -> bool {
+    if last.is_some_and(|started| now.duration_since(started) < VISITOR_UPDATE_INTERVAL) {
+        return false;
+    }
+    *last = Some(now);
+    true
+}
+
+// Without OBJECT_STORAGE_URL, stored files are served by the app itself under /storage/
+pub(crate) fn storage_base_uri() -> String {
+    env::var("OBJECT_STORAGE_URL")
+        .ok()
+        .filter(|uri| !uri.trim().is_empty())
+        .unwrap_or_else(|| "/storage/".to_string())
+}
+
+// Backends with a public URL configured store absolute URLs, which are used as-is
+pub(crate) fn stored_uri(base_uri: &str, uri: &str) -> String {
+    if uri.contains("://") {
+        uri.to_string()
+    } else {
+        format!("{base_uri}{uri}")
+    }
+}
+
+async fn stored_blob<T, U, V, W, X, Y>(
+    State(state): State<ServiceConfig<T, U, V, W, X, Y>>,
+    Path(key): Path<String>,
+) -> Response
+where
+    T: DatabaseProvider + Clone + Send + Sync + 'static,
+    U: StorageService + Clone + Send + Sync + 'static,
+    V: LocationParserService + Clone + Copy + Send + Sync + 'static,
+    W: GeocoderProvider + Clone + Send + Sync + 'static,
+    X: OcrService + Clone + Copy + Send + Sync + 'static,
+    Y: PageRenderer + Clone + Copy + Send + Sync + 'static,
+{
+    match state.storage_service.get(&key).await {
+        Ok(Some(blob)) => {
+            let mut response =
+                ([(header::CONTENT_TYPE, blob.content_type)], blob.data).into_response();
+            if let Some(cache_control) = blob.cache_control.and_then(|value| value.parse().ok()) {
+                response
+                    .headers_mut()
+                    .insert(header::CACHE_CONTROL, cache_control);
+            }
+            response
+        }
+        _ => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+// Sends people to our archived copy whenever there is one, and to the gazette website only for
+// notices processed before they were archived
+async fn archived_pdf(Path(hash): Path<String>) -> Response {
+    let db = DatabaseConnection {
+        provider: RedisProvider,
+    };
+    let base_uri = storage_base_uri();
+
+    let gazette = match db.fetch_entry(&hash).await {
+        Ok(Some(gazette)) => gazette,
+        Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+        Err(_) => return StatusCode::SERVICE_UNAVAILABLE.into_response(),
+    };
+
+    match &gazette.archive {
+        Some(archive) => Redirect::temporary(&stored_uri(&base_uri, &archive.uri)).into_response(),
+        None if is_web_address(&gazette.uri) => Redirect::temporary(&gazette.uri).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+// Addresses given with a notice added by hand are only linked to if they're web addresses
+fn document_link(gazette: &Gazette) -> String {
+    if gazette.archive.is_none() && is_web_address(&gazette.uri) {
+        gazette.uri.clone()
+    } else {
+        format!("/pdf/{}", make_hash(&gazette.uri))
+    }
+}
+
 async fn initial_list() -> Markup {
     html! {
         (PreEscaped(render_list().await))
@@ -109,13 +271,14 @@ async fn fetch_polygons() -> String {
     let db = DatabaseConnection {
         provider: RedisProvider,
     };
-    let base_uri = env::var("OBJECT_STORAGE_URL").unwrap_or_default();
+    let base_uri = storage_base_uri();
     if let Ok(gazettes) = db.fetch_entries().await {
         let mut feature_collection = GeoJsonFeatureCollection::new();
         let tomorrow = Local::now().date_naive() + TimeDelta::days(1);
 
-        for gazette in gazettes {
-            if let Some(polygon) = &gazette.polygon {
+        // Notices waiting for review, or turned down, aren't shown
+        for gazette in gazettes.into_iter().filter(|gz| gz.review.is_public()) {
+            if gazette.polygon.is_some() || gazette.geometry_override.is_some() {
                 if let Some(end_date) = &gazette.end {
                     if end_date < &tomorrow {
                         continue;
@@ -125,6 +288,15 @@ async fn fetch_polygons() -> String {
                     continue;
                 }
 
+                // Notices processed before the display geometry was stored get it worked out here
+                let display = gazette
+                    .display
+                    .clone()
+                    .or_else(|| display_geometry(&gazette, &CleanupSettings::from_env()));
+                let Some(display) = display else {
+                    continue;
+                };
+
                 let mut start = String::new();
                 let mut end = String::new();
                 let mut img_uri = None;
@@ -138,39 +310,22 @@ async fn fetch_polygons() -> String {
                 }
 
                 if let Some(img) = &gazette.img_uri {
-                    img_uri = Some(format!("{base_uri}{img}"));
+                    img_uri = Some(stored_uri(&base_uri, img));
                 }
 
-                let processed_polygon = polygon
-                    .clone()
-                    .remove_isolated_points(5.0, 2)
-                    .remove_identical_points()
-                    .clone();
-
-                let geometry = if processed_polygon.data.len() < 3 {
-                    GeoJsonGeometry::Point {
-                        coordinates: processed_polygon.centre().into(),
-                    }
-                } else {
-                    GeoJsonGeometry::Polygon {
-                        coordinates: vec![processed_polygon
-                            .convex_hull()
-                            .data
-                            .iter()
-                            .map(|point| [point.longitude, point.latitude])
-                            .collect::<Vec<[f64; 2]>>()],
-                    }
-                };
-
+                let document_uri = document_link(&gazette);
                 let feature = GeoJsonFeature {
                     type_field: "Feature".to_string(),
-                    geometry,
+                    geometry: (&display.shape).into(),
                     properties: GeoJsonProperties {
                         title: gazette.title,
+                        document_uri,
                         uri: gazette.uri,
                         img_uri,
                         start,
                         end,
+                        area: display.area,
+                        gazetted: gazette.source.is_gazetted(),
                     },
                 };
 
@@ -187,22 +342,30 @@ async fn render_list() -> String {
     let db = DatabaseConnection {
         provider: RedisProvider,
     };
-    let base_uri = env::var("OBJECT_STORAGE_URL").unwrap_or_default();
+    let base_uri = storage_base_uri();
     if let Ok(gazettes) = db.fetch_entries().await {
-        let acc = gazettes.iter().fold(String::new(), |mut acc, gz| {
+        let acc = gazettes.iter().filter(|gz| gz.review.is_public()).fold(String::new(), |mut acc, gz| {
             let (title, published) = gz.title
                 .as_deref()
                 .and_then(|title| title.split_once(" Dated ")).unwrap_or(("Failed to parse Title", "Failed to parse published date"));
+            // Notices added by hand have whatever title they were given, and no publication date
+            let title = if gz.source.is_gazetted() { title } else { gz.title.as_deref().unwrap_or(&gz.uri) };
             acc += html!(
                 li {
                     div {
                         span {
-                            a href=(gz.uri) target="_blank" {
+                            a href=(document_link(gz)) target="_blank" {
                                 span.title {
                                     (title)
                                 }
-                                span.published {
-                                    "Published " (published)
+                                @if gz.source.is_gazetted() {
+                                    span.published {
+                                        "Published " (published)
+                                    }
+                                } @else {
+                                    span.unofficial {
+                                        "Not published in the Government Gazette"
+                                    }
                                 }
                                 @if let (Some(start_date), Some(end_date)) = (&gz.start, &gz.end) {
                                     span.time {
@@ -212,13 +375,25 @@ async fn render_list() -> String {
                                 span.uri {
                                     (gz.uri)
                                 }
+                                @if let Some(archive) = &gz.archive {
+                                    span.archive {
+                                        "Archived copy SHA-256 " (archive.sha256)
+                                    }
+                                }
                             }
                         }
                     }
                     div.thumbnail {
                         @if let Some(img_uri) = &gz.img_uri {
-                            a href=(&gz.uri) target="_blank" {
-                                img src=(format!("{}{}", &base_uri, &img_uri)) {}
+                            a href=(document_link(gz)) target="_blank" {
+                                @if let Some(smallest) = gz.img_variants.iter().min_by_key(|variant| variant.width) {
+                                    img src=(stored_uri(&base_uri, &smallest.uri))
+                                        srcset=(srcset(&gz.img_variants, &base_uri))
+                                        sizes=(THUMBNAIL_SIZES)
+                                        loading="lazy" {}
+                                } @else {
+                                    img src=(stored_uri(&base_uri, img_uri)) {}
+                                }
                             }
                         }
                     }
@@ -234,6 +409,17 @@ async fn render_list() -> String {
     "Failed to fetch data".to_string()
 }
 
+// Matches the thumbnail dimensions in main.css
+const THUMBNAIL_SIZES: &str = "(max-width: 430px) 100vw, 128px";
+
+fn srcset(variants: &[ImageVariant], base_uri: &str) -> String {
+    variants
+        .iter()
+        .map(|variant| format!("{} {}w", stored_uri(base_uri, &variant.uri), variant.width))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
 async fn landing() -> Markup {
     let initial_polygons = fetch_polygons().await;
     let initial_list_content = initial_list().await;
@@ -266,3 +452,25 @@ fn map_javascript(initial_polygons: &str) -> Markup {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visitor_update_due() {
+        let start = Instant::now();
+        let mut last = None;
+
+        assert!(visitor_update_due(&mut last, start));
+        assert!(!visitor_update_due(
+            &mut last,
+            start + Duration::from_secs(60)
+        ));
+        assert!(visitor_update_due(
+            &mut last,
+            start + VISITOR_UPDATE_INTERVAL
+        ));
+        assert_eq!(last, Some(start + VISITOR_UPDATE_INTERVAL));
+    }
+}
@@ -0,0 +1,98 @@
+let corners = [];
+let outline = L.polygon([], {color: '#ff0000', weight: 2, opacity: 0.65}).addTo(map);
+
+if(referenceShape){
+    const reference = L.geoJSON(referenceShape, {
+        pointToLayer: (feature, latlng) => {
+            return new L.Circle(latlng, {radius: 500});
+        },
+        style: {color: '#444444', weight: 2, dashArray: '6 6', fill: false}
+    }).addTo(map);
+    map.fitBounds(reference.getBounds(), {maxZoom: 16});
+}
+
+function redrawOutline() {
+    outline.setLatLngs(corners.map((corner) => corner.getLatLng()));
+}
+
+function addCorner(latlng, index) {
+    const corner = L.marker(latlng, {draggable: canEdit}).addTo(map);
+    corner.on('drag', redrawOutline);
+    corner.on('contextmenu', function() {
+        if(!canEdit){
+            return;
+        }
+        corner.remove();
+        corners = corners.filter((other) => other !== corner);
+        redrawOutline();
+    });
+    corners.splice(index, 0, corner);
+    redrawOutline();
+}
+
+// New corners go into whichever side of the shape they're closest to
+function insertionIndex(latlng) {
+    if(corners.length < 3){
+        return corners.length;
+    }
+    const point = map.latLngToLayerPoint(latlng);
+    let best = corners.length;
+    let bestDistance = Infinity;
+    corners.forEach(function(corner, index) {
+        const next = corners[(index + 1) % corners.length];
+        const distance = L.LineUtil.pointToSegmentDistance(
+            point,
+            map.latLngToLayerPoint(corner.getLatLng()),
+            map.latLngToLayerPoint(next.getLatLng())
+        );
+        if(distance < bestDistance){
+            bestDistance = distance;
+            best = index + 1;
+        }
+    });
+    return best;
+}
+
+editingRing.forEach(function([longitude, latitude]) {
+    addCorner(L.latLng(latitude, longitude), corners.length);
+});
+if(corners.length > 0){
+    map.fitBounds(outline.getBounds(), {maxZoom: 16});
+}
+
+map.on('click', function(e) {
+    if(canEdit){
+        addCorner(e.latlng, insertionIndex(e.latlng));
+    }
+});
+
+function saveGeometry(ring) {
+    fetch(saveUri, {
+        method: 'POST',
+        headers: {'Content-Type': 'application/json'},
+        body: JSON.stringify({ring: ring, note: document.getElementById('note').value})
+    }).then(function(response) {
+        if(response.ok){
+            window.location.reload();
+        } else {
+            response.text().then((message) => alert(message || `Could not save (${response.status})`));
+        }
+    });
+}
+
+const saveButton = document.getElementById('save');
+if(saveButton){
+    saveButton.addEventListener('click', function() {
+        saveGeometry(corners.map(function(corner) {
+            const latlng = corner.getLatLng();
+            return {latitude: latlng.lat, longitude: latlng.lng};
+        }));
+    });
+}
+
+const clearButton = document.getElementById('clear');
+if(clearButton){
+    clearButton.addEventListener('click', function() {
+        saveGeometry(null);
+    });
+}
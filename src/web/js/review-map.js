@@ -0,0 +1,19 @@
+if(proposedShape){
+    const shape = L.geoJSON(proposedShape, {
+        pointToLayer: (feature, latlng) => {
+            return new L.Circle(latlng, {radius: 500});
+        },
+        style: {color: '#ff0000', weight: 2, opacity: 0.65}
+    }).addTo(map);
+    map.fitBounds(shape.getBounds(), {maxZoom: 16});
+}
+
+// Places left out of the shape are grey
+parsedPoints.forEach(function(point) {
+    const label = document.createElement('span');
+    label.textContent = point.input;
+    L.circleMarker([point.latitude, point.longitude], {
+        radius: 5,
+        color: point.unused ? '#888888' : '#0051ff'
+    }).bindPopup(label).addTo(map);
+});
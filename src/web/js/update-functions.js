@@ -1,5 +1,32 @@
 const MS_PER_DAY = 86400000;
 
+// Popups are built from text nodes, since notices added by hand have free text titles
+function popupParagraph(popup) {
+    const paragraph = document.createElement('p');
+    popup.appendChild(paragraph);
+    return paragraph;
+}
+
+function addLabelled(parent, label, value) {
+    const strong = document.createElement('strong');
+    strong.textContent = label;
+    parent.appendChild(strong);
+    parent.appendChild(document.createTextNode(` ${value}`));
+}
+
+// Only web addresses are linked, relative ones included
+function webAddress(uri) {
+    if(!uri){
+        return null;
+    }
+    try {
+        const url = new URL(uri, window.location.href);
+        return url.protocol === 'http:' || url.protocol === 'https:' ? url.href : null;
+    } catch {
+        return null;
+    }
+}
+
 function updatePolygons(geojsonData) {
     currentPolygons.forEach(function(polygon) {
         polygon.remove();
@@ -17,6 +44,9 @@ function updatePolygons(geojsonData) {
             let date = Date.now()
             let falloff = date - MS_PER_DAY * 30;
 
+            // Notices that were never gazetted are dashed, whatever their dates
+            let dashArray = feature.properties.gazetted ? null : '6 6';
+
             if(beyond < date){
                 return
             // }
@@ -30,13 +60,15 @@ function updatePolygons(geojsonData) {
                 return {
                     color: '#ff0000',
                     weight: 2,
-                    opacity: 0.65
+                    opacity: 0.65,
+                    dashArray: dashArray
                 }
             }
             return {
                 color: '#ffb516',
                 weight: 2,
-                opacity: 0.25
+                opacity: 0.25,
+                dashArray: dashArray
             };
         },
         onEachFeature: function(feature, layer) {
@@ -46,22 +78,61 @@ function updatePolygons(geojsonData) {
             const end_string = `${end_date.toLocaleDateString('en-AU', { dateStyle: 'full' })}`;
             const duration = `${Math.floor((end_date - start_date + MS_PER_DAY) / MS_PER_DAY)} day(s)`;
             const time_until_start = Math.floor((start_date - Date.now()) / MS_PER_DAY);
-            let start_notice = '';
+            const area = feature.properties.area;
+            const popupContent = document.createElement('div');
+            popupContent.className = 'custom-popup';
+
+            let [title, posted] = (feature.properties.title || '').split(" Dated ", 2);
+            const heading = popupParagraph(popupContent);
+            const titleText = document.createElement('strong');
+            heading.appendChild(titleText);
+            heading.appendChild(document.createElement('br'));
+            if(feature.properties.gazetted){
+                titleText.textContent = title;
+                heading.appendChild(document.createTextNode(`Published ${posted}`));
+            } else {
+                titleText.textContent = feature.properties.title || feature.properties.uri;
+                const unofficial = document.createElement('strong');
+                unofficial.textContent = 'Not published in the Government Gazette';
+                heading.appendChild(unofficial);
+            }
+
+            const dates = popupParagraph(popupContent);
+            addLabelled(dates, 'Begins:', start_string);
+            dates.appendChild(document.createElement('br'));
+            addLabelled(dates, 'Ends:', end_string);
+
+            const details = popupParagraph(popupContent);
             if(time_until_start > 0){
-                start_notice = `<strong>Days until start:</strong> ${time_until_start}<br />`
+                addLabelled(details, 'Days until start:', time_until_start);
+                details.appendChild(document.createElement('br'));
+            }
+            addLabelled(details, 'Duration:', duration);
+            if(area){
+                details.appendChild(document.createElement('br'));
+                addLabelled(details, 'Area:', area >= 1000000
+                    ? `${(area / 1000000).toFixed(2)} km²`
+                    : `${(area / 10000).toFixed(1)} ha`);
+            }
+
+            const documentUri = webAddress(feature.properties.document_uri);
+            if(documentUri){
+                const link = document.createElement('a');
+                link.href = documentUri;
+                link.target = '_blank';
+                link.textContent = 'View Details';
+                popupParagraph(popupContent).appendChild(link);
+            }
+
+            const imgUri = webAddress(feature.properties.img_uri);
+            if(imgUri){
+                const img = document.createElement('img');
+                img.alt = feature.properties.title || '';
+                img.src = imgUri;
+                img.style.maxWidth = '200px';
+                popupContent.appendChild(img);
             }
-            const [title, posted] = feature.properties.title.split(" Dated ", 2);
-            const popupContent = `
-                                    <div class='custom-popup'>
-                                        <p><strong>${title}</strong><br />Published ${posted}</p>
-                                        <p><strong>Begins:</strong> ${start_string}<br/><strong>Ends:</strong> ${end_string}</p>
-                                        <p>${start_notice}<strong>Duration:</strong> ${duration}</p>
-                                        <p><a href='${feature.properties.uri}' target='_blank'>View Details</a></p>
-                                        ${feature.properties.img_uri ?
-                                        `<img alt='${feature.properties.title}' src='${feature.properties.img_uri}' style='max-width: 200px;'>`
-                                            : ''}
-                                        </div>
-                                    `;
+
             layer.bindPopup(popupContent);
             currentPolygons.push(layer);
         }
@@ -1,4 +1,9 @@
+mod admin;
+mod announcements;
+mod auth;
 pub mod core;
+mod review;
+mod submit;
 mod templates;
 
 pub use core::start_server;
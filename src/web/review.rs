@@ -0,0 +1,235 @@
+use crate::db::redis::RedisProvider;
+use crate::db::DatabaseConnection;
+use crate::utils::auth::{Role, User};
+use crate::utils::gazette::{make_hash, Gazette, Review};
+use crate::utils::geojson::GeoJsonGeometry;
+use crate::web::admin::{find_gazette, save_gazette, signed_in_as};
+use crate::web::core::{storage_base_uri, stored_uri};
+use crate::web::templates::base::base_template;
+use crate::web::templates::styles::get_styles;
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+    Extension,
+};
+use chrono::Utc;
+use maud::{html, Markup, PreEscaped};
+use serde::Serialize;
+
+// A parsed location as the review map draws it
+#[derive(Serialize)]
+struct ParsedPoint<'a> {
+    input: &'a str,
+    latitude: f64,
+    longitude: f64,
+    // Dropped by the cleanup, or only found as a suburb
+    unused: bool,
+}
+
+// JSON placed inside a script element can't be allowed to close it
+fn script_json<T: Serialize>(value: &T) -> String {
+    serde_json::to_string(value)
+        .unwrap_or_else(|_| "null".to_string())
+        .replace('<', "\\u003c")
+}
+
+fn review_list(gazettes: &[&Gazette]) -> Markup {
+    html! {
+        ul.admin {
+            @for gz in gazettes {
+                li {
+                    a href=(format!("/admin/review/{}", make_hash(&gz.uri))) {
+                        span.title { (gz.title.as_deref().unwrap_or(&gz.uri)) }
+                        @if let (Some(start), Some(end)) = (&gz.start, &gz.end) {
+                            span.time {
+                                "Enforced from " (start.format("%A %e %B %Y")) " to " (end.format("%A %e %B %Y"))
+                            }
+                        }
+                        @if let Review::Rejected { by, .. } = &gz.review {
+                            span.published { "Turned down by " (by) }
+                        }
+                    }
+                }
+            }
+            @if gazettes.is_empty() {
+                li { "Nothing here" }
+            }
+        }
+    }
+}
+
+pub async fn review_queue(Extension(user): Extension<User>) -> Response {
+    let db = DatabaseConnection {
+        provider: RedisProvider,
+    };
+    let Ok(gazettes) = db.fetch_entries().await else {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    };
+    let pending: Vec<_> = gazettes
+        .iter()
+        .filter(|gz| gz.review == Review::Pending)
+        .collect();
+    let rejected: Vec<_> = gazettes
+        .iter()
+        .filter(|gz| matches!(gz.review, Review::Rejected { .. }))
+        .collect();
+
+    base_template(&html! {
+        div.center {
+            (signed_in_as(&user))
+            span.heading { "Waiting for review" }
+            (review_list(&pending))
+            span.heading { "Turned down" }
+            (review_list(&rejected))
+        }
+        (get_styles())
+    })
+    .into_response()
+}
+
+pub async fn review_notice(Extension(user): Extension<User>, Path(hash): Path<String>) -> Response {
+    let gazette = match find_gazette(&hash).await {
+        Ok(gazette) => gazette,
+        Err(status) => return status.into_response(),
+    };
+    let rejected = matches!(gazette.review, Review::Rejected { .. });
+    // Read from the PDF each time rather than kept, unless it had to be scanned
+    let text = gazette.get_doc_text().await.ok();
+
+    let removed = gazette
+        .display
+        .as_ref()
+        .map(|display| display.removed.as_slice())
+        .unwrap_or_default();
+    let points: Vec<_> = gazette
+        .geocoded
+        .iter()
+        .map(|point| ParsedPoint {
+            input: &point.input,
+            latitude: point.result.position.latitude,
+            longitude: point.result.position.longitude,
+            unused: point.result.is_locality() || removed.contains(&point.result.position),
+        })
+        .collect();
+    let proposed = gazette
+        .display
+        .as_ref()
+        .map(|display| GeoJsonGeometry::from(&display.shape));
+    let archive = gazette
+        .archive
+        .as_ref()
+        .map(|archive| stored_uri(&storage_base_uri(), &archive.uri));
+
+    base_template(&html! {
+        div.center {
+            (signed_in_as(&user))
+            span.heading { "Review" }
+            span.subheading { (gazette.title.as_deref().unwrap_or(&gazette.uri)) }
+            @if user.role >= Role::Editor {
+                div.editor {
+                    form method="post" action=(format!("/admin/review/{hash}/approve")) {
+                        button type="submit" { "Approve" }
+                    }
+                    a href=(format!("/admin/gazettes/{hash}")) { "Fix the shape" }
+                    @if !rejected {
+                        form method="post" action=(format!("/admin/review/{hash}/reject")) {
+                            button type="submit" { "Turn down" }
+                        }
+                    }
+                }
+            }
+        }
+        div.review {
+            div {
+                @if let Some(archive) = &archive {
+                    iframe.document src=(archive) {}
+                }
+                a href=(format!("/pdf/{hash}")) target="_blank" { "Open the gazette" }
+            }
+            div {
+                div #map {}
+                @if let (Some(start), Some(end)) = (&gazette.start, &gazette.end) {
+                    span.time {
+                        "Enforced from " (start.format("%A %e %B %Y")) " to " (end.format("%A %e %B %Y"))
+                    }
+                } @else {
+                    span.time { "No dates found" }
+                }
+                table.locations {
+                    tr { th { "Location" } th { "Found" } th { "Match" } th { "Confidence" } }
+                    @for point in &gazette.geocoded {
+                        tr {
+                            td { (point.input) }
+                            td { (point.result.formatted_address.as_deref().unwrap_or("")) }
+                            td { (format!("{:?}", point.result.match_type)) }
+                            td { (format!("{:.2}", point.result.confidence)) }
+                        }
+                    }
+                }
+                pre.text {
+                    (text.as_deref().unwrap_or("Could not read the gazette's text"))
+                }
+            }
+        }
+        (get_styles())
+        script {
+            (PreEscaped(include_str!("js/map-init.js")))
+            (PreEscaped(format!(
+                "let proposedShape = {};
+                let parsedPoints = {};",
+                script_json(&proposed),
+                script_json(&points)
+            )))
+            (PreEscaped(include_str!("js/review-map.js")))
+        }
+    })
+    .into_response()
+}
+
+async fn decide(hash: &str, review: Review) -> Response {
+    let mut gazette = match find_gazette(hash).await {
+        Ok(gazette) => gazette,
+        Err(status) => return status.into_response(),
+    };
+
+    gazette.review = review;
+    match save_gazette(&gazette).await {
+        Ok(()) => Redirect::to("/admin/review").into_response(),
+        Err(status) => status.into_response(),
+    }
+}
+
+pub async fn approve_notice(
+    Extension(user): Extension<User>,
+    Path(hash): Path<String>,
+) -> Response {
+    let review = Review::Approved {
+        by: user.name,
+        at: Utc::now().timestamp(),
+    };
+    decide(&hash, review).await
+}
+
+pub async fn reject_notice(Extension(user): Extension<User>, Path(hash): Path<String>) -> Response {
+    let review = Review::Rejected {
+        by: user.name,
+        at: Utc::now().timestamp(),
+    };
+    decide(&hash, review).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_json() {
+        let json = script_json(&"</script><script>alert(1)</script>");
+        assert!(!json.contains('<'));
+        assert_eq!(
+            serde_json::from_str::<String>(&json).unwrap(),
+            "</script><script>alert(1)</script>"
+        );
+    }
+}
@@ -0,0 +1,244 @@
+use crate::db::core::DatabaseProvider;
+use crate::db::DatabaseConnection;
+use crate::geocoder::core::GeocoderProvider;
+use crate::location_parser::core::LocationParserService;
+use crate::ocr::core::OcrService;
+use crate::renderer::core::PageRenderer;
+use crate::storage::StorageService;
+use crate::utils::auth::User;
+use crate::utils::gazette::{make_hash, Gazette, NoticeSource, PdfBytes, Review};
+use crate::utils::http::{flush_api_calls, is_web_address};
+use crate::utils::updater::ServiceConfig;
+use crate::web::admin::signed_in_as;
+use crate::web::templates::base::base_template;
+use crate::web::templates::styles::get_styles;
+use axum::{
+    extract::{Multipart, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+    Extension,
+};
+use chrono::{NaiveDate, Utc};
+use maud::{html, Markup};
+use std::sync::Arc;
+
+// Well over the size of any gazette so far
+pub const MAX_SUBMISSION_BYTES: usize = 50 * 1024 * 1024;
+
+// What the form sends; every field but the source is optional, though it needs a PDF or text
+#[derive(Debug, Default)]
+struct Submission {
+    source_uri: String,
+    title: Option<String>,
+    start: Option<String>,
+    end: Option<String>,
+    text: Option<String>,
+    pdf: Option<Vec<u8>>,
+}
+
+impl Submission {
+    async fn read(mut multipart: Multipart) -> Result<Self, String> {
+        let mut submission = Self::default();
+
+        while let Some(field) = multipart.next_field().await.map_err(|e| e.to_string())? {
+            let name = field.name().unwrap_or_default().to_string();
+            if name == "pdf" {
+                let data = field.bytes().await.map_err(|e| e.to_string())?;
+                submission.pdf = Some(data.to_vec()).filter(|data| !data.is_empty());
+                continue;
+            }
+
+            let value = field.text().await.map_err(|e| e.to_string())?;
+            let value = Some(value.trim().to_string()).filter(|value| !value.is_empty());
+            match name.as_str() {
+                "source_uri" => submission.source_uri = value.unwrap_or_default(),
+                "title" => submission.title = value,
+                "start" => submission.start = value,
+                "end" => submission.end = value,
+                "text" => submission.text = value,
+                _ => {}
+            }
+        }
+
+        Ok(submission)
+    }
+
+    fn into_gazette(self, submitted_by: &str) -> Result<Gazette, &'static str> {
+        if !is_web_address(&self.source_uri) {
+            return Err("The source needs to be a web address");
+        }
+        // The source is usually a police web page, which can't be read as the notice itself
+        if self.pdf.is_none() && self.text.is_none() {
+            return Err("Attach the notice's PDF or paste its text");
+        }
+        if self
+            .pdf
+            .as_ref()
+            .is_some_and(|pdf| !pdf.starts_with(b"%PDF"))
+        {
+            return Err("That file isn't a PDF");
+        }
+
+        let date = |value: Option<String>| {
+            value
+                .map(|value| NaiveDate::parse_from_str(&value, "%Y-%m-%d"))
+                .transpose()
+                .map_err(|_| "Dates need to be written as YYYY-MM-DD")
+        };
+        let (start, end) = (date(self.start)?, date(self.end)?);
+        if let (Some(start), Some(end)) = (start, end) {
+            if end < start {
+                return Err("The notice can't end before it starts");
+            }
+        }
+
+        Ok(Gazette {
+            uri: self.source_uri,
+            title: self.title,
+            flagged: true,
+            start,
+            end,
+            // Taken as it is, the same as text read from a scan
+            ocr_text: self.text,
+            review: Review::for_new_notice(),
+            source: NoticeSource::NonGazetted {
+                submitted_by: submitted_by.to_string(),
+                submitted_at: Utc::now().timestamp(),
+            },
+            pdf: self.pdf.map(|pdf| PdfBytes(Arc::new(pdf))),
+            ..Default::default()
+        })
+    }
+}
+
+fn submit_template(user: &User, error: Option<&str>) -> Markup {
+    base_template(&html! {
+        div.center {
+            (signed_in_as(user))
+            span.heading { "Add a notice" }
+            span.subheading { "For declarations made without being published in the Government Gazette" }
+            @if let Some(error) = error {
+                span.update { (error) }
+            }
+            form.login method="post" action="/admin/submit" enctype="multipart/form-data" {
+                input type="url" name="source_uri" placeholder="Where it was announced" required;
+                input type="text" name="title" placeholder="Title";
+                label { "Starts " input type="date" name="start"; }
+                label { "Ends " input type="date" name="end"; }
+                label { "PDF of the notice " input type="file" name="pdf" accept="application/pdf"; }
+                textarea name="text" rows="10" placeholder="Or the text of the notice, which is used instead of the PDF's" {}
+                button type="submit" { "Add notice" }
+            }
+        }
+        (get_styles())
+    })
+}
+
+pub async fn submit_page(Extension(user): Extension<User>) -> Markup {
+    submit_template(&user, None)
+}
+
+// Goes through the same steps as a notice found in the gazette, which can take a while
+pub async fn submit_notice<T, U, V, W, X, Y>(
+    State(state): State<ServiceConfig<T, U, V, W, X, Y>>,
+    Extension(user): Extension<User>,
+    multipart: Multipart,
+) -> Response
+where
+    T: DatabaseProvider + Clone + Send + Sync + 'static,
+    U: StorageService + Clone + Send + Sync + 'static,
+    V: LocationParserService + Clone + Copy + Send + Sync + 'static,
+    W: GeocoderProvider + Clone + Send + Sync + 'static,
+    X: OcrService + Clone + Copy + Send + Sync + 'static,
+    Y: PageRenderer + Clone + Copy + Send + Sync + 'static,
+{
+    let rejected = |error: &str| {
+        (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            submit_template(&user, Some(error)),
+        )
+            .into_response()
+    };
+    let gazette = match Submission::read(multipart).await {
+        Ok(submission) => submission.into_gazette(&user.name),
+        Err(error) => return rejected(&error),
+    };
+    let gazette = match gazette {
+        Ok(gazette) => gazette,
+        Err(error) => return rejected(error),
+    };
+
+    let hash = make_hash(&gazette.uri);
+    let db = DatabaseConnection {
+        provider: state.database_provider.clone(),
+    };
+    if db.has_entry(&hash).await.unwrap_or(false) {
+        return rejected("There's already a notice from that address");
+    }
+
+    let processed = state.process(gazette).await;
+    if let Err(e) = flush_api_calls(&db).await {
+        eprintln!("{e}");
+    }
+
+    match processed {
+        Ok(_) => Redirect::to(&format!("/admin/gazettes/{hash}")).into_response(),
+        Err(_) => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn submission() -> Submission {
+        Submission {
+            source_uri: "https://www.police.vic.gov.au/designated-area".to_string(),
+            start: Some("2025-10-03".to_string()),
+            end: Some("2025-10-05".to_string()),
+            text: Some("Flinders Street and Swanston Street, Melbourne".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_into_gazette() {
+        let gazette = submission().into_gazette("sam").unwrap();
+        assert!(gazette.flagged);
+        assert!(!gazette.source.is_gazetted());
+        assert_eq!(gazette.start, NaiveDate::from_ymd_opt(2025, 10, 3));
+        assert!(gazette.ocr_text.is_some());
+        assert!(gazette.pdf.is_none());
+
+        let invalid = [
+            Submission {
+                source_uri: "police website".to_string(),
+                ..submission()
+            },
+            Submission {
+                source_uri: "javascript:alert(1)//https://www.police.vic.gov.au".to_string(),
+                ..submission()
+            },
+            Submission {
+                pdf: Some(b"<html>".to_vec()),
+                ..submission()
+            },
+            Submission {
+                end: Some("3 October".to_string()),
+                ..submission()
+            },
+            Submission {
+                end: Some("2025-10-01".to_string()),
+                ..submission()
+            },
+            Submission {
+                text: None,
+                pdf: None,
+                ..submission()
+            },
+        ];
+        for submission in invalid {
+            assert!(submission.into_gazette("sam").is_err());
+        }
+    }
+}
@@ -20,7 +20,10 @@ pub fn update_notice() -> Markup {
             a href="https://content.legislation.vic.gov.au/sites/default/files/2025-08/90-24aa076-authorised.pdf" alt="Current authorised version of the Control of Weapons Act 1990" target="_blank" {
                 "section 10D(4)(a) of the Control of Weapons Act 1990"
             }
-            ". Victoria Police continue to have a presence and attempt to enforce the act in these areas. This is unlawful. The act can only come into effect during the period specified in the notice after its publication in the Government Gazette (under section 10D(6)). I have submitted FOI requests and questions to the police commissioner but have yet to receive a response."
+            ". Victoria Police continue to have a presence and attempt to enforce the act in these areas. This is unlawful. The act can only come into effect during the period specified in the notice after its publication in the Government Gazette (under section 10D(6)). I have submitted FOI requests and questions to the police commissioner but have yet to receive a response. "
+            a href="/announcements" {
+                "See the announcements we couldn't find a gazette for"
+            }
         }
     }
 }